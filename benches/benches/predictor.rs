@@ -0,0 +1,68 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use vaporetto::{Predictor, Sentence};
+
+use benches::{features, inputs, load_model};
+
+fn total_bytes(texts: &[String]) -> u64 {
+    texts.iter().map(|text| text.len() as u64).sum()
+}
+
+fn bench_update_raw(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("update_raw/{}", features()));
+    for (name, texts) in inputs() {
+        group.throughput(Throughput::Bytes(total_bytes(&texts)));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &texts, |b, texts| {
+            let mut s = Sentence::default();
+            b.iter(|| {
+                for text in texts {
+                    s.update_raw(text.as_str()).unwrap();
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_predict(c: &mut Criterion) {
+    let predictor = Predictor::new(load_model(), false).unwrap();
+    let mut group = c.benchmark_group(format!("predict/{}", features()));
+    for (name, texts) in inputs() {
+        group.throughput(Throughput::Bytes(total_bytes(&texts)));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &texts, |b, texts| {
+            let mut s = Sentence::default();
+            b.iter(|| {
+                for text in texts {
+                    s.update_raw(text.as_str()).unwrap();
+                    predictor.predict(&mut s);
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "tag-prediction")]
+fn bench_predict_tags(c: &mut Criterion) {
+    let predictor = Predictor::new(load_model(), true).unwrap();
+    let mut group = c.benchmark_group(format!("predict_tags/{}", features()));
+    for (name, texts) in inputs() {
+        group.throughput(Throughput::Bytes(total_bytes(&texts)));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &texts, |b, texts| {
+            let mut s = Sentence::default();
+            b.iter(|| {
+                for text in texts {
+                    s.update_raw(text.as_str()).unwrap();
+                    predictor.predict(&mut s);
+                    s.fill_tags();
+                }
+            });
+        });
+    }
+    group.finish();
+}
+
+#[cfg(not(feature = "tag-prediction"))]
+criterion_group!(benches, bench_update_raw, bench_predict);
+#[cfg(feature = "tag-prediction")]
+criterion_group!(benches, bench_update_raw, bench_predict, bench_predict_tags);
+criterion_main!(benches);
@@ -0,0 +1,89 @@
+//! Representative inputs for benchmarks of Vaporetto.
+
+use std::fs::File;
+
+use vaporetto::Model;
+
+/// Path to the model used for benchmarks, relative to this crate.
+pub const MODEL_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../resources/model.bin");
+
+const SENTENCES: &[&str] = &[
+    "まぁ社長は火星猫だ",
+    "まぁ良いだろう",
+    "東京特許許可局で記者会見が行われた。",
+    "吾輩は猫である。名前はまだ無い。",
+    "どこで生れたかとんと見当がつかぬ。",
+    "何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。",
+];
+
+const QUERIES: &[&str] = &[
+    "火星猫",
+    "社長",
+    "まぁ良い",
+    "特許",
+    "記者会見",
+    "吾輩",
+    "名前",
+    "見当",
+];
+
+const KATAKANA: &[&str] = &[
+    "コンピュータサイエンス",
+    "アルゴリズムとデータストラクチャ",
+    "マルチスレッドプログラミング",
+    "ニューラルネットワークアーキテクチャ",
+    "インターネットプロトコルスイート",
+];
+
+const MIXED: &[&str] = &[
+    "Vaporetto 0.6.5をRust 1.88でビルドした。",
+    "詳細はhttps://github.com/daac-tools/vaporettoを参照。",
+    "CPU使用率が95%を超えたらalertを出す",
+    "iPhone15とPixel 8の比較レビュー",
+    "2024年3月14日 15:30 JST",
+];
+
+/// Returns names and texts of representative inputs: short queries, a long document,
+/// katakana-heavy text, and text mixed with ASCII characters.
+pub fn inputs() -> Vec<(&'static str, Vec<String>)> {
+    let long_document = SENTENCES.iter().cycle().take(300).copied().collect();
+    vec![
+        (
+            "short_queries",
+            QUERIES.iter().map(|s| s.to_string()).collect(),
+        ),
+        ("long_document", vec![long_document]),
+        (
+            "katakana_heavy",
+            KATAKANA.iter().map(|s| s.to_string()).collect(),
+        ),
+        ("mixed_ascii", MIXED.iter().map(|s| s.to_string()).collect()),
+    ]
+}
+
+/// Returns enabled crate features, which are used to distinguish benchmarks.
+pub fn features() -> String {
+    let features = [
+        ("cache-type-score", cfg!(feature = "cache-type-score")),
+        ("fix-weight-length", cfg!(feature = "fix-weight-length")),
+        ("tag-prediction", cfg!(feature = "tag-prediction")),
+        ("charwise-pma", cfg!(feature = "charwise-pma")),
+        ("portable-simd", cfg!(feature = "portable-simd")),
+    ];
+    let enabled: Vec<_> = features
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    if enabled.is_empty() {
+        "no-features".to_string()
+    } else {
+        enabled.join("+")
+    }
+}
+
+/// Loads the model used for benchmarks.
+pub fn load_model() -> Model {
+    let f = File::open(MODEL_PATH).expect("the model must exist in resources/");
+    Model::read(f).unwrap()
+}
@@ -0,0 +1,396 @@
+use std::io::{self, BufRead, BufWriter, Write};
+
+use clap::{Parser, ValueEnum};
+use vaporetto::{errors::VaporettoError, ConlluField, Sentence};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Space-separated tokens with slash-separated tags, which is compatible with the full
+    /// annotation format of KyTea
+    Tokenized,
+
+    /// Partial annotation format
+    Part,
+
+    /// One token per line with tab-separated tags, such as BCCWJ and UniDic corpora. Sentences
+    /// are separated by empty lines.
+    Tsv,
+
+    /// MeCab output. Each line consists of a surface and comma-separated tags separated by a
+    /// tab, and each sentence ends with `EOS`.
+    Mecab,
+
+    /// CoNLL-U format. Sentences are separated by empty lines.
+    Conllu,
+}
+
+#[derive(Parser, Debug)]
+#[command(about = "A program to convert corpus formats.")]
+struct Args {
+    /// The input format
+    #[arg(long, value_enum)]
+    from: Format,
+
+    /// The output format
+    #[arg(long, value_enum)]
+    to: Format,
+
+    /// Comma-separated indices of tag columns to output in order, e.g., `1,0`. All columns are
+    /// output by default.
+    #[arg(long, value_delimiter = ',')]
+    tag_columns: Vec<usize>,
+
+    /// Comma-separated CoNLL-U fields of the tag columns: {lemma, upos, xpos, feats} or an
+    /// attribute name of the MISC field. When reading, the fields are stored in the tag columns
+    /// in order. When writing, the output tag columns are written to the fields in order.
+    #[arg(long, value_delimiter = ',', default_value = "upos")]
+    conllu_fields: Vec<ConlluField>,
+}
+
+/// Appends the text to the tokenized format, escaping special characters.
+fn push_escaped(buf: &mut String, text: &str) {
+    for c in text.chars() {
+        if matches!(c, ' ' | '\\' | '/') {
+            buf.push('\\');
+        }
+        buf.push(c);
+    }
+}
+
+/// Splits comma-separated features of MeCab, following the CSV format.
+fn split_mecab_features(features: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = features.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => result.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    result.push(field);
+    result
+}
+
+/// Parses a sentence consisting of the given lines. On failure, returns the index of the
+/// malformed line and the error message.
+fn parse_sentence(
+    format: Format,
+    lines: &[String],
+    conllu_fields: &[ConlluField],
+) -> Result<Sentence<'static, 'static>, (usize, String)> {
+    // Tokens of TSV and MeCab formats are converted into the tokenized format.
+    let mut tokenized = String::new();
+    // Byte offset of the token of each line in the tokenized format.
+    let mut line_starts = vec![];
+    match format {
+        Format::Tokenized => {
+            return Sentence::from_tokenized(&lines[0]).map_err(|e| (0, e.to_string()));
+        }
+        Format::Part => {
+            return Sentence::from_partial_annotation(&lines[0]).map_err(|e| (0, e.to_string()));
+        }
+        Format::Conllu => {
+            return Sentence::from_conllu(&lines.join("\n"), conllu_fields).map_err(|e| match e {
+                VaporettoError::Parse(e) => (e.line(), e.msg().to_string()),
+                e => (0, e.to_string()),
+            });
+        }
+        Format::Tsv => {
+            for (i, line) in lines.iter().enumerate() {
+                let mut cols = line.split('\t');
+                let surface = cols.next().unwrap();
+                if surface.is_empty() {
+                    return Err((i, "the surface is empty".into()));
+                }
+                if !tokenized.is_empty() {
+                    tokenized.push(' ');
+                }
+                line_starts.push(tokenized.len());
+                push_escaped(&mut tokenized, surface);
+                for tag in cols {
+                    tokenized.push('/');
+                    push_escaped(&mut tokenized, tag);
+                }
+            }
+        }
+        Format::Mecab => {
+            for (i, line) in lines.iter().enumerate() {
+                let (surface, features) = line
+                    .split_once('\t')
+                    .ok_or_else(|| (i, "a tab is missing".to_string()))?;
+                if surface.is_empty() {
+                    return Err((i, "the surface is empty".into()));
+                }
+                if !tokenized.is_empty() {
+                    tokenized.push(' ');
+                }
+                line_starts.push(tokenized.len());
+                push_escaped(&mut tokenized, surface);
+                for tag in split_mecab_features(features) {
+                    tokenized.push('/');
+                    if tag != "*" {
+                        push_escaped(&mut tokenized, &tag);
+                    }
+                }
+            }
+        }
+    }
+    Sentence::from_tokenized(&tokenized).map_err(|e| match e {
+        // The position in the converted text is not meaningful, so only the line is reported.
+        VaporettoError::Parse(e) => {
+            let i = line_starts
+                .partition_point(|&start| start <= e.byte_offset())
+                .saturating_sub(1);
+            (i, e.msg().to_string())
+        }
+        e => (0, e.to_string()),
+    })
+}
+
+/// Writes an empty sentence given by an empty line.
+fn write_empty(format: Format, mut out: impl Write) -> io::Result<()> {
+    match format {
+        Format::Tokenized | Format::Part | Format::Tsv => out.write_all(b"\n"),
+        // CoNLL-U cannot represent an empty sentence.
+        Format::Conllu => Ok(()),
+        Format::Mecab => out.write_all(b"EOS\n"),
+    }
+}
+
+/// Checks that the sentence can be written in the given format. Line-based formats have no way
+/// to escape tabs and line feeds in surfaces and tags.
+fn check_writable(s: &Sentence, format: Format) -> Result<(), String> {
+    if matches!(format, Format::Tokenized | Format::Part) {
+        return Ok(());
+    }
+    for token in s.iter_tokens() {
+        if token.surface().contains(['\t', '\n']) {
+            return Err(format!(
+                "the surface {:?} contains a tab or a line feed",
+                token.surface()
+            ));
+        }
+        for tag in token.tags().iter().flatten() {
+            if tag.contains(['\t', '\n']) {
+                return Err(format!("the tag {tag:?} contains a tab or a line feed"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the sentence in the given format, including the line feed at the end.
+fn write_sentence(
+    s: &Sentence,
+    format: Format,
+    conllu_fields: &[ConlluField],
+    buf: &mut String,
+    mut out: impl Write,
+) -> io::Result<()> {
+    match format {
+        Format::Tokenized => {
+            s.write_tokenized_text(buf);
+            buf.push('\n');
+        }
+        Format::Part => {
+            s.write_partial_annotation_text(buf);
+            buf.push('\n');
+        }
+        Format::Tsv => {
+            buf.clear();
+            for token in s.iter_tokens() {
+                buf.push_str(token.surface());
+                for tag in token.tags() {
+                    buf.push('\t');
+                    buf.push_str(tag.as_deref().unwrap_or_default());
+                }
+                buf.push('\n');
+            }
+            buf.push('\n');
+        }
+        Format::Mecab => s.write_mecab(buf),
+        Format::Conllu => {
+            s.write_conllu(buf, conllu_fields);
+            buf.push('\n');
+        }
+    }
+    out.write_all(buf.as_bytes())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut buf = String::new();
+    let mut n_sentences = 0;
+    let mut n_errors = 0;
+
+    // Lines of the current sentence and the line number of the first line.
+    let mut lines = vec![];
+    let mut start_line = 1;
+    let mut input = io::stdin().lock().lines().enumerate();
+    loop {
+        let (line_no, line) = match input.next() {
+            Some((i, line)) => (i + 1, Some(line?)),
+            None => (0, None),
+        };
+        let is_end = match (args.from, line.as_deref()) {
+            (_, None) => true,
+            // Empty lines are passed through to keep the lines aligned with the input.
+            (Format::Tokenized | Format::Part, Some("")) => {
+                write_empty(args.to, &mut out)?;
+                continue;
+            }
+            (Format::Tokenized | Format::Part, Some(_)) => {
+                lines.extend(line.clone());
+                true
+            }
+            (Format::Tsv | Format::Conllu, Some(line)) => line.trim().is_empty(),
+            (Format::Mecab, Some(line)) => line == "EOS" || line.trim().is_empty(),
+        };
+        if !is_end {
+            if lines.is_empty() {
+                start_line = line_no;
+            }
+            lines.extend(line);
+            continue;
+        }
+        if args.from == Format::Tokenized || args.from == Format::Part {
+            start_line = line_no;
+        }
+        if !lines.is_empty() {
+            match parse_sentence(args.from, &lines, &args.conllu_fields) {
+                Ok(mut s) => {
+                    if !args.tag_columns.is_empty() {
+                        s.select_tag_columns(&args.tag_columns);
+                    }
+                    if let Err(msg) = check_writable(&s, args.to) {
+                        eprintln!("line {start_line}: {msg}");
+                        n_errors += 1;
+                    } else {
+                        write_sentence(&s, args.to, &args.conllu_fields, &mut buf, &mut out)?;
+                        n_sentences += 1;
+                    }
+                }
+                Err((i, msg)) => {
+                    eprintln!("line {}: {msg}", start_line + i);
+                    n_errors += 1;
+                }
+            }
+            lines.clear();
+        }
+        if line.is_none() {
+            break;
+        }
+    }
+    out.flush()?;
+
+    eprintln!("# of sentences: {n_sentences}");
+    if n_errors != 0 {
+        eprintln!("# of skipped sentences: {n_errors}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the sentence in the given format and splits the output into the lines of the
+    /// sentence.
+    fn write_lines(s: &Sentence, format: Format, conllu_fields: &[ConlluField]) -> Vec<String> {
+        let mut buf = String::new();
+        let mut out = vec![];
+        write_sentence(s, format, conllu_fields, &mut buf, &mut out).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .take_while(|line| !line.is_empty() && *line != "EOS")
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_push_escaped() {
+        let mut buf = String::new();
+        push_escaped(&mut buf, "a b/c\\d");
+        assert_eq!("a\\ b\\/c\\\\d", buf);
+    }
+
+    #[test]
+    fn test_split_mecab_features() {
+        assert_eq!(vec!["名詞", "*", ""], split_mecab_features("名詞,*,"));
+        assert_eq!(
+            vec!["補助記号", ",", "\"a\",b"],
+            split_mecab_features("補助記号,\",\",\"\"\"a\"\",b\""),
+        );
+        assert_eq!(vec![""], split_mecab_features("\"\""));
+    }
+
+    #[test]
+    fn test_parse_sentence_error_line() {
+        let lines = ["火星\t名詞".into(), "猫\t名\0詞".into(), "だ".into()];
+        assert_eq!(
+            Some(1),
+            parse_sentence(Format::Tsv, &lines, &[])
+                .err()
+                .map(|(i, _)| i)
+        );
+
+        let lines = ["火星\t名詞".into(), "\t名詞".into()];
+        assert_eq!(
+            Some(1),
+            parse_sentence(Format::Mecab, &lines, &[])
+                .err()
+                .map(|(i, _)| i)
+        );
+
+        let lines = ["火星\t名詞".into(), "猫\t名詞".into(), "だ".into()];
+        assert_eq!(
+            Some(2),
+            parse_sentence(Format::Mecab, &lines, &[])
+                .err()
+                .map(|(i, _)| i)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let conllu_fields = [ConlluField::Upos, ConlluField::Misc("Reading".into())];
+        let s =
+            Sentence::from_tokenized("火星/名詞/カ,セー 猫/名詞/\"ネコ\" だ/助動詞/ダ").unwrap();
+        let mut expected = String::new();
+        s.write_tokenized_text(&mut expected);
+        for format in [
+            Format::Tokenized,
+            Format::Part,
+            Format::Tsv,
+            Format::Mecab,
+            Format::Conllu,
+        ] {
+            let lines = write_lines(&s, format, &conllu_fields);
+            let parsed = parse_sentence(format, &lines, &conllu_fields).unwrap();
+            let mut actual = String::new();
+            parsed.write_tokenized_text(&mut actual);
+            assert_eq!(expected, actual, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let s = Sentence::from_tokenized("火星/名詞\t固有 猫").unwrap();
+        assert!(check_writable(&s, Format::Tokenized).is_ok());
+        assert!(check_writable(&s, Format::Part).is_ok());
+        assert!(check_writable(&s, Format::Tsv).is_err());
+        assert!(check_writable(&s, Format::Mecab).is_err());
+        assert!(check_writable(&s, Format::Conllu).is_err());
+    }
+}
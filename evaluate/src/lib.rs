@@ -0,0 +1,3 @@
+//! Evaluation of system outputs against references, shared by the `evaluate` and `train` tools.
+
+pub mod report;
@@ -1,9 +1,17 @@
+mod significance;
+
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, BufRead};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use evaluate::report::{
+    position_tags, tags_match, Evaluator, JsonReport, NamedReport, Report, WordCounts,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
 use vaporetto::{CharacterBoundary, CharacterType, Model, Predictor, Sentence};
 use vaporetto_rules::{
     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
@@ -11,6 +19,8 @@ use vaporetto_rules::{
     SentenceFilter, StringFilter,
 };
 
+use significance::SignificanceTest;
+
 #[derive(Clone, Debug)]
 enum WsConst {
     GraphemeCluster,
@@ -39,12 +49,55 @@ enum EvaluationMetric {
     Word,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum InputFormat {
+    /// Fully annotated tokenized text
+    Tokenized,
+
+    /// Partially annotated text. Only annotated boundaries and tags are evaluated.
+    Part,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// The metric given by --metric
+    Text,
+
+    /// The metric given by --metric followed by a detailed report
+    Report,
+
+    /// A detailed report in JSON
+    Json,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum ErrorsFormat {
+    /// The reference and the system output separated by a tab
+    SideBySide,
+
+    /// Partial annotation where only boundaries and tags that differ from the reference are
+    /// annotated with the reference values
+    Part,
+}
+
 #[derive(Parser, Debug)]
 #[command(about = "A program to evaluate the accuracy of Vaporetto.")]
 struct Args {
     /// The model file to use when analyzing text
-    #[arg(long)]
-    model: PathBuf,
+    #[arg(long, required_unless_present = "model_a", conflicts_with = "model_a")]
+    model: Option<PathBuf>,
+
+    /// The first model file to compare with --model-b on the same test set
+    #[arg(long, requires = "model_b")]
+    model_a: Option<PathBuf>,
+
+    /// The second model file to compare with --model-a on the same test set
+    #[arg(long, requires = "model_a")]
+    model_b: Option<PathBuf>,
+
+    /// The format of the test data given from stdin.
+    #[arg(long, value_enum, default_value = "tokenized")]
+    input_format: InputFormat,
 
     /// Predicts POS tags.
     #[arg(long)]
@@ -64,6 +117,182 @@ struct Args {
     /// word: evaluates each word using Nagata's method.
     #[arg(long, default_value = "char")]
     metric: EvaluationMetric,
+
+    /// Comma-separated names of tag columns to evaluate with the word metric, e.g., `pos`. All
+    /// columns are evaluated by default.
+    #[arg(long, value_delimiter = ',', requires = "predict_tags")]
+    tag_columns: Vec<String>,
+
+    /// Predicts only combinations of tags observed in training for each token.
+    #[arg(long, requires = "predict_tags")]
+    constrain_tag_tuples: bool,
+
+    /// Tokenized corpora or word lists used for training. Recalls of in-vocabulary and
+    /// out-of-vocabulary words are reported.
+    #[arg(long)]
+    dict: Vec<PathBuf>,
+
+    /// Writes sentences whose system outputs do not match the references to the given file.
+    #[arg(long, conflicts_with = "model_a")]
+    errors: Option<PathBuf>,
+
+    /// The format of --errors.
+    #[arg(long, value_enum, default_value = "side-by-side")]
+    errors_format: ErrorsFormat,
+
+    /// The output format.
+    #[arg(long, value_enum, default_value = "text")]
+    format: OutputFormat,
+
+    /// The significance test of the difference of F1 scores given by --metric between
+    /// --model-a and --model-b.
+    #[arg(long, value_enum, default_value = "bootstrap")]
+    significance_test: SignificanceTest,
+
+    /// The number of samples of the significance test.
+    #[arg(long, default_value = "1000")]
+    n_samples: usize,
+
+    /// The random seed of the significance test.
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Writes sentences where --model-a and --model-b disagree to the given file. Each line
+    /// consists of the line number, the reference, and outputs of the two models, separated by
+    /// tabs.
+    #[arg(long, requires = "model_a")]
+    disagreements: Option<PathBuf>,
+}
+
+/// Returns the system output on the original text of the reference.
+fn sys_on_reference<'a, 'b>(r: &'a Sentence, s: &Sentence<'_, 'b>) -> Sentence<'a, 'b> {
+    // The text of the reference is always valid.
+    let mut sys = Sentence::from_raw(r.as_raw_text()).unwrap();
+    sys.boundaries_mut().copy_from_slice(s.boundaries());
+    sys.reset_tags(s.n_tags());
+    sys.tags_mut().clone_from_slice(s.tags());
+    sys
+}
+
+/// Returns counts of a sentence used for the significance test of the given metric.
+fn metric_counts(report: &Report, metric: &EvaluationMetric) -> WordCounts {
+    match metric {
+        EvaluationMetric::Char => {
+            let b = &report.boundary;
+            WordCounts {
+                n_sys: b.n_tp + b.n_fp,
+                n_ref: b.n_tp + b.n_fn,
+                n_cor: b.n_tp,
+            }
+        }
+        EvaluationMetric::Word => report.word,
+    }
+}
+
+fn print_metric(report: &Report, metric: &EvaluationMetric) {
+    match metric {
+        EvaluationMetric::Char => {
+            let b = &report.boundary;
+            println!("Precision: {}", b.precision());
+            println!("Recall: {}", b.recall());
+            println!("F1: {}", b.f1());
+            println!(
+                "TP: {}, TN: {}, FP: {}, FN: {}",
+                b.n_tp, b.n_tn, b.n_fp, b.n_fn
+            );
+        }
+        EvaluationMetric::Word => {
+            // Reference:
+            // Masaaki Nagata. 1994. A stochastic Japanese morphological analyzer using a forward-DP
+            // backward-A* n-best search algorithm. In COLING 1994 Volume 1: The 15th International
+            // Conference on Computational Linguistics.
+            let w = &report.word;
+            println!("Precision: {}", w.precision());
+            println!("Recall: {}", w.recall());
+            println!("F1: {}", w.f1());
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonModel<'a> {
+    path: &'a Path,
+    report: JsonReport<'a>,
+}
+
+#[derive(Serialize)]
+struct JsonSignificance {
+    test: SignificanceTest,
+    n_samples: usize,
+    difference: f64,
+    p_value: f64,
+}
+
+#[derive(Serialize)]
+struct JsonComparison<'a> {
+    model_a: JsonModel<'a>,
+    model_b: JsonModel<'a>,
+    significance: JsonSignificance,
+    n_disagreements: usize,
+}
+
+/// Writes the reference in the input format.
+fn write_reference(r: &Sentence, input_format: InputFormat, buf: &mut String) {
+    match input_format {
+        InputFormat::Tokenized => r.write_tokenized_text(buf),
+        InputFormat::Part => r.write_partial_annotation_text(buf),
+    }
+}
+
+/// Writes a sentence that the system output does not match the reference.
+fn write_error(
+    r: &Sentence,
+    s: &Sentence,
+    input_format: InputFormat,
+    format: ErrorsFormat,
+    tag_columns: &[usize],
+    mut out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut buf = String::new();
+    match format {
+        ErrorsFormat::SideBySide => {
+            write_reference(r, input_format, &mut buf);
+            write!(out, "{buf}\t")?;
+            sys_on_reference(r, s).write_tokenized_text(&mut buf);
+            writeln!(out, "{buf}")?;
+        }
+        ErrorsFormat::Part => {
+            // Only boundaries and tags that differ from the reference are annotated.
+            let mut diff = Sentence::from_raw(r.as_raw_text())?;
+            for ((d, &r_b), &s_b) in diff
+                .boundaries_mut()
+                .iter_mut()
+                .zip(r.boundaries())
+                .zip(s.boundaries())
+            {
+                if r_b != s_b {
+                    *d = r_b;
+                }
+            }
+            let n_tags = r.n_tags();
+            diff.reset_tags(n_tags);
+            let ref_tags = position_tags(r, tag_columns);
+            let sys_tags = position_tags(s, tag_columns);
+            for (i, (r_t, s_t)) in ref_tags.iter().zip(&sys_tags).enumerate() {
+                let is_end = r
+                    .boundaries()
+                    .get(i)
+                    .is_none_or(|&b| b == CharacterBoundary::WordBoundary);
+                if is_end && !tags_match(r_t, s_t, input_format == InputFormat::Part) {
+                    diff.tags_mut()[i * n_tags..(i + 1) * n_tags]
+                        .clone_from_slice(&r.tags()[i * n_tags..(i + 1) * n_tags]);
+                }
+            }
+            diff.write_partial_annotation_text(&mut buf);
+            writeln!(out, "{buf}")?;
+        }
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -80,116 +309,256 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    eprintln!("Loading model file...");
-    let mut f = zstd::Decoder::new(File::open(args.model)?)?;
-    let model = Model::read(&mut f)?;
-    let predictor = Predictor::new(model, args.predict_tags)?;
+    let dictionary = if args.dict.is_empty() {
+        None
+    } else {
+        let mut dictionary = HashSet::new();
+        for path in &args.dict {
+            eprintln!("Loading {path:?} ...");
+            let f = BufReader::new(File::open(path)?);
+            for line in f.lines() {
+                let line = line?;
+                if line.is_empty() {
+                    continue;
+                }
+                let s = Sentence::from_tokenized(&line)?;
+                for token in s.iter_tokens() {
+                    dictionary.insert(token.surface().to_string());
+                }
+            }
+        }
+        eprintln!("# of words: {}", dictionary.len());
+        Some(dictionary)
+    };
+
+    let model_paths: Vec<_> = args
+        .model
+        .into_iter()
+        .chain(args.model_a)
+        .chain(args.model_b)
+        .collect();
+    let mut predictors = vec![];
+    let mut tag_names = vec![];
+    let mut tag_columns = vec![];
+    for (i, path) in model_paths.iter().enumerate() {
+        eprintln!("Loading {path:?} ...");
+        let mut f = zstd::Decoder::new(File::open(path)?)?;
+        let model = Model::read(&mut f)?;
+        let columns = args
+            .tag_columns
+            .iter()
+            .map(|name| {
+                model
+                    .tag_column_index(name)
+                    .ok_or_else(|| format!("unknown tag column: {name}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if i == 0 {
+            tag_names = model
+                .tag_schema()
+                .iter()
+                .map(|c| c.name().to_string())
+                .collect();
+            if !tag_names.is_empty() {
+                let names: Vec<_> = tag_names
+                    .iter()
+                    .map(|name| if name.is_empty() { "-" } else { name })
+                    .collect();
+                eprintln!("Tag columns: {}", names.join(", "));
+            }
+            tag_columns = columns;
+        } else if columns != tag_columns {
+            return Err("--tag-columns must refer to the same columns of both models".into());
+        }
+        let mut predictor = Predictor::new(model, args.predict_tags)?;
+        predictor.constrain_tag_tuples(args.constrain_tag_tuples);
+        predictors.push(predictor);
+    }
+    if !tag_columns.is_empty() {
+        tag_names = args.tag_columns;
+    }
+    let evaluator = Evaluator::new(
+        tag_columns.clone(),
+        dictionary,
+        args.input_format == InputFormat::Part,
+    );
+    let mut errors_out = args
+        .errors
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()?;
+    let mut disagreements_out = args
+        .disagreements
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()?;
 
     eprintln!("Start tokenization");
 
-    let mut results = vec![];
+    let mut reports = vec![Report::default(); predictors.len()];
+    // Counts of each sentence for the significance test.
+    let mut sentence_counts = vec![vec![]; predictors.len()];
+    let mut n_disagreements = 0;
+    let mut buf = String::new();
     let lines = io::stdin().lock().lines();
-    for line in lines {
+    for (i, line) in lines.enumerate() {
         let line = line?;
         if line.is_empty() {
             continue;
         }
-        let mut s = Sentence::from_tokenized(&line)?;
-        let ref_boundaries = s.boundaries().to_vec();
-        let mut ref_tags = vec![];
-        for i in 0..=ref_boundaries.len() {
-            ref_tags.push(s.tags()[i * s.n_tags()..(i + 1) * s.n_tags()].to_vec());
-        }
-        if !args.no_norm {
-            let new_line = fullwidth_filter.filter(s.as_raw_text());
-            s = Sentence::from_raw(new_line)?
+        let r = match args.input_format {
+            InputFormat::Tokenized => Sentence::from_tokenized(&line)?,
+            InputFormat::Part => Sentence::from_partial_annotation(&line)?,
         };
-        predictor.predict(&mut s);
-        post_filters.iter().for_each(|filter| filter.filter(&mut s));
-        if args.predict_tags {
-            s.fill_tags();
-        }
-        let sys_boundaries = s.boundaries().to_vec();
-        let mut sys_tags = vec![];
-        for i in 0..=sys_boundaries.len() {
-            sys_tags.push(s.tags()[i * s.n_tags()..(i + 1) * s.n_tags()].to_vec());
-        }
-        results.push((ref_boundaries, ref_tags, sys_boundaries, sys_tags));
-    }
-
-    match args.metric {
-        EvaluationMetric::Char => {
-            let mut n_tp = 0;
-            let mut n_tn = 0;
-            let mut n_fp = 0;
-            let mut n_fn = 0;
-            for (rs_b, _, hs_b, _) in results {
-                for (r, h) in rs_b.into_iter().zip(hs_b) {
-                    if r == h {
-                        if h == CharacterBoundary::WordBoundary {
-                            n_tp += 1;
-                        } else {
-                            n_tn += 1;
-                        }
-                    } else if h == CharacterBoundary::WordBoundary {
-                        n_fp += 1;
-                    } else {
-                        n_fn += 1;
-                    }
+        let mut outputs = vec![];
+        for (predictor, (report, counts)) in predictors
+            .iter()
+            .zip(reports.iter_mut().zip(&mut sentence_counts))
+        {
+            let mut s = if args.no_norm {
+                Sentence::from_raw(r.as_raw_text())?
+            } else {
+                Sentence::from_raw(fullwidth_filter.filter(r.as_raw_text()))?
+            };
+            predictor.predict(&mut s);
+            post_filters.iter().for_each(|filter| filter.filter(&mut s));
+            if args.predict_tags {
+                s.fill_tags();
+            }
+            let sentence_report = evaluator.evaluate(&r, &s);
+            if let Some(out) = errors_out.as_mut() {
+                if !sentence_report.is_correct() {
+                    write_error(
+                        &r,
+                        &s,
+                        args.input_format,
+                        args.errors_format,
+                        &tag_columns,
+                        out,
+                    )?;
                 }
             }
-            let precision = f64::from(n_tp) / f64::from(n_tp + n_fp);
-            let recall = f64::from(n_tp) / f64::from(n_tp + n_fn);
-            let f1 = 2. * precision * recall / (precision + recall);
-            println!("Precision: {precision}");
-            println!("Recall: {recall}");
-            println!("F1: {f1}");
-            println!("TP: {n_tp}, TN: {n_tn}, FP: {n_fp}, FN: {n_fn}");
+            counts.push(metric_counts(&sentence_report, &args.metric));
+            *report += &sentence_report;
+            outputs.push(s);
         }
-        EvaluationMetric::Word => {
-            // Reference:
-            // Masaaki Nagata. 1994. A stochastic Japanese morphological analyzer using a forward-DP
-            // backward-A* n-best search algorithm. In COLING 1994 Volume 1: The 15th International
-            // Conference on Computational Linguistics.
-            let mut n_sys = 0;
-            let mut n_ref = 0;
-            let mut n_cor = 0;
-            for (refs_b, refs_t, syss_b, syss_t) in results {
-                let mut matched = true;
-                for (((r_b, r_t), s_b), s_t) in refs_b.iter().zip(&refs_t).zip(&syss_b).zip(&syss_t)
-                {
-                    if r_b == s_b {
-                        if *s_b == CharacterBoundary::WordBoundary {
-                            if matched && r_t == s_t {
-                                n_cor += 1;
-                            }
-                            matched = true;
-                            n_ref += 1;
-                            n_sys += 1;
-                        }
-                    } else {
-                        if *s_b == CharacterBoundary::WordBoundary {
-                            n_sys += 1;
-                        } else {
-                            n_ref += 1;
-                        }
-                        matched = false;
+        if let [a, b] = &outputs[..] {
+            if a.boundaries() != b.boundaries()
+                || position_tags(a, &tag_columns) != position_tags(b, &tag_columns)
+            {
+                n_disagreements += 1;
+                if let Some(out) = disagreements_out.as_mut() {
+                    write_reference(&r, args.input_format, &mut buf);
+                    write!(out, "{}\t{buf}", i + 1)?;
+                    for s in [a, b] {
+                        sys_on_reference(&r, s).write_tokenized_text(&mut buf);
+                        write!(out, "\t{buf}")?;
                     }
+                    writeln!(out)?;
                 }
-                if matched && refs_t.last().unwrap() == syss_t.last().unwrap() {
-                    n_cor += 1;
+            }
+        }
+    }
+    if let Some(mut out) = errors_out {
+        out.flush()?;
+    }
+    if let Some(mut out) = disagreements_out {
+        out.flush()?;
+    }
+
+    // Unnamed tag columns are shown by their indices.
+    for (i, name) in tag_names.iter_mut().enumerate() {
+        if name.is_empty() {
+            *name = i.to_string();
+        }
+    }
+    let n_columns = reports.iter().map(|r| r.tags.len()).max().unwrap_or(0);
+    for i in tag_names.len()..n_columns {
+        tag_names.push(i.to_string());
+    }
+    let named_reports: Vec<_> = reports
+        .iter()
+        .map(|report| NamedReport {
+            report,
+            tag_names: &tag_names,
+            has_dictionary: !args.dict.is_empty(),
+        })
+        .collect();
+
+    let (named_report_a, named_report_b) = match &named_reports[..] {
+        [named_report] => {
+            if args.format == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&named_report.to_json())?);
+            } else {
+                print_metric(named_report.report, &args.metric);
+                if args.format == OutputFormat::Report {
+                    println!();
+                    print!("{named_report}");
                 }
-                n_sys += 1;
-                n_ref += 1;
             }
-            let precision = f64::from(n_cor) / f64::from(n_sys);
-            let recall = f64::from(n_cor) / f64::from(n_ref);
-            let f1 = 2. * precision * recall / (precision + recall);
-            println!("Precision: {precision}");
-            println!("Recall: {recall}");
-            println!("F1: {f1}");
+            return Ok(());
+        }
+        [a, b] => (a, b),
+        _ => unreachable!(),
+    };
+
+    eprintln!("Start the significance test");
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let p_value = significance::p_value(
+        args.significance_test,
+        &sentence_counts[0],
+        &sentence_counts[1],
+        args.n_samples,
+        &mut rng,
+    );
+    let f1_a = metric_counts(&reports[0], &args.metric).f1();
+    let f1_b = metric_counts(&reports[1], &args.metric).f1();
+
+    if args.format == OutputFormat::Json {
+        let comparison = JsonComparison {
+            model_a: JsonModel {
+                path: &model_paths[0],
+                report: named_report_a.to_json(),
+            },
+            model_b: JsonModel {
+                path: &model_paths[1],
+                report: named_report_b.to_json(),
+            },
+            significance: JsonSignificance {
+                test: args.significance_test,
+                n_samples: args.n_samples,
+                difference: f1_b - f1_a,
+                p_value,
+            },
+            n_disagreements,
+        };
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+
+    for (name, path, named_report) in [
+        ("A", &model_paths[0], named_report_a),
+        ("B", &model_paths[1], named_report_b),
+    ] {
+        println!("[Model {name}] {}", path.display());
+        print_metric(named_report.report, &args.metric);
+        if args.format == OutputFormat::Report {
+            println!();
+            print!("{named_report}");
         }
+        println!();
     }
+    let test_name = match args.significance_test {
+        SignificanceTest::Bootstrap => "paired bootstrap resampling",
+        SignificanceTest::Randomization => "approximate randomization",
+    };
+    println!(
+        "Significance test: {test_name} ({} samples)",
+        args.n_samples
+    );
+    println!("F1 (A): {f1_a}");
+    println!("F1 (B): {f1_b}");
+    println!("Difference (B - A): {}", f1_b - f1_a);
+    println!("p-value: {p_value}");
+    println!("# of disagreements: {n_disagreements}");
 
     Ok(())
 }
@@ -0,0 +1,569 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::ops::AddAssign;
+
+use serde::Serialize;
+use vaporetto::{CharacterBoundary, CharacterType, Sentence};
+
+/// Returns `n / d`, or 0 if `d` is 0.
+fn ratio(n: usize, d: usize) -> f64 {
+    if d == 0 {
+        0.
+    } else {
+        n as f64 / d as f64
+    }
+}
+
+/// Returns the harmonic mean of the precision and the recall, or 0 if both are 0.
+fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall == 0. {
+        0.
+    } else {
+        2. * precision * recall / (precision + recall)
+    }
+}
+
+/// Counts of character boundaries.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoundaryCounts {
+    pub n_tp: usize,
+    pub n_tn: usize,
+    pub n_fp: usize,
+    pub n_fn: usize,
+}
+
+impl BoundaryCounts {
+    fn add(&mut self, r: CharacterBoundary, s: CharacterBoundary) {
+        if r == s {
+            if s == CharacterBoundary::WordBoundary {
+                self.n_tp += 1;
+            } else {
+                self.n_tn += 1;
+            }
+        } else if s == CharacterBoundary::WordBoundary {
+            self.n_fp += 1;
+        } else {
+            self.n_fn += 1;
+        }
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.n_tp, self.n_tp + self.n_fp)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.n_tp, self.n_tp + self.n_fn)
+    }
+
+    pub fn f1(&self) -> f64 {
+        f1(self.precision(), self.recall())
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        ratio(
+            self.n_tp + self.n_tn,
+            self.n_tp + self.n_tn + self.n_fp + self.n_fn,
+        )
+    }
+}
+
+impl AddAssign<&Self> for BoundaryCounts {
+    fn add_assign(&mut self, other: &Self) {
+        self.n_tp += other.n_tp;
+        self.n_tn += other.n_tn;
+        self.n_fp += other.n_fp;
+        self.n_fn += other.n_fn;
+    }
+}
+
+/// Counts of words in the system output, in the reference, and in both.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WordCounts {
+    pub n_sys: usize,
+    pub n_ref: usize,
+    pub n_cor: usize,
+}
+
+impl WordCounts {
+    pub fn precision(&self) -> f64 {
+        ratio(self.n_cor, self.n_sys)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.n_cor, self.n_ref)
+    }
+
+    pub fn f1(&self) -> f64 {
+        f1(self.precision(), self.recall())
+    }
+}
+
+impl AddAssign<&Self> for WordCounts {
+    fn add_assign(&mut self, other: &Self) {
+        self.n_sys += other.n_sys;
+        self.n_ref += other.n_ref;
+        self.n_cor += other.n_cor;
+    }
+}
+
+/// Counts of correct items among reference items.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RecallCounts {
+    pub n_ref: usize,
+    pub n_cor: usize,
+}
+
+impl RecallCounts {
+    pub fn recall(&self) -> f64 {
+        ratio(self.n_cor, self.n_ref)
+    }
+}
+
+impl AddAssign<&Self> for RecallCounts {
+    fn add_assign(&mut self, other: &Self) {
+        self.n_ref += other.n_ref;
+        self.n_cor += other.n_cor;
+    }
+}
+
+/// Evaluation results of sentences. Results of multiple sentences can be merged with `+=`.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    /// Counts of character boundaries.
+    pub boundary: BoundaryCounts,
+
+    /// Counts of words, where a word is correct if its span and tags are correct.
+    pub word: WordCounts,
+
+    /// Counts of words, where a word is correct if its span is correct.
+    pub segmentation: WordCounts,
+
+    /// Counts of tags of each column. Only correctly segmented words with annotated tags are
+    /// counted.
+    pub tags: Vec<RecallCounts>,
+
+    /// Counts of character boundaries for each pair of character types on both sides.
+    pub char_type_pairs: BTreeMap<(u8, u8), BoundaryCounts>,
+
+    /// Counts of correctly segmented words in the dictionary.
+    pub in_vocabulary: RecallCounts,
+
+    /// Counts of correctly segmented words not in the dictionary.
+    pub out_of_vocabulary: RecallCounts,
+}
+
+impl Report {
+    /// Returns true if the system output completely matches the reference.
+    pub fn is_correct(&self) -> bool {
+        self.boundary.n_fp == 0
+            && self.boundary.n_fn == 0
+            && self.word.n_cor == self.word.n_ref
+            && self.word.n_cor == self.word.n_sys
+    }
+}
+
+impl AddAssign<&Self> for Report {
+    fn add_assign(&mut self, other: &Self) {
+        self.boundary += &other.boundary;
+        self.word += &other.word;
+        self.segmentation += &other.segmentation;
+        if self.tags.len() < other.tags.len() {
+            self.tags.resize(other.tags.len(), RecallCounts::default());
+        }
+        for (t, o) in self.tags.iter_mut().zip(&other.tags) {
+            *t += o;
+        }
+        for (pair, counts) in &other.char_type_pairs {
+            *self.char_type_pairs.entry(*pair).or_default() += counts;
+        }
+        self.in_vocabulary += &other.in_vocabulary;
+        self.out_of_vocabulary += &other.out_of_vocabulary;
+    }
+}
+
+/// Returns tags of each character position, keeping only the given columns if specified.
+pub fn position_tags<'a>(
+    s: &Sentence<'_, 'a>,
+    columns: &[usize],
+) -> Vec<Vec<Option<Cow<'a, str>>>> {
+    let n_tags = s.n_tags();
+    (0..=s.boundaries().len())
+        .map(|i| {
+            let tags = &s.tags()[i * n_tags..(i + 1) * n_tags];
+            if columns.is_empty() {
+                tags.to_vec()
+            } else {
+                columns
+                    .iter()
+                    .map(|&c| tags.get(c).cloned().flatten())
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+/// Returns spans of words in characters.
+fn word_spans(boundaries: &[CharacterBoundary]) -> Vec<(usize, usize)> {
+    let mut spans = vec![];
+    let mut start = 0;
+    for (i, &b) in boundaries.iter().enumerate() {
+        if b == CharacterBoundary::WordBoundary {
+            spans.push((start, i + 1));
+            start = i + 1;
+        }
+    }
+    spans.push((start, boundaries.len() + 1));
+    spans
+}
+
+/// Returns true if all boundaries inside and on both sides of the span are annotated in the
+/// reference.
+fn is_annotated(ref_boundaries: &[CharacterBoundary], (start, end): (usize, usize)) -> bool {
+    let lo = start.saturating_sub(1);
+    let hi = end.min(ref_boundaries.len());
+    ref_boundaries[lo..hi]
+        .iter()
+        .all(|&b| b != CharacterBoundary::Unknown)
+}
+
+/// Returns true if tags of the system output match the reference. If `partial` is true, tags
+/// that are not annotated in the reference are ignored.
+pub fn tags_match<T: PartialEq>(r_t: &[Option<T>], s_t: &[Option<T>], partial: bool) -> bool {
+    if partial {
+        r_t.iter()
+            .enumerate()
+            .all(|(i, tag)| tag.is_none() || s_t.get(i) == Some(tag))
+    } else {
+        r_t == s_t
+    }
+}
+
+/// Evaluates system outputs against references.
+pub struct Evaluator {
+    tag_columns: Vec<usize>,
+    dictionary: Option<HashSet<String>>,
+    partial: bool,
+}
+
+impl Evaluator {
+    /// Creates a new evaluator. Only the given tag columns are evaluated if specified.
+    /// In-vocabulary and out-of-vocabulary recalls are computed if `dictionary` is given.
+    ///
+    /// If `partial` is true, references are regarded as partially annotated: unknown boundaries,
+    /// words touching them, and tags that are not annotated are not evaluated.
+    pub fn new(
+        tag_columns: Vec<usize>,
+        dictionary: Option<HashSet<String>>,
+        partial: bool,
+    ) -> Self {
+        Self {
+            tag_columns,
+            dictionary,
+            partial,
+        }
+    }
+
+    /// Evaluates a system output of a sentence. `sys` must have the same number of characters
+    /// as `reference`.
+    pub fn evaluate(&self, reference: &Sentence, sys: &Sentence) -> Report {
+        let mut report = Report::default();
+        let ref_tags = position_tags(reference, &self.tag_columns);
+        let sys_tags = position_tags(sys, &self.tag_columns);
+
+        let char_types: Vec<_> = reference
+            .as_raw_text()
+            .chars()
+            .map(|c| CharacterType::get_type(c) as u8)
+            .collect();
+        for (i, (&r, &s)) in reference
+            .boundaries()
+            .iter()
+            .zip(sys.boundaries())
+            .enumerate()
+        {
+            if r == CharacterBoundary::Unknown {
+                continue;
+            }
+            report.boundary.add(r, s);
+            report
+                .char_type_pairs
+                .entry((char_types[i], char_types[i + 1]))
+                .or_default()
+                .add(r, s);
+        }
+
+        // Unknown boundaries are not regarded as word boundaries, and words touching them are
+        // excluded.
+        let ref_boundaries = reference.boundaries();
+        let ref_spans: Vec<_> = word_spans(ref_boundaries)
+            .into_iter()
+            .filter(|&span| is_annotated(ref_boundaries, span))
+            .collect();
+        let sys_spans: HashSet<_> = word_spans(sys.boundaries())
+            .into_iter()
+            .filter(|&span| is_annotated(ref_boundaries, span))
+            .collect();
+        report.word.n_ref = ref_spans.len();
+        report.word.n_sys = sys_spans.len();
+        report.segmentation = report.word;
+        let chars: Vec<_> = reference.as_raw_text().chars().collect();
+        for &(start, end) in &ref_spans {
+            let matched = sys_spans.contains(&(start, end));
+            if matched {
+                report.segmentation.n_cor += 1;
+                let (r_t, s_t) = (&ref_tags[end - 1], &sys_tags[end - 1]);
+                if tags_match(r_t, s_t, self.partial) {
+                    report.word.n_cor += 1;
+                }
+                if report.tags.len() < r_t.len() {
+                    report.tags.resize(r_t.len(), RecallCounts::default());
+                }
+                for (i, tag) in r_t.iter().enumerate() {
+                    if tag.is_some() {
+                        report.tags[i].n_ref += 1;
+                        if s_t.get(i) == Some(tag) {
+                            report.tags[i].n_cor += 1;
+                        }
+                    }
+                }
+            }
+            if let Some(dictionary) = self.dictionary.as_ref() {
+                let surface: String = chars[start..end].iter().collect();
+                let counts = if dictionary.contains(&surface) {
+                    &mut report.in_vocabulary
+                } else {
+                    &mut report.out_of_vocabulary
+                };
+                counts.n_ref += 1;
+                if matched {
+                    counts.n_cor += 1;
+                }
+            }
+        }
+        report
+    }
+}
+
+fn char_type_name(t: u8) -> &'static str {
+    match t {
+        1 => "Digit",
+        2 => "Roman",
+        3 => "Hiragana",
+        4 => "Katakana",
+        5 => "Kanji",
+        _ => "Other",
+    }
+}
+
+#[derive(Serialize)]
+struct JsonPrf {
+    precision: f64,
+    recall: f64,
+    f1: f64,
+}
+
+#[derive(Serialize)]
+struct JsonBoundary {
+    precision: f64,
+    recall: f64,
+    f1: f64,
+    accuracy: f64,
+    tp: usize,
+    tn: usize,
+    fp: usize,
+    #[serde(rename = "fn")]
+    fn_: usize,
+}
+
+impl From<&BoundaryCounts> for JsonBoundary {
+    fn from(c: &BoundaryCounts) -> Self {
+        Self {
+            precision: c.precision(),
+            recall: c.recall(),
+            f1: c.f1(),
+            accuracy: c.accuracy(),
+            tp: c.n_tp,
+            tn: c.n_tn,
+            fp: c.n_fp,
+            fn_: c.n_fn,
+        }
+    }
+}
+
+impl From<&WordCounts> for JsonPrf {
+    fn from(c: &WordCounts) -> Self {
+        Self {
+            precision: c.precision(),
+            recall: c.recall(),
+            f1: c.f1(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonTag<'a> {
+    name: &'a str,
+    accuracy: f64,
+    correct: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+struct JsonRecall {
+    recall: f64,
+    correct: usize,
+    total: usize,
+}
+
+impl From<&RecallCounts> for JsonRecall {
+    fn from(c: &RecallCounts) -> Self {
+        Self {
+            recall: c.recall(),
+            correct: c.n_cor,
+            total: c.n_ref,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct JsonReport<'a> {
+    boundary: JsonBoundary,
+    word: JsonPrf,
+    segmentation: JsonPrf,
+    tags: Vec<JsonTag<'a>>,
+    char_type_pairs: BTreeMap<String, JsonBoundary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_vocabulary: Option<JsonRecall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    out_of_vocabulary: Option<JsonRecall>,
+}
+
+/// A report with names of tag columns for printing.
+pub struct NamedReport<'a> {
+    pub report: &'a Report,
+    pub tag_names: &'a [String],
+    pub has_dictionary: bool,
+}
+
+impl<'a> NamedReport<'a> {
+    pub fn to_json(&self) -> JsonReport<'a> {
+        let report = self.report;
+        JsonReport {
+            boundary: (&report.boundary).into(),
+            word: (&report.word).into(),
+            segmentation: (&report.segmentation).into(),
+            tags: report
+                .tags
+                .iter()
+                .zip(self.tag_names)
+                .map(|(c, name)| JsonTag {
+                    name,
+                    accuracy: c.recall(),
+                    correct: c.n_cor,
+                    total: c.n_ref,
+                })
+                .collect(),
+            char_type_pairs: report
+                .char_type_pairs
+                .iter()
+                .map(|(&(l, r), c)| {
+                    (
+                        format!("{}|{}", char_type_name(l), char_type_name(r)),
+                        c.into(),
+                    )
+                })
+                .collect(),
+            in_vocabulary: self.has_dictionary.then(|| (&report.in_vocabulary).into()),
+            out_of_vocabulary: self
+                .has_dictionary
+                .then(|| (&report.out_of_vocabulary).into()),
+        }
+    }
+}
+
+impl fmt::Display for NamedReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let report = self.report;
+        let b = &report.boundary;
+        writeln!(
+            f,
+            "Boundary: P={:.4} R={:.4} F1={:.4} Acc={:.4}",
+            b.precision(),
+            b.recall(),
+            b.f1(),
+            b.accuracy(),
+        )?;
+        for (name, c) in [
+            ("Word (segmentation)", &report.segmentation),
+            ("Word (segmentation and tags)", &report.word),
+        ] {
+            writeln!(
+                f,
+                "{name}: P={:.4} R={:.4} F1={:.4}",
+                c.precision(),
+                c.recall(),
+                c.f1(),
+            )?;
+        }
+        if !report.tags.is_empty() {
+            writeln!(f, "Tag accuracy:")?;
+            for (c, name) in report.tags.iter().zip(self.tag_names) {
+                writeln!(f, "  {name}: {:.4} ({}/{})", c.recall(), c.n_cor, c.n_ref)?;
+            }
+        }
+        writeln!(f, "Boundaries by character types:")?;
+        for (&(l, r), c) in &report.char_type_pairs {
+            writeln!(
+                f,
+                "  {}|{}: Acc={:.4} F1={:.4} (TP: {}, TN: {}, FP: {}, FN: {})",
+                char_type_name(l),
+                char_type_name(r),
+                c.accuracy(),
+                c.f1(),
+                c.n_tp,
+                c.n_tn,
+                c.n_fp,
+                c.n_fn,
+            )?;
+        }
+        if self.has_dictionary {
+            for (name, c) in [
+                ("In-vocabulary", &report.in_vocabulary),
+                ("Out-of-vocabulary", &report.out_of_vocabulary),
+            ] {
+                writeln!(
+                    f,
+                    "{name} recall: {:.4} ({}/{})",
+                    c.recall(),
+                    c.n_cor,
+                    c.n_ref
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_counts() {
+        let b = BoundaryCounts::default();
+        assert_eq!(0., b.precision());
+        assert_eq!(0., b.recall());
+        assert_eq!(0., b.f1());
+        assert_eq!(0., b.accuracy());
+
+        let w = WordCounts {
+            n_sys: 0,
+            n_ref: 3,
+            n_cor: 0,
+        };
+        assert_eq!(0., w.precision());
+        assert_eq!(0., w.recall());
+        assert_eq!(0., w.f1());
+    }
+}
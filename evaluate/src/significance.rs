@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use rand::Rng;
+use serde::Serialize;
+
+use evaluate::report::WordCounts;
+
+/// Statistical significance test over sentences.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SignificanceTest {
+    /// Paired bootstrap resampling
+    Bootstrap,
+
+    /// Approximate randomization test
+    Randomization,
+}
+
+fn sum(counts: impl Iterator<Item = WordCounts>) -> WordCounts {
+    let mut total = WordCounts::default();
+    for c in counts {
+        total += &c;
+    }
+    total
+}
+
+/// Returns the p-value of the difference of F1 scores of two systems, where `a` and `b` are
+/// counts of each sentence.
+///
+/// With bootstrap resampling, the p-value is the ratio of samples in which the system with the
+/// higher F1 score on the whole set does not outperform the other.
+///
+/// With approximate randomization, outputs of the two systems are randomly swapped for each
+/// sentence, and the p-value is the ratio of trials whose absolute difference is at least the
+/// observed one.
+///
+/// Reference:
+/// Philipp Koehn. 2004. Statistical significance tests for machine translation evaluation. In
+/// Proceedings of EMNLP 2004.
+/// Stefan Riezler and John T. Maxwell. 2005. On some pitfalls in automatic evaluation and
+/// significance testing for MT. In Proceedings of the ACL Workshop on Intrinsic and Extrinsic
+/// Evaluation Measures for Machine Translation and/or Summarization.
+pub fn p_value<R>(
+    test: SignificanceTest,
+    a: &[WordCounts],
+    b: &[WordCounts],
+    n_samples: usize,
+    rng: &mut R,
+) -> f64
+where
+    R: Rng,
+{
+    let observed = sum(b.iter().copied()).f1() - sum(a.iter().copied()).f1();
+    if observed == 0. || observed.is_nan() {
+        return 1.;
+    }
+    let mut n_extreme = 0;
+    match test {
+        SignificanceTest::Bootstrap => {
+            for _ in 0..n_samples {
+                let indices: Vec<_> = (0..a.len()).map(|_| rng.gen_range(0..a.len())).collect();
+                let diff = sum(indices.iter().map(|&i| b[i])).f1()
+                    - sum(indices.iter().map(|&i| a[i])).f1();
+                // NaN is also regarded as not outperforming.
+                let outperforms = diff * observed.signum() > 0.;
+                if !outperforms {
+                    n_extreme += 1;
+                }
+            }
+            n_extreme as f64 / n_samples as f64
+        }
+        SignificanceTest::Randomization => {
+            for _ in 0..n_samples {
+                let mut sum_a = WordCounts::default();
+                let mut sum_b = WordCounts::default();
+                for (x, y) in a.iter().zip(b) {
+                    let (x, y) = if rng.gen() { (y, x) } else { (x, y) };
+                    sum_a += x;
+                    sum_b += y;
+                }
+                let diff = sum_b.f1() - sum_a.f1();
+                let is_less_extreme = diff.abs() < observed.abs();
+                if !is_less_extreme {
+                    n_extreme += 1;
+                }
+            }
+            (n_extreme + 1) as f64 / (n_samples + 1) as f64
+        }
+    }
+}
@@ -7,8 +7,7 @@ use std::io::Read;
 use std::rc::Rc;
 
 use gloo_worker::{HandlerId, Spawnable, Worker, WorkerBridge, WorkerScope};
-use serde::{Deserialize, Serialize};
-use vaporetto::{CharacterType, Model, Predictor, Sentence};
+use vaporetto::{CharacterType, Model, OwnedToken, Predictor, Sentence};
 use vaporetto_rules::{
     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
     string_filters::KyteaFullwidthFilter,
@@ -20,15 +19,9 @@ use yew::{html, Component, Context, Html};
 use crate::text_input::TextInput;
 use crate::token_view::TokenView;
 
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
-pub struct Token {
-    pub surface: String,
-    pub tags: Vec<String>,
-}
-
 pub struct WorkerMessage {
     pub id: HandlerId,
-    pub output: (Vec<Token>, usize),
+    pub output: (Vec<OwnedToken>, usize),
 }
 
 #[ouroboros::self_referencing]
@@ -48,7 +41,7 @@ pub struct VaporettoWorker {
 impl Worker for VaporettoWorker {
     type Input = String;
     type Message = WorkerMessage;
-    type Output = (Vec<Token>, usize);
+    type Output = (Vec<OwnedToken>, usize);
 
     fn create(_scope: &WorkerScope<Self>) -> Self {
         let model_data = include_bytes!("bccwj-suw+unidic_pos+pron.model.zst");
@@ -107,18 +100,7 @@ impl Worker for VaporettoWorker {
         let tokens = self
             .borrow_sentence_orig()
             .iter_tokens()
-            .map(|token| Token {
-                surface: token.surface().to_string(),
-                tags: token
-                    .tags()
-                    .iter()
-                    .map(|tag| {
-                        tag.as_ref()
-                            .map(|tag| tag.to_string())
-                            .unwrap_or_else(String::new)
-                    })
-                    .collect(),
-            })
+            .map(OwnedToken::from)
             .collect();
         let n_tags = self.borrow_sentence_orig().n_tags();
 
@@ -129,13 +111,13 @@ impl Worker for VaporettoWorker {
 
 pub enum Msg {
     SetText(String),
-    WorkerResult((Vec<Token>, usize)),
+    WorkerResult((Vec<OwnedToken>, usize)),
 }
 
 pub struct App {
     bridge: WorkerBridge<VaporettoWorker>,
     text: Rc<String>,
-    tokens: Option<Rc<Vec<Token>>>,
+    tokens: Option<Rc<Vec<OwnedToken>>>,
     n_tags: usize,
 }
 
@@ -1,13 +1,13 @@
 use std::rc::Rc;
 
+use vaporetto::OwnedToken;
 use yew::{function_component, html, Html, Properties};
 
 use crate::fl;
-use crate::Token;
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props {
-    pub tokens: Rc<Vec<Token>>,
+    pub tokens: Rc<Vec<OwnedToken>>,
     pub n_tags: usize,
 }
 
@@ -31,10 +31,10 @@ pub fn token_view(props: &Props) -> Html {
                 {
                     for tokens.iter().map(|token| html! {
                         <tr>
-                            <td>{&token.surface}</td>
+                            <td>{token.surface()}</td>
                             {
-                                for token.tags.iter().map(|tag| html! {
-                                    <td>{tag}</td>
+                                for token.tags().iter().map(|tag| html! {
+                                    <td>{tag.as_deref().unwrap_or_default()}</td>
                                 })
                             }
                         </tr>
@@ -2,7 +2,6 @@ use std::fs;
 use std::path::PathBuf;
 
 use clap::Parser;
-use serde::{Deserialize, Serialize};
 use vaporetto::{Model, WordWeightRecord};
 
 #[derive(Parser, Debug)]
@@ -24,18 +23,30 @@ struct Args {
     #[arg(long)]
     replace_dict: Option<PathBuf>,
 
+    /// The name of the dictionary to dump or replace. If not specified, the unnamed dictionary is
+    /// used.
+    #[arg(long)]
+    dict_name: Option<String>,
+
+    /// Add the dictionary given by --replace-dict as a new named dictionary if the model does not
+    /// contain the dictionary given by --dict-name. Without this flag, unknown names are errors.
+    #[arg(long, requires_all = ["replace_dict", "dict_name"])]
+    add_dict: bool,
+
+    /// Print names of dictionaries contained in the model.
+    #[arg(long)]
+    list_dicts: bool,
+
+    /// Comma-separated indices of tag columns to keep in the given order. The other columns are
+    /// dropped.
+    #[arg(long, value_delimiter = ',')]
+    tag_columns: Option<Vec<usize>>,
+
     /// The number of workers for zstd (0 means multithreaded will be disabled)
     #[arg(long, default_value = "0")]
     zstd_workers: u32,
 }
 
-#[derive(Deserialize, Serialize)]
-struct WordWeightRecordFlatten {
-    word: String,
-    weights: String,
-    comment: String,
-}
-
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
@@ -43,17 +54,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut f = zstd::Decoder::new(fs::File::open(args.model_in)?)?;
     let mut model = Model::read(&mut f)?;
 
+    if args.list_dicts {
+        for name in model.dictionary_names() {
+            println!("{name}");
+        }
+    }
+
     if let Some(path) = args.dump_dict {
+        let dict = if let Some(name) = args.dict_name.as_ref() {
+            model
+                .named_dictionary(name)
+                .ok_or_else(|| format!("dictionary `{name}` is not found"))?
+        } else {
+            model.dictionary()
+        };
         eprintln!("Saving dictionary file...");
         let file = fs::File::create(path)?;
         let mut wtr = csv::Writer::from_writer(file);
-        for data in model.dictionary() {
-            let str_weights: Vec<_> = data.get_weights().iter().map(|w| w.to_string()).collect();
-            wtr.serialize(WordWeightRecordFlatten {
-                word: data.get_word().to_string(),
-                weights: str_weights.join(" "),
-                comment: data.get_comment().to_string(),
-            })?;
+        for data in dict {
+            wtr.serialize(data)?;
         }
     }
 
@@ -61,16 +80,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         eprintln!("Loading dictionary file...");
         let file = fs::File::open(path)?;
         let mut rdr = csv::Reader::from_reader(file);
-        let mut dict = vec![];
-        for result in rdr.deserialize() {
-            let record: WordWeightRecordFlatten = result?;
-            let mut weights = vec![];
-            for w in record.weights.split(' ') {
-                weights.push(w.parse()?);
+        let dict = rdr
+            .deserialize()
+            .collect::<Result<Vec<WordWeightRecord>, _>>()?;
+        if let Some(name) = args.dict_name.as_ref() {
+            if !args.add_dict && model.named_dictionary(name).is_none() {
+                return Err(format!(
+                    "dictionary `{name}` is not found (specify --add-dict to add it)"
+                )
+                .into());
             }
-            dict.push(WordWeightRecord::new(record.word, weights, record.comment)?);
+            model.replace_named_dictionary(name, dict);
+        } else {
+            model.replace_dictionary(dict);
         }
-        model.replace_dictionary(dict);
+    }
+
+    if let Some(columns) = args.tag_columns {
+        model.select_tag_columns(&columns)?;
     }
 
     if let Some(path) = args.model_out {
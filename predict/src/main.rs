@@ -1,11 +1,13 @@
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{self, BufRead, BufWriter, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Instant;
 
-use clap::Parser;
-use vaporetto::{CharacterType, Model, Predictor, Sentence};
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use vaporetto::{CharacterType, ConlluField, Model, Predictor, Sentence, Token};
 use vaporetto_rules::{
     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter},
     string_filters::KyteaFullwidthFilter,
@@ -34,6 +36,29 @@ impl FromStr for WsConst {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Space-separated tokens with slash-separated tags
+    Tokenized,
+
+    /// CoNLL-U format. Tag columns are written to the fields given by their names: {lemma, upos,
+    /// xpos, feats} or attributes of the MISC field.
+    Conllu,
+
+    /// MeCab-compatible format. Each line consists of a surface and comma-separated tags
+    /// separated by a tab, and each sentence ends with `EOS`.
+    Mecab,
+
+    /// Tab-separated values. Each line consists of a surface, start and end positions in
+    /// characters, start and end positions in bytes, and tags. Sentences are separated by empty
+    /// lines.
+    Tsv,
+
+    /// JSON Lines. Each line is a JSON object of a sentence with tokens, their offsets and tags.
+    /// Boundary scores and tag candidates are included with --scores and --tag-scores.
+    Jsonl,
+}
+
 #[derive(Parser, Debug)]
 #[command(about = "A program to perform word segmentation.")]
 struct Args {
@@ -50,17 +75,30 @@ struct Args {
     #[arg(long)]
     wsconst: Vec<WsConst>,
 
-    /// Prints boundary scores.
+    /// Prints boundary scores. Only available with --format tokenized or jsonl.
     #[arg(long)]
     scores: bool,
 
-    /// Prints tag scores.
+    /// Prints tag scores. Only available with --format tokenized or jsonl.
     #[arg(long)]
     tag_scores: bool,
 
+    /// Comma-separated names of tag columns to output in order, e.g., `pos`. All columns are
+    /// output by default.
+    #[arg(long, value_delimiter = ',', requires = "predict_tags")]
+    tag_columns: Vec<String>,
+
+    /// Predicts only combinations of tags observed in training for each token.
+    #[arg(long, requires = "predict_tags")]
+    constrain_tag_tuples: bool,
+
     /// Do not normalize input strings before prediction.
     #[arg(long)]
     no_norm: bool,
+
+    /// The output format
+    #[arg(long, value_enum, default_value = "tokenized")]
+    format: OutputFormat,
 }
 
 fn print_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::error::Error>> {
@@ -74,10 +112,27 @@ fn print_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::er
     Ok(())
 }
 
-fn print_tag_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+/// Returns tag candidates of the given columns in order. All columns are returned if `columns` is
+/// empty.
+fn tag_candidates<'b>(token: &Token<'_, 'b>, columns: &[usize]) -> Vec<Vec<(Cow<'b, str>, i32)>> {
+    let cands = token.decoded_tag_candidates().unwrap_or_default();
+    if columns.is_empty() {
+        return cands;
+    }
+    columns
+        .iter()
+        .map(|&c| cands.get(c).cloned().unwrap_or_default())
+        .collect()
+}
+
+fn print_tag_scores(
+    s: &Sentence,
+    columns: &[usize],
+    mut out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
     for token in s.iter_tokens() {
         out.write_all(token.surface().as_bytes())?;
-        for cands in token.tag_candidates() {
+        for cands in tag_candidates(&token, columns) {
             out.write_all(b"\t")?;
             for (i, (tag, score)) in cands.iter().enumerate() {
                 if i != 0 {
@@ -92,8 +147,167 @@ fn print_tag_scores(s: &Sentence, mut out: impl Write) -> Result<(), Box<dyn std
     Ok(())
 }
 
+#[derive(Serialize)]
+struct JsonTagCandidate<'a> {
+    tag: Cow<'a, str>,
+    score: i32,
+}
+
+#[derive(Serialize)]
+struct JsonToken<'a> {
+    surface: &'a str,
+    start: usize,
+    end: usize,
+    byte_start: usize,
+    byte_end: usize,
+    tags: Vec<Option<&'a str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag_candidates: Option<Vec<Vec<JsonTagCandidate<'a>>>>,
+}
+
+#[derive(Serialize)]
+struct JsonSentence<'a> {
+    text: &'a str,
+    tokens: Vec<JsonToken<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    boundary_scores: Option<&'a [i32]>,
+}
+
+/// Settings of the output.
+struct Output<'a> {
+    format: OutputFormat,
+    conllu_fields: &'a [ConlluField],
+    tag_columns: &'a [usize],
+    scores: bool,
+    tag_scores: bool,
+}
+
+impl Output<'_> {
+    /// Writes the sentence `s` in the output format. Scores are taken from `s_pred`, which is the
+    /// predicted sentence with the same boundaries as `s`.
+    fn write_sentence(
+        &self,
+        s: &Sentence,
+        s_pred: &Sentence,
+        buf: &mut String,
+        mut out: impl Write,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.format {
+            OutputFormat::Tokenized => {
+                s.write_tokenized_text(buf);
+                out.write_all(buf.as_bytes())?;
+                out.write_all(b"\n")?;
+                if self.scores {
+                    print_scores(s_pred, &mut out)?;
+                }
+                if self.tag_scores {
+                    print_tag_scores(s_pred, self.tag_columns, &mut out)?;
+                }
+            }
+            OutputFormat::Conllu => {
+                // Each token line ends with a line feed, so sentences are separated by empty
+                // lines.
+                s.write_conllu(buf, self.conllu_fields);
+                out.write_all(buf.as_bytes())?;
+                out.write_all(b"\n")?;
+            }
+            OutputFormat::Mecab => {
+                s.write_mecab(buf);
+                out.write_all(buf.as_bytes())?;
+            }
+            OutputFormat::Tsv => {
+                let byte_offsets = byte_offsets(s.as_raw_text());
+                for token in s.iter_tokens() {
+                    write!(
+                        out,
+                        "{}\t{}\t{}\t{}\t{}",
+                        token.surface(),
+                        token.start(),
+                        token.end(),
+                        byte_offsets[token.start()],
+                        byte_offsets[token.end()],
+                    )?;
+                    for tag in token.tags() {
+                        out.write_all(b"\t")?;
+                        out.write_all(tag.as_deref().unwrap_or_default().as_bytes())?;
+                    }
+                    out.write_all(b"\n")?;
+                }
+                out.write_all(b"\n")?;
+            }
+            OutputFormat::Jsonl => {
+                let byte_offsets = byte_offsets(s.as_raw_text());
+                let tokens = s
+                    .iter_tokens()
+                    .zip(s_pred.iter_tokens())
+                    .map(|(token, token_pred)| JsonToken {
+                        surface: token.surface(),
+                        start: token.start(),
+                        end: token.end(),
+                        byte_start: byte_offsets[token.start()],
+                        byte_end: byte_offsets[token.end()],
+                        tags: token.tags().iter().map(|tag| tag.as_deref()).collect(),
+                        tag_candidates: self.tag_scores.then(|| {
+                            tag_candidates(&token_pred, self.tag_columns)
+                                .into_iter()
+                                .map(|cands| {
+                                    cands
+                                        .into_iter()
+                                        .map(|(tag, score)| JsonTagCandidate { tag, score })
+                                        .collect()
+                                })
+                                .collect()
+                        }),
+                    })
+                    .collect();
+                let json = JsonSentence {
+                    text: s.as_raw_text(),
+                    tokens,
+                    boundary_scores: self.scores.then(|| s_pred.boundary_scores()),
+                };
+                serde_json::to_writer(&mut out, &json)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes an empty sentence given by an empty line.
+    fn write_empty(&self, mut out: impl Write) -> Result<(), Box<dyn std::error::Error>> {
+        match self.format {
+            OutputFormat::Tokenized | OutputFormat::Tsv => out.write_all(b"\n")?,
+            // CoNLL-U cannot represent an empty sentence.
+            OutputFormat::Conllu => (),
+            OutputFormat::Mecab => out.write_all(b"EOS\n")?,
+            OutputFormat::Jsonl => {
+                let json = JsonSentence {
+                    text: "",
+                    tokens: vec![],
+                    boundary_scores: self.scores.then_some(&[]),
+                };
+                serde_json::to_writer(&mut out, &json)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the byte offset of each character position, including the end of the text.
+fn byte_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(i, _)| i)
+        .chain([text.len()])
+        .collect()
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    if (args.scores || args.tag_scores)
+        && !matches!(args.format, OutputFormat::Tokenized | OutputFormat::Jsonl)
+    {
+        return Err("--scores and --tag-scores require --format tokenized or jsonl".into());
+    }
 
     let pre_filter = KyteaFullwidthFilter;
     let mut post_filters: Vec<Box<dyn SentenceFilter>> = vec![];
@@ -109,10 +323,53 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     eprintln!("Loading model file...");
     let mut f = zstd::Decoder::new(File::open(args.model)?)?;
     let model = Model::read(&mut f)?;
+    if !model.tag_schema().is_empty() {
+        let names: Vec<_> = model
+            .tag_schema()
+            .iter()
+            .map(|c| if c.name().is_empty() { "-" } else { c.name() })
+            .collect();
+        eprintln!("Tag columns: {}", names.join(", "));
+    }
+    let tag_columns = args
+        .tag_columns
+        .iter()
+        .map(|name| {
+            model
+                .tag_column_index(name)
+                .ok_or_else(|| format!("unknown tag column: {name}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let conllu_fields = if args.format == OutputFormat::Conllu && args.predict_tags {
+        let columns = if tag_columns.is_empty() {
+            (0..model.n_tag_columns()).collect()
+        } else {
+            tag_columns.clone()
+        };
+        columns
+            .into_iter()
+            .map(|i| {
+                let name = model.tag_schema().get(i).map_or("", |c| c.name());
+                name.parse().map_err(|_| {
+                    format!("--format conllu requires a name of tag column {i} (see --tag-names of train)")
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![]
+    };
     let mut predictor = Predictor::new(model, args.predict_tags)?;
     if args.tag_scores {
         predictor.store_tag_scores(true);
     }
+    predictor.constrain_tag_tuples(args.constrain_tag_tuples);
+    let output = Output {
+        format: args.format,
+        conllu_fields: &conllu_fields,
+        tag_columns: &tag_columns,
+        scores: args.scores,
+        tag_scores: args.tag_scores,
+    };
 
     let is_tty = atty::is(atty::Stream::Stdout);
 
@@ -131,16 +388,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 post_filters.iter().for_each(|filter| filter.filter(&mut s));
                 if args.predict_tags {
                     s.fill_tags();
+                    if !tag_columns.is_empty() {
+                        s.select_tag_columns(&tag_columns);
+                    }
                 }
-                s.write_tokenized_text(&mut buf);
-                out.write_all(buf.as_bytes())?;
-                if args.scores {
-                    print_scores(&s, &mut out)?;
-                }
-            }
-            out.write_all(b"\n")?;
-            if args.tag_scores {
-                print_tag_scores(&s, &mut out)?;
+                output.write_sentence(&s, &s, &mut buf, &mut out)?;
+            } else {
+                output.write_empty(&mut out)?;
             }
             if is_tty {
                 out.flush()?;
@@ -157,22 +411,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 post_filters.iter().for_each(|filter| filter.filter(&mut s));
                 if args.predict_tags {
                     s.fill_tags();
+                    if !tag_columns.is_empty() {
+                        s.select_tag_columns(&tag_columns);
+                    }
                 }
                 s_orig.update_raw(line)?;
                 s_orig.reset_tags(s.n_tags());
                 s_orig.boundaries_mut().copy_from_slice(s.boundaries());
                 s_orig.tags_mut().clone_from_slice(s.tags());
-                s_orig.write_tokenized_text(&mut buf);
-                out.write_all(buf.as_bytes())?;
-                out.write_all(b"\n")?;
-                if args.scores {
-                    print_scores(&s, &mut out)?;
-                }
+                output.write_sentence(&s_orig, &s, &mut buf, &mut out)?;
             } else {
-                out.write_all(b"\n")?;
-            }
-            if args.tag_scores {
-                print_tag_scores(&s, &mut out)?;
+                output.write_empty(&mut out)?;
             }
             if is_tty {
                 out.flush()?;
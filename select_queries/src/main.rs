@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Parser;
+use vaporetto::{Model, Predictor, Sentence, UncertaintySampler};
+use vaporetto_rules::{string_filters::KyteaFullwidthFilter, StringFilter};
+
+#[derive(Parser, Debug)]
+#[command(about = "A program to select uncertain boundaries from raw text for partial annotation.")]
+struct Args {
+    /// The model file to use when predicting boundaries
+    #[arg(long)]
+    model: PathBuf,
+
+    /// The number of boundaries to select
+    #[arg(long, default_value = "100")]
+    n_queries: usize,
+
+    /// The number of context characters printed on each side of a selected boundary
+    #[arg(long, default_value = "10")]
+    window: usize,
+
+    /// Do not normalize input strings before prediction.
+    #[arg(long)]
+    no_norm: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let pre_filter = KyteaFullwidthFilter;
+
+    eprintln!("Loading model file...");
+    let mut f = zstd::Decoder::new(File::open(args.model)?)?;
+    let model = Model::read(&mut f)?;
+    let predictor = Predictor::new(model, false)?;
+
+    eprintln!("Start prediction");
+    let mut lines = vec![];
+    let mut sampler = UncertaintySampler::new(args.n_queries);
+    let mut s = Sentence::default();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let result = if args.no_norm {
+            s.update_raw(line.clone())
+        } else {
+            s.update_raw(pre_filter.filter(&line))
+        };
+        if result.is_ok() {
+            predictor.predict(&mut s);
+            sampler.add_sentence(lines.len(), &s);
+        }
+        lines.push(line);
+    }
+
+    // Queries are printed with the original text.
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut buf = String::new();
+    let mut s = Sentence::default();
+    for query in sampler.into_queries() {
+        s.update_raw(lines[query.sentence_id].as_str())?;
+        query.write_partial_annotation_text(&s, args.window, &mut buf);
+        out.write_all(buf.as_bytes())?;
+        out.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
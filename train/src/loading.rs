@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use clap::ValueEnum;
+use vaporetto::errors::VaporettoError;
+
+/// Behavior on malformed lines in corpora.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OnError {
+    /// Abort loading.
+    Abort,
+
+    /// Skip malformed lines with warnings.
+    Skip,
+
+    /// Repair common issues of malformed lines, and skip lines that cannot be repaired.
+    Fix,
+}
+
+/// Repairs a tokenized text: removes NULs, trims leading and trailing whitespaces, collapses
+/// consecutive spaces, and escapes slashes that do not follow a character.
+pub fn fix_tokenized(text: &str) -> String {
+    let mut result = String::new();
+    let mut escape = false;
+    let mut space = false;
+    for c in text.trim().chars() {
+        match (escape, c) {
+            (_, '\0') => (),
+            (false, '\\') => escape = true,
+            (false, ' ') => space = !result.is_empty(),
+            (false, '/') if result.is_empty() || space => {
+                if space {
+                    result.push(' ');
+                    space = false;
+                }
+                result.push_str("\\/");
+            }
+            _ => {
+                if space {
+                    result.push(' ');
+                    space = false;
+                }
+                if escape {
+                    result.push('\\');
+                    escape = false;
+                }
+                result.push(c);
+            }
+        }
+    }
+    result
+}
+
+/// Repairs a partially annotated text: removes NULs and whitespaces at the beginning together
+/// with their boundaries, and removes trailing whitespaces and boundaries.
+pub fn fix_partial_annotation(text: &str) -> String {
+    let mut result = String::new();
+    let mut is_char = true;
+    let mut escape = false;
+    // Whether the current character and its annotation are removed.
+    let mut remove = false;
+    for c in text.trim_end().chars() {
+        if is_char {
+            remove = c == '\0' || (result.is_empty() && c.is_whitespace());
+            is_char = false;
+            if !remove {
+                result.push(c);
+            }
+            continue;
+        }
+        match (escape, c) {
+            (false, '\\') => escape = true,
+            (false, ' ' | '-' | '|') => is_char = true,
+            _ => escape = false,
+        }
+        if !remove && c != '\0' {
+            result.push(c);
+        }
+    }
+    // The last character is missing or removed.
+    if is_char || remove {
+        result.pop();
+    }
+    result
+}
+
+/// Loads sentences from corpora, handling malformed lines according to [`OnError`].
+pub struct CorpusLoader {
+    on_error: OnError,
+    n_fixed: usize,
+    n_skipped: usize,
+    // The number of skipped lines for each error message.
+    skipped_errors: BTreeMap<String, usize>,
+}
+
+impl CorpusLoader {
+    pub fn new(on_error: OnError) -> Self {
+        Self {
+            on_error,
+            n_fixed: 0,
+            n_skipped: 0,
+            skipped_errors: BTreeMap::new(),
+        }
+    }
+
+    /// Parses a line (or a block of lines) of a corpus. If the line is malformed, the error is
+    /// reported with its position as `file:line:col`. With [`OnError::Fix`], `fix` is applied to
+    /// the line and the result is parsed again. Returns `None` if the line is skipped.
+    pub fn parse<T, E, P>(
+        &mut self,
+        path: &Path,
+        line_no: usize,
+        line: &str,
+        parse: P,
+        fix: Option<fn(&str) -> String>,
+    ) -> Result<Option<T>, Box<dyn Error>>
+    where
+        P: Fn(&str) -> Result<T, E>,
+        E: Into<Box<dyn Error>>,
+    {
+        let e: Box<dyn Error> = match parse(line) {
+            Ok(s) => return Ok(Some(s)),
+            Err(e) => e.into(),
+        };
+        let parse_error = match e.downcast_ref() {
+            Some(VaporettoError::Parse(e)) => Some(e),
+            _ => None,
+        };
+        let pos = match parse_error {
+            Some(e) => format!(
+                "{}:{}:{}",
+                path.display(),
+                line_no + e.line(),
+                e.column() + 1,
+            ),
+            None => format!("{}:{line_no}", path.display()),
+        };
+        match self.on_error {
+            OnError::Abort => return Err(format!("{pos}: {e}").into()),
+            OnError::Fix => {
+                if let Some(s) = fix.and_then(|fix| parse(&fix(line)).ok()) {
+                    eprintln!("warning: {pos}: {e}; fixed");
+                    self.n_fixed += 1;
+                    return Ok(Some(s));
+                }
+            }
+            OnError::Skip => (),
+        }
+        eprintln!("warning: {pos}: {e}; skipped");
+        self.n_skipped += 1;
+        let msg = match parse_error {
+            Some(e) => e.msg().to_string(),
+            None => e.to_string(),
+        };
+        *self.skipped_errors.entry(msg).or_default() += 1;
+        Ok(None)
+    }
+}
+
+impl fmt::Display for CorpusLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "# of fixed lines: {}", self.n_fixed)?;
+        write!(f, "\n# of skipped lines: {}", self.n_skipped)?;
+        for (msg, n) in &self.skipped_errors {
+            write!(f, "\n  {msg}: {n}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use vaporetto::Sentence;
+
+    #[test]
+    fn test_fix_tokenized() {
+        assert_eq!("これ/代名詞 は", fix_tokenized("これ/代名詞 は"));
+        assert_eq!("a b c", fix_tokenized("  a   b\0 c  "));
+        assert_eq!("\\/a b \\/", fix_tokenized("/a b /"));
+        assert_eq!("a\\/b c/x", fix_tokenized("a\\/b c/x"));
+        assert_eq!("", fix_tokenized(" \0 "));
+    }
+
+    #[test]
+    fn test_fix_tokenized_parsable() {
+        for text in ["  a   b  ", "/a b /", "a\0 b", "a\0/ b"] {
+            let fixed = fix_tokenized(text);
+            assert!(
+                Sentence::from_tokenized(&fixed).is_ok(),
+                "{text:?} -> {fixed:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_fix_partial_annotation() {
+        assert_eq!("a|b-c", fix_partial_annotation("a|b-c"));
+        assert_eq!("a|b", fix_partial_annotation(" |a|b"));
+        assert_eq!("a|b", fix_partial_annotation("a|b| "));
+        assert_eq!("a|b", fix_partial_annotation("a|b|"));
+        assert_eq!("a|b", fix_partial_annotation("a|\0 b"));
+        assert_eq!("", fix_partial_annotation("  "));
+    }
+
+    #[test]
+    fn test_fix_partial_annotation_parsable() {
+        for text in [" |a|b", "a|b| ", "a|b|", "a|\0 b"] {
+            let fixed = fix_partial_annotation(text);
+            assert!(
+                Sentence::from_partial_annotation(&fixed).is_ok(),
+                "{text:?} -> {fixed:?}",
+            );
+        }
+    }
+}
@@ -1,12 +1,387 @@
-use std::collections::BTreeSet;
+mod loading;
+mod validation;
+
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
 use std::io::{prelude::*, stderr, BufReader};
 use std::path::PathBuf;
+use std::str::FromStr;
 
-use clap::{ArgGroup, Parser};
-use vaporetto::{Sentence, SolverType, Trainer};
+use clap::{ArgGroup, Parser, ValueEnum};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use vaporetto::{
+    errors::VaporettoError, ConfidenceMode, ConlluField, Model, Sentence, SolverType, TagColumn,
+    TagModel, Trainer, MAX_EXAMPLE_WEIGHT,
+};
 use vaporetto_rules::{string_filters::KyteaFullwidthFilter, StringFilter};
 
+use loading::{CorpusLoader, OnError};
+use validation::Scores;
+
+#[derive(Clone, Debug)]
+struct NamedDict {
+    name: String,
+    path: PathBuf,
+}
+
+impl FromStr for NamedDict {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, path) = s
+            .split_once('=')
+            .ok_or("Could not parse a NAME=PATH value")?;
+        if name.is_empty() {
+            return Err("The dictionary name must not be empty");
+        }
+        Ok(Self {
+            name: name.to_string(),
+            path: path.into(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SearchStrategy {
+    /// Tries all combinations of the candidates.
+    Grid,
+
+    /// Tries randomly sampled combinations of the candidates.
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SelectionMetric {
+    /// Boundary F1 score
+    Boundary,
+
+    /// Word-level F1 score including tags
+    Tag,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct HyperParams {
+    charw: u8,
+    charn: u8,
+    typew: u8,
+    typen: u8,
+    cost: f64,
+}
+
+impl HyperParams {
+    /// Returns hyperparameters that determine features.
+    const fn feature_params(&self) -> (u8, u8, u8, u8) {
+        (self.charw, self.charn, self.typew, self.typen)
+    }
+}
+
+impl fmt::Display for HyperParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "charw={} charn={} typew={} typen={} cost={}",
+            self.charw, self.charn, self.typew, self.typen, self.cost
+        )
+    }
+}
+
+/// Candidates of each hyperparameter.
+struct SearchSpace {
+    cost: Vec<f64>,
+    charw: Vec<u8>,
+    charn: Vec<u8>,
+    typew: Vec<u8>,
+    typen: Vec<u8>,
+}
+
+impl SearchSpace {
+    /// Returns all combinations. Candidates with the same features are adjacent, so that their
+    /// features are extracted only once.
+    fn grid(&self) -> Vec<HyperParams> {
+        let mut candidates = vec![];
+        for &charw in &self.charw {
+            for &charn in &self.charn {
+                for &typew in &self.typew {
+                    for &typen in &self.typen {
+                        for &cost in &self.cost {
+                            candidates.push(HyperParams {
+                                charw,
+                                charn,
+                                typew,
+                                typen,
+                                cost,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Returns randomly sampled combinations. Candidates with the same features are sorted to be
+    /// adjacent, so that their features are extracted only once.
+    fn random(&self, n_trials: usize, seed: u64) -> Vec<HyperParams> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut candidates: Vec<_> = (0..n_trials)
+            .map(|_| HyperParams {
+                charw: *self.charw.choose(&mut rng).unwrap(),
+                charn: *self.charn.choose(&mut rng).unwrap(),
+                typew: *self.typew.choose(&mut rng).unwrap(),
+                typen: *self.typen.choose(&mut rng).unwrap(),
+                cost: *self.cost.choose(&mut rng).unwrap(),
+            })
+            .collect();
+        candidates.sort_by_key(HyperParams::feature_params);
+        candidates
+    }
+}
+
+fn or_default<T>(candidates: Vec<T>, default: T) -> Vec<T> {
+    if candidates.is_empty() {
+        vec![default]
+    } else {
+        candidates
+    }
+}
+
+/// A training sentence.
+struct Example {
+    sentence: Sentence<'static, 'static>,
+    weight: u32,
+
+    /// Confidences of the boundaries given by --soft.
+    confidences: Option<Vec<f64>>,
+}
+
+/// Settings shared by all training runs.
+struct TrainConfig<'a> {
+    dictionaries: &'a BTreeMap<String, BTreeMap<String, Option<String>>>,
+    tag_dictionary: &'a [Sentence<'static, 'static>],
+    dictn: u8,
+    dict_word_min_freq: Option<u32>,
+    use_categories: bool,
+    quantize_bits: u8,
+    narrow_weights: bool,
+    confidence_mode: ConfidenceMode,
+    tag_cost: &'a [f64],
+    tag_solver: &'a [SolverType],
+    fallback_tag_columns: &'a [usize],
+    tag_schema: &'a [TagColumn],
+    eps: f64,
+    solver: SolverType,
+
+    /// The serialized model whose tag models are retrained.
+    base_model: Option<&'a [u8]>,
+    append_tags: bool,
+}
+
+impl<'a> TrainConfig<'a> {
+    /// Creates a trainer and extracts features of the examples.
+    fn extract(
+        &self,
+        params: &HyperParams,
+        examples: &'a [Example],
+    ) -> Result<Trainer<'a>, Box<dyn std::error::Error>> {
+        eprintln!("Extracting into features...");
+        // The unnamed dictionary is stored with an empty name.
+        let (dictionary, dict_categories): (Vec<_>, Vec<_>) = self
+            .dictionaries
+            .get("")
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .unzip();
+        let mut trainer = Trainer::new(
+            params.charw,
+            params.charn,
+            params.typew,
+            params.typen,
+            dictionary,
+            self.dictn,
+            self.tag_dictionary,
+        )?;
+        if self.use_categories {
+            trainer.set_dict_word_categories("", dict_categories)?;
+        }
+        for (name, dictionary) in self
+            .dictionaries
+            .iter()
+            .filter(|(name, _)| !name.is_empty())
+        {
+            let (dictionary, dict_categories): (Vec<_>, Vec<_>) =
+                dictionary.clone().into_iter().unzip();
+            trainer.add_dictionary(name.clone(), dictionary)?;
+            if self.use_categories {
+                trainer.set_dict_word_categories(name, dict_categories)?;
+            }
+        }
+        if let Some(min_freq) = self.dict_word_min_freq {
+            trainer.learn_dict_word_weights(min_freq)?;
+        }
+        trainer.set_quantize_bit_depth(self.quantize_bits)?;
+        trainer.set_narrow_weights(self.narrow_weights);
+        if !self.fallback_tag_columns.is_empty() {
+            trainer.set_fallback_tag_columns(self.fallback_tag_columns.to_vec())?;
+        }
+        if !self.tag_schema.is_empty() {
+            trainer.set_tag_schema(self.tag_schema.to_vec())?;
+        }
+        for (i, example) in examples.iter().enumerate() {
+            if i % 10000 == 0 {
+                eprint!("# of features: {}\r", trainer.n_features(),);
+                stderr().flush()?;
+            }
+            if let Some(confidences) = &example.confidences {
+                trainer.add_example_with_confidences(
+                    &example.sentence,
+                    confidences,
+                    self.confidence_mode,
+                )?;
+            } else {
+                trainer.add_weighted_example(&example.sentence, example.weight)?;
+            }
+        }
+        eprintln!("# of features: {}", trainer.n_features(),);
+        Ok(trainer)
+    }
+
+    /// Sets hyperparameters of tag columns, which depend on the cost.
+    fn set_tag_column_params(&self, trainer: &mut Trainer<'a>, params: &HyperParams) {
+        for column in 0..self.tag_cost.len().max(self.tag_solver.len()) {
+            trainer.set_tag_column_params(
+                column,
+                self.eps,
+                self.tag_cost.get(column).copied().unwrap_or(params.cost),
+                self.tag_solver.get(column).copied().unwrap_or(self.solver),
+            );
+        }
+    }
+
+    /// Attaches retrained tag models to the base model.
+    fn attach_tag_models(
+        &self,
+        base_model: &[u8],
+        tag_models: Vec<TagModel>,
+    ) -> Result<Model, Box<dyn std::error::Error>> {
+        let mut model = Model::read_slice(base_model)?.0;
+        let mut tag_schema = vec![];
+        if self.append_tags {
+            tag_schema = model.tag_schema().to_vec();
+            tag_schema.resize(model.n_tag_columns(), TagColumn::default());
+            model.append_tag_columns(tag_models)?;
+        } else {
+            model.replace_tag_models(tag_models)?;
+        }
+        if !self.tag_schema.is_empty() {
+            tag_schema.extend_from_slice(self.tag_schema);
+            model.set_tag_schema(tag_schema)?;
+        }
+        Ok(model)
+    }
+
+    /// Prints the end of training, and whether the fallback tag model was trained.
+    fn print_finish(tag_models: &[TagModel]) {
+        if tag_models.iter().any(TagModel::is_fallback) {
+            eprintln!("Trained the fallback tag model.");
+        }
+        eprintln!("Finish training.");
+    }
+
+    /// Trains a model from all examples added to the trainer.
+    fn train(
+        &self,
+        mut trainer: Trainer<'a>,
+        params: &HyperParams,
+    ) -> Result<Model, Box<dyn std::error::Error>> {
+        self.set_tag_column_params(&mut trainer, params);
+        if let Some(base_model) = self.base_model {
+            eprintln!("Start training tags...");
+            let tag_models = trainer.train_tags(self.eps, params.cost, self.solver)?;
+            Self::print_finish(&tag_models);
+            return self.attach_tag_models(base_model, tag_models);
+        }
+        eprintln!("Start training...");
+        let model = trainer.train(self.eps, params.cost, self.solver)?;
+        Self::print_finish(model.tag_models());
+        Ok(model)
+    }
+
+    /// Trains a model from examples whose indices satisfy `filter` without extracting features
+    /// again.
+    fn train_subset(
+        &self,
+        trainer: &mut Trainer<'a>,
+        params: &HyperParams,
+        filter: impl Fn(usize) -> bool,
+    ) -> Result<Model, Box<dyn std::error::Error>> {
+        self.set_tag_column_params(trainer, params);
+        if let Some(base_model) = self.base_model {
+            eprintln!("Start training tags...");
+            let tag_models =
+                trainer.train_tags_subset(self.eps, params.cost, self.solver, filter)?;
+            Self::print_finish(&tag_models);
+            return self.attach_tag_models(base_model, tag_models);
+        }
+        eprintln!("Start training...");
+        let model = trainer.train_subset(self.eps, params.cost, self.solver, filter)?;
+        Self::print_finish(model.tag_models());
+        Ok(model)
+    }
+}
+
+/// Returns the cached trainer if its features are extracted with the same hyperparameters, or
+/// extracts features again.
+fn cached_trainer<'c, 'a>(
+    cache: &'c mut Option<(HyperParams, Trainer<'a>)>,
+    config: &TrainConfig<'a>,
+    params: &HyperParams,
+    examples: &'a [Example],
+) -> Result<&'c mut Trainer<'a>, Box<dyn std::error::Error>> {
+    if cache
+        .as_ref()
+        .is_none_or(|(cached, _)| cached.feature_params() != params.feature_params())
+    {
+        // Drops the old trainer before extracting features.
+        *cache = None;
+        *cache = Some((*params, config.extract(params, examples)?));
+    }
+    Ok(&mut cache.as_mut().unwrap().1)
+}
+
+fn normalize(s: Sentence<'static, 'static>) -> Result<Sentence<'static, 'static>, VaporettoError> {
+    let new_line = KyteaFullwidthFilter.filter(s.as_raw_text());
+    let mut new_s = Sentence::from_raw(new_line)?;
+    new_s.boundaries_mut().copy_from_slice(s.boundaries());
+    new_s.reset_tags(s.n_tags());
+    new_s.tags_mut().clone_from_slice(s.tags());
+    Ok(new_s)
+}
+
+/// Parses a line of `--soft`, which consists of a tokenized text and confidences of boundaries
+/// separated by a tab.
+fn parse_soft_line(
+    line: &str,
+) -> Result<(Sentence<'static, 'static>, Vec<f64>), Box<dyn std::error::Error>> {
+    let (tokenized, confidences) = line
+        .rsplit_once('\t')
+        .ok_or("each line of --soft must contain confidences after a tab")?;
+    let s = Sentence::from_tokenized(tokenized)?;
+    let confidences = confidences
+        .split_ascii_whitespace()
+        .map(f64::from_str)
+        .collect::<Result<Vec<_>, _>>()?;
+    if confidences.len() != s.boundaries().len() {
+        return Err(
+            "the number of confidences must be one less than the number of characters".into(),
+        );
+    }
+    if confidences.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        return Err("confidences must be in the range of [0, 1]".into());
+    }
+    Ok((s, confidences))
+}
+
 #[derive(Parser, Debug)]
 #[command(
     about = "A program to train models of Vaporetto.",
@@ -21,10 +396,62 @@ struct Args {
     #[arg(long, group = "dataset")]
     part: Vec<PathBuf>,
 
+    /// A training corpus in the CoNLL-U format, e.g., a treebank of Universal Dependencies.
+    /// Sentences are separated by empty lines.
+    #[arg(long, group = "dataset")]
+    conllu: Vec<PathBuf>,
+
+    /// Comma-separated fields of the corpora given by --conllu stored in tag columns in order:
+    /// {lemma, upos, xpos, feats} or an attribute name of the MISC field. If --tag-names or
+    /// --lemma-columns is not specified, it is derived from these fields.
+    #[arg(long, value_delimiter = ',', default_value = "upos")]
+    conllu_fields: Vec<ConlluField>,
+
+    /// A training corpus annotated by another analyzer. Each line consists of a tokenized
+    /// sentence and space-separated confidences of its character boundaries, separated by a tab.
+    #[arg(long, group = "dataset")]
+    soft: Vec<PathBuf>,
+
+    /// Boundaries in the corpora given by --soft with confidences lower than this value are
+    /// ignored.
+    #[arg(long, default_value = "0")]
+    soft_threshold: f64,
+
+    /// Weights each boundary in the corpora given by --soft by its confidence multiplied by this
+    /// value, instead of using --soft-threshold. {1..=100}
+    #[arg(
+        long,
+        conflicts_with = "soft_threshold",
+        value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_EXAMPLE_WEIGHT)),
+    )]
+    soft_weight_scale: Option<u32>,
+
+    /// Comma-separated weights of the corpora given by --tok in the same order. Sentences in a
+    /// corpus with weight N are treated as if they appeared N times. {0..=100}
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(..=i64::from(MAX_EXAMPLE_WEIGHT)),
+    )]
+    tok_weight: Vec<u32>,
+
+    /// Comma-separated weights of the corpora given by --part in the same order. {0..=100}
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u32).range(..=i64::from(MAX_EXAMPLE_WEIGHT)),
+    )]
+    part_weight: Vec<u32>,
+
     /// A word dictionary file
     #[arg(long)]
     dict: Vec<PathBuf>,
 
+    /// A word dictionary file with a name in the form of NAME=PATH. Each named dictionary is
+    /// trained in its own feature space and stored separately in the model.
+    #[arg(long)]
+    named_dict: Vec<NamedDict>,
+
     /// The file to write the trained model to
     #[arg(long)]
     model: PathBuf,
@@ -50,6 +477,16 @@ struct Args {
     #[arg(long, default_value = "4")]
     dictn: u8,
 
+    /// Dictionary words appearing at least this number of times as tokens in the training corpus
+    /// get their own weights. Other words share weights with words of the same length.
+    #[arg(long)]
+    dict_word_min_freq: Option<u32>,
+
+    /// The index of the tag used as a category of dictionary words. Words in the same category
+    /// share weights. The categories are stored as comments of dictionary words in the model.
+    #[arg(long)]
+    dict_category_tag: Option<usize>,
+
     /// The epsilon stopping criterion for classifier training
     #[arg(long, default_value = "0.01")]
     eps: f64,
@@ -58,14 +495,109 @@ struct Args {
     #[arg(long, default_value = "1.0")]
     cost: f64,
 
+    /// The bit depth used to quantize weights. Smaller values make models smaller. {2..=24}
+    #[arg(long, default_value = "16")]
+    quantize_bits: u8,
+
+    /// Quantizes weights more coarsely so that the predictor stores them in 8-bit or 16-bit
+    /// integers. Requires --quantize-bits of at most 16.
+    #[arg(long)]
+    narrow_weights: bool,
+
     /// The solver. {0, 1, 2, 3, 4, 5, 6, 7} (see LIBLINEAR documentation for more details)
     #[arg(long)]
     solver: SolverType,
 
+    /// Comma-separated costs of tag columns in order, overriding --cost for tags
+    #[arg(long, value_delimiter = ',')]
+    tag_cost: Vec<f64>,
+
+    /// Comma-separated solvers of tag columns in order, overriding --solver for tags
+    #[arg(long, value_delimiter = ',')]
+    tag_solver: Vec<SolverType>,
+
+    /// Comma-separated tag columns predicted for tokens without their own tag models, e.g., `0`
+    /// for parts of speech. The fallback model uses suffixes and character types of tokens.
+    #[arg(long, value_delimiter = ',')]
+    fallback_tag_columns: Vec<usize>,
+
+    /// Comma-separated names of tag columns in order, e.g., `pos,pron`. With --append-tags, the
+    /// names are given to the appended columns.
+    #[arg(long, value_delimiter = ',')]
+    tag_names: Vec<String>,
+
+    /// Comma-separated tag columns containing lemmas, which are trained as edit scripts from
+    /// surfaces. Specify them also in --fallback-tag-columns to predict lemmas of unseen words.
+    #[arg(long, value_delimiter = ',')]
+    lemma_columns: Vec<usize>,
+
+    /// An existing model. If specified, only tags are trained and attached to this model without
+    /// changing word boundaries.
+    #[arg(long)]
+    base_model: Option<PathBuf>,
+
+    /// Appends the trained tag columns to those of --base-model instead of replacing them.
+    #[arg(long, requires = "base_model")]
+    append_tags: bool,
+
+    /// A tokenized development corpus. Boundary and tag F1 scores on this corpus are reported
+    /// after training.
+    #[arg(long)]
+    dev: Vec<PathBuf>,
+
+    /// Performs K-fold cross-validation on the training corpus before training the final model
+    #[arg(long, value_name = "K")]
+    folds: Option<usize>,
+
+    /// Searches for the hyperparameters with the best score on the development corpus, or in
+    /// cross-validation if --folds is specified, and writes the best model
+    #[arg(long)]
+    search: Option<SearchStrategy>,
+
+    /// Comma-separated candidates of --cost for the search
+    #[arg(long, value_delimiter = ',')]
+    search_cost: Vec<f64>,
+
+    /// Comma-separated candidates of --charw for the search
+    #[arg(long, value_delimiter = ',')]
+    search_charw: Vec<u8>,
+
+    /// Comma-separated candidates of --charn for the search
+    #[arg(long, value_delimiter = ',')]
+    search_charn: Vec<u8>,
+
+    /// Comma-separated candidates of --typew for the search
+    #[arg(long, value_delimiter = ',')]
+    search_typew: Vec<u8>,
+
+    /// Comma-separated candidates of --typen for the search
+    #[arg(long, value_delimiter = ',')]
+    search_typen: Vec<u8>,
+
+    /// The number of trials of the random search
+    #[arg(long, default_value = "10")]
+    search_trials: usize,
+
+    /// The random seed of the random search
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// The score used to select the best hyperparameters
+    #[arg(long, value_enum, default_value = "boundary")]
+    select_by: SelectionMetric,
+
     /// Do not normalize training data.
     #[arg(long)]
     no_norm: bool,
 
+    /// Behavior on malformed lines in the corpora and dictionaries. Errors are reported as `file:line:col`.
+    #[arg(long, value_enum, default_value = "abort")]
+    on_error: OnError,
+
+    /// Equivalent to `--on-error skip`
+    #[arg(long, conflicts_with = "on_error")]
+    skip_invalid_lines: bool,
+
     /// The number of workers for zstd (0 means multithreaded will be disabled)
     #[arg(long, default_value = "0")]
     zstd_workers: u32,
@@ -74,113 +606,418 @@ struct Args {
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let fullwidth_filter = KyteaFullwidthFilter;
-
     eprintln!("Loading dataset...");
     let mut train_sents = vec![];
+    let on_error = if args.skip_invalid_lines {
+        OnError::Skip
+    } else {
+        args.on_error
+    };
+    let mut loader = CorpusLoader::new(on_error);
 
-    for path in args.tok {
+    if !args.tok_weight.is_empty() && args.tok_weight.len() != args.tok.len() {
+        return Err("the number of --tok-weight values must match the number of --tok".into());
+    }
+    if !args.part_weight.is_empty() && args.part_weight.len() != args.part.len() {
+        return Err("the number of --part-weight values must match the number of --part".into());
+    }
+    let tok_weights = args.tok_weight.into_iter().chain(std::iter::repeat(1));
+    let part_weights = args.part_weight.into_iter().chain(std::iter::repeat(1));
+
+    for (path, weight) in args.tok.into_iter().zip(tok_weights) {
         eprintln!("Loading {path:?} ...");
-        let f = File::open(path)?;
+        let f = File::open(&path)?;
         let f = BufReader::new(f);
         for (i, line) in f.lines().enumerate() {
             if i % 10000 == 0 {
                 eprint!("# of sentences: {i}\r");
                 stderr().flush()?;
             }
-            let s = Sentence::from_tokenized(&line?)?;
-            let s = if args.no_norm {
-                s
-            } else {
-                let new_line = fullwidth_filter.filter(s.as_raw_text());
-                let mut new_s = Sentence::from_raw(new_line)?;
-                new_s.boundaries_mut().clone_from_slice(s.boundaries());
-                new_s.reset_tags(s.n_tags());
-                new_s.tags_mut().clone_from_slice(s.tags());
-                new_s
+            let Some(s) = loader.parse(
+                &path,
+                i + 1,
+                &line?,
+                Sentence::from_tokenized,
+                Some(loading::fix_tokenized),
+            )?
+            else {
+                continue;
             };
-            train_sents.push(s);
+            let s = if args.no_norm { s } else { normalize(s)? };
+            train_sents.push(Example {
+                sentence: s,
+                weight,
+                confidences: None,
+            });
         }
         eprintln!("# of sentences: {}", train_sents.len());
     }
-    for path in args.part {
+    for (path, weight) in args.part.into_iter().zip(part_weights) {
+        eprintln!("Loading {path:?} ...");
+        let f = File::open(&path)?;
+        let f = BufReader::new(f);
+        for (i, line) in f.lines().enumerate() {
+            if i % 10000 == 0 {
+                eprint!("# of sentences: {i}\r");
+                stderr().flush()?;
+            }
+            let Some(s) = loader.parse(
+                &path,
+                i + 1,
+                &line?,
+                Sentence::from_partial_annotation,
+                Some(loading::fix_partial_annotation),
+            )?
+            else {
+                continue;
+            };
+            let s = if args.no_norm { s } else { normalize(s)? };
+            train_sents.push(Example {
+                sentence: s,
+                weight,
+                confidences: None,
+            });
+        }
+        eprintln!("# of sentences: {}", train_sents.len());
+    }
+
+    for path in &args.conllu {
         eprintln!("Loading {path:?} ...");
         let f = File::open(path)?;
         let f = BufReader::new(f);
+        let mut lines = f.lines();
+        let mut block = String::new();
+        // The line number of the first line of the block.
+        let mut block_line_no = 0;
+        let mut line_no = 0;
+        loop {
+            let line = lines.next().transpose()?;
+            let eof = line.is_none();
+            line_no += 1;
+            if let Some(line) = line.filter(|line| !line.trim().is_empty()) {
+                if block.is_empty() {
+                    block_line_no = line_no;
+                }
+                block.push_str(&line);
+                block.push('\n');
+                continue;
+            }
+            if !block.is_empty() {
+                if train_sents.len() % 10000 == 0 {
+                    eprint!("# of sentences: {}\r", train_sents.len());
+                    stderr().flush()?;
+                }
+                let s = loader.parse(
+                    path,
+                    block_line_no,
+                    &block,
+                    |block| Sentence::from_conllu(block, &args.conllu_fields),
+                    None,
+                )?;
+                block.clear();
+                let Some(s) = s else {
+                    continue;
+                };
+                let s = if args.no_norm { s } else { normalize(s)? };
+                train_sents.push(Example {
+                    sentence: s,
+                    weight: 1,
+                    confidences: None,
+                });
+            }
+            if eof {
+                break;
+            }
+        }
+        eprintln!("# of sentences: {}", train_sents.len());
+    }
+
+    for path in args.soft {
+        eprintln!("Loading {path:?} ...");
+        let f = File::open(&path)?;
+        let f = BufReader::new(f);
         for (i, line) in f.lines().enumerate() {
             if i % 10000 == 0 {
                 eprint!("# of sentences: {i}\r");
                 stderr().flush()?;
             }
-            let s = Sentence::from_partial_annotation(&line?)?;
-            let s = if args.no_norm {
-                s
-            } else {
-                let new_line = fullwidth_filter.filter(s.as_raw_text());
-                let mut new_s = Sentence::from_raw(new_line)?;
-                new_s.boundaries_mut().copy_from_slice(s.boundaries());
-                new_s.reset_tags(s.n_tags());
-                new_s.tags_mut().clone_from_slice(s.tags());
-                new_s
+            // Repairing the text may change the number of characters, so malformed lines are not
+            // fixed.
+            let Some((s, confidences)) =
+                loader.parse(&path, i + 1, &line?, parse_soft_line, None)?
+            else {
+                continue;
             };
-            train_sents.push(s);
+            let s = if args.no_norm { s } else { normalize(s)? };
+            train_sents.push(Example {
+                sentence: s,
+                weight: 1,
+                confidences: Some(confidences),
+            });
         }
         eprintln!("# of sentences: {}", train_sents.len());
     }
 
     let mut tag_dictionary = vec![];
-    let mut dictionary = BTreeSet::new();
-    for path in args.dict {
+    // The unnamed dictionary is stored with an empty name.
+    let mut dictionaries: BTreeMap<_, BTreeMap<_, _>> = BTreeMap::new();
+    let dict_paths = args.dict.into_iter().map(|path| (String::new(), path));
+    let named_dict_paths = args
+        .named_dict
+        .into_iter()
+        .map(|NamedDict { name, path }| (name, path));
+    for (name, path) in dict_paths.chain(named_dict_paths) {
         eprintln!("Loading {path:?} ...");
-        let f = File::open(path)?;
+        let dictionary = dictionaries.entry(name).or_default();
+        let f = File::open(&path)?;
         let f = BufReader::new(f);
-        for line in f.lines() {
+        for (i, line) in f.lines().enumerate() {
             if dictionary.len() % 10000 == 0 {
                 eprint!("# of words: {}\r", dictionary.len());
                 stderr().flush()?;
             }
-            let s = Sentence::from_tokenized(&line?)?;
-            let s = if args.no_norm {
-                s
-            } else {
-                let new_line = fullwidth_filter.filter(s.as_raw_text());
-                let mut new_s = Sentence::from_raw(new_line)?;
-                new_s.boundaries_mut().clone_from_slice(s.boundaries());
-                new_s.reset_tags(s.n_tags());
-                new_s.tags_mut().clone_from_slice(s.tags());
-                new_s
+            let Some(s) = loader.parse(
+                &path,
+                i + 1,
+                &line?,
+                Sentence::from_tokenized,
+                Some(loading::fix_tokenized),
+            )?
+            else {
+                continue;
             };
+            let s = if args.no_norm { s } else { normalize(s)? };
             for token in s.iter_tokens() {
-                dictionary.insert(token.surface().to_string());
+                let category = args.dict_category_tag.and_then(|idx| {
+                    token
+                        .tags()
+                        .get(idx)
+                        .and_then(|tag| tag.as_ref())
+                        .map(|tag| tag.to_string())
+                });
+                dictionary
+                    .entry(token.surface().to_string())
+                    .or_insert(category);
             }
             tag_dictionary.push(s);
         }
         eprintln!("# of words: {}", dictionary.len());
     }
-    let dictionary = dictionary.into_iter().collect();
-
-    eprintln!("Extracting into features...");
-    let mut trainer = Trainer::new(
-        args.charw,
-        args.charn,
-        args.typew,
-        args.typen,
-        dictionary,
-        args.dictn,
-        &tag_dictionary,
-    )?;
-    for (i, s) in train_sents.iter().enumerate() {
-        if i % 10000 == 0 {
-            eprint!("# of features: {}\r", trainer.n_features(),);
-            stderr().flush()?;
-        }
-        trainer.add_example(s);
-    }
-    eprintln!("# of features: {}", trainer.n_features(),);
-
-    eprintln!("Start training...");
-    let model = trainer.train(args.eps, args.cost, args.solver)?;
-    eprintln!("Finish training.");
+
+    let mut dev_sents = vec![];
+    for path in args.dev {
+        eprintln!("Loading {path:?} ...");
+        let f = File::open(&path)?;
+        let f = BufReader::new(f);
+        for (i, line) in f.lines().enumerate() {
+            let Some(s) = loader.parse(
+                &path,
+                i + 1,
+                &line?,
+                Sentence::from_tokenized,
+                Some(loading::fix_tokenized),
+            )?
+            else {
+                continue;
+            };
+            let s = if args.no_norm { s } else { normalize(s)? };
+            dev_sents.push(s);
+        }
+        eprintln!("# of sentences: {}", dev_sents.len());
+    }
+    if on_error != OnError::Abort {
+        eprintln!("{loader}");
+    }
+
+    let default_params = HyperParams {
+        charw: args.charw,
+        charn: args.charn,
+        typew: args.typew,
+        typen: args.typen,
+        cost: args.cost,
+    };
+    let candidates = match args.search {
+        Some(strategy) => {
+            if args.folds.is_none() && dev_sents.is_empty() {
+                return Err("--search requires --dev or --folds".into());
+            }
+            let space = SearchSpace {
+                cost: or_default(args.search_cost, args.cost),
+                charw: or_default(args.search_charw, args.charw),
+                charn: or_default(args.search_charn, args.charn),
+                typew: or_default(args.search_typew, args.typew),
+                typen: or_default(args.search_typen, args.typen),
+            };
+            match strategy {
+                SearchStrategy::Grid => space.grid(),
+                SearchStrategy::Random => space.random(args.search_trials, args.seed),
+            }
+        }
+        None => vec![default_params],
+    };
+    if candidates.is_empty() {
+        return Err("--search-trials must be at least 1".into());
+    }
+    if let Some(folds) = args.folds {
+        if folds < 2 || folds > train_sents.len() {
+            return Err("--folds must be at least 2 and at most the number of sentences".into());
+        }
+    }
+
+    let base_model = if let Some(path) = args.base_model {
+        eprintln!("Loading {path:?} ...");
+        let mut f = zstd::Decoder::new(File::open(path)?)?;
+        Some(Model::read(&mut f)?.to_vec()?)
+    } else {
+        None
+    };
+
+    // Tag columns of CoNLL-U corpora are named after their fields.
+    let mut tag_names = args.tag_names;
+    let mut lemma_columns = args.lemma_columns;
+    if !args.conllu.is_empty() {
+        if tag_names.is_empty() {
+            tag_names = args
+                .conllu_fields
+                .iter()
+                .map(|field| field.name().to_string())
+                .collect();
+        }
+        if lemma_columns.is_empty() {
+            lemma_columns = args
+                .conllu_fields
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| **field == ConlluField::Lemma)
+                .map(|(i, _)| i)
+                .collect();
+        }
+    }
+    let n_schema_columns = lemma_columns
+        .iter()
+        .map(|&c| c + 1)
+        .fold(tag_names.len(), usize::max);
+    let tag_schema: Vec<_> = (0..n_schema_columns)
+        .map(|i| {
+            let name = tag_names.get(i).map_or("", String::as_str);
+            if lemma_columns.contains(&i) {
+                TagColumn::lemma(name)
+            } else {
+                TagColumn::new(name)
+            }
+        })
+        .collect();
+
+    let config = TrainConfig {
+        dictionaries: &dictionaries,
+        tag_dictionary: &tag_dictionary,
+        dictn: args.dictn,
+        dict_word_min_freq: args.dict_word_min_freq,
+        use_categories: args.dict_category_tag.is_some(),
+        quantize_bits: args.quantize_bits,
+        narrow_weights: args.narrow_weights,
+        confidence_mode: args.soft_weight_scale.map_or(
+            ConfidenceMode::Threshold(args.soft_threshold),
+            ConfidenceMode::Weight,
+        ),
+        tag_cost: &args.tag_cost,
+        tag_solver: &args.tag_solver,
+        fallback_tag_columns: &args.fallback_tag_columns,
+        tag_schema: &tag_schema,
+        eps: args.eps,
+        solver: args.solver,
+        base_model: base_model.as_deref(),
+        append_tags: args.append_tags,
+    };
+
+    // Features are extracted once for each combination of window sizes and n-gram lengths, and
+    // reused for all costs and folds.
+    let mut cache = None;
+
+    // The best hyperparameters, their score, and the serialized model trained on the whole
+    // training corpus if available.
+    let mut best: Option<(HyperParams, f64, Option<Vec<u8>>)> = None;
+    for (i, params) in candidates.iter().enumerate() {
+        if candidates.len() != 1 {
+            eprintln!("Trial {}/{}: {params}", i + 1, candidates.len());
+        }
+        let (scores, model_data) = if let Some(folds) = args.folds {
+            let trainer = cached_trainer(&mut cache, &config, params, &train_sents)?;
+            let mut fold_scores = vec![];
+            for fold in 0..folds {
+                eprintln!("Fold {}/{folds}", fold + 1);
+                let model = config.train_subset(trainer, params, |j| j % folds != fold)?;
+                let test_sents = train_sents
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| j % folds == fold)
+                    .map(|(_, example)| &example.sentence);
+                let scores = validation::evaluate(model, test_sents)?;
+                eprintln!("Fold {}/{folds}: {scores}", fold + 1);
+                fold_scores.push(scores);
+            }
+            let scores = Scores::mean(&fold_scores);
+            eprintln!("Cross-validation: {scores}");
+            (Some(scores), None)
+        } else if dev_sents.is_empty() {
+            // Without --dev and --folds, there is only one candidate, which is trained below.
+            (None, None)
+        } else {
+            let trainer = cached_trainer(&mut cache, &config, params, &train_sents)?;
+            let model = config.train_subset(trainer, params, |_| true)?;
+            let model_data = model.to_vec()?;
+            let scores = validation::evaluate(model, &dev_sents)?;
+            eprintln!("Development: {scores}");
+            (Some(scores), Some(model_data))
+        };
+        let score = match (scores, args.select_by) {
+            (None, _) => 0.,
+            (Some(scores), SelectionMetric::Boundary) => scores.boundary.f1,
+            (Some(scores), SelectionMetric::Tag) => {
+                scores
+                    .tag
+                    .ok_or("--select-by tag requires tag annotations and tag models")?
+                    .f1
+            }
+        };
+        if best
+            .as_ref()
+            .is_none_or(|(_, best_score, _)| score > *best_score)
+        {
+            best = Some((*params, score, model_data));
+        }
+    }
+    let (best_params, _, best_model_data) = best.unwrap();
+    if candidates.len() != 1 {
+        eprintln!("Best: {best_params}");
+    }
+
+    let model = if let Some(model_data) = best_model_data {
+        drop(cache);
+        Model::read_slice(&model_data)?.0
+    } else {
+        if args.folds.is_some() {
+            eprintln!("Training the final model...");
+        }
+        let trainer = match cache.take() {
+            Some((params, trainer)) if params.feature_params() == best_params.feature_params() => {
+                trainer
+            }
+            cached => {
+                drop(cached);
+                config.extract(&best_params, &train_sents)?
+            }
+        };
+        let model = config.train(trainer, &best_params)?;
+        if !dev_sents.is_empty() {
+            let model_data = model.to_vec()?;
+            let scores = validation::evaluate(model, &dev_sents)?;
+            eprintln!("Development: {scores}");
+            Model::read_slice(&model_data)?.0
+        } else {
+            model
+        }
+    };
 
     let mut f = zstd::Encoder::new(File::create(args.model)?, 19)?;
     f.multithread(args.zstd_workers)?;
@@ -0,0 +1,119 @@
+use std::fmt;
+
+use evaluate::report::{BoundaryCounts, Evaluator, Report, WordCounts};
+use vaporetto::{errors::VaporettoError, Model, Predictor, Sentence};
+
+/// Precision, recall, and F1 score.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Prf {
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+impl Prf {
+    fn mean(items: &[Self]) -> Self {
+        let n = items.len() as f64;
+        Self {
+            precision: items.iter().map(|x| x.precision).sum::<f64>() / n,
+            recall: items.iter().map(|x| x.recall).sum::<f64>() / n,
+            f1: items.iter().map(|x| x.f1).sum::<f64>() / n,
+        }
+    }
+}
+
+impl From<&BoundaryCounts> for Prf {
+    fn from(c: &BoundaryCounts) -> Self {
+        Self {
+            precision: c.precision(),
+            recall: c.recall(),
+            f1: c.f1(),
+        }
+    }
+}
+
+impl From<&WordCounts> for Prf {
+    fn from(c: &WordCounts) -> Self {
+        Self {
+            precision: c.precision(),
+            recall: c.recall(),
+            f1: c.f1(),
+        }
+    }
+}
+
+impl fmt::Display for Prf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "P={:.4} R={:.4} F1={:.4}",
+            self.precision, self.recall, self.f1
+        )
+    }
+}
+
+/// Accuracy of a model on annotated sentences.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Scores {
+    /// Scores of character boundaries.
+    pub boundary: Prf,
+
+    /// Word-level scores where a word is correct only when its span and all of its annotated tags
+    /// are correct. `None` if the reference has no tags or the model does not predict tags.
+    pub tag: Option<Prf>,
+}
+
+impl Scores {
+    /// Averages scores, e.g., of cross-validation folds.
+    pub fn mean(items: &[Self]) -> Self {
+        let boundary = Prf::mean(&items.iter().map(|s| s.boundary).collect::<Vec<_>>());
+        let tag = items
+            .iter()
+            .map(|s| s.tag)
+            .collect::<Option<Vec<_>>>()
+            .map(|tags| Prf::mean(&tags));
+        Self { boundary, tag }
+    }
+}
+
+impl fmt::Display for Scores {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "boundary: {}", self.boundary)?;
+        if let Some(tag) = self.tag {
+            write!(f, ", tag: {tag}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Predicts the given tokenized sentences with the model and compares the results with the
+/// references.
+///
+/// Tags that are not annotated in the reference are not taken into account.
+pub fn evaluate<'r, 's: 'r>(
+    model: Model,
+    refs: impl IntoIterator<Item = &'r Sentence<'s, 's>>,
+) -> Result<Scores, VaporettoError> {
+    let predict_tags = !model.tag_models().is_empty();
+    let predictor = Predictor::new(model, predict_tags)?;
+
+    // References are evaluated as partially annotated so that tags that are not annotated are
+    // ignored.
+    let evaluator = Evaluator::new(vec![], None, true);
+    let mut report = Report::default();
+    let mut has_ref_tags = false;
+    for r in refs {
+        let mut s = Sentence::from_raw(r.as_raw_text())?;
+        predictor.predict(&mut s);
+        if predict_tags {
+            s.fill_tags();
+        }
+        has_ref_tags |= r.tags().iter().any(Option::is_some);
+        report += &evaluator.evaluate(r, &s);
+    }
+
+    Ok(Scores {
+        boundary: Prf::from(&report.boundary),
+        tag: (predict_tags && has_ref_tags).then(|| Prf::from(&report.word)),
+    })
+}
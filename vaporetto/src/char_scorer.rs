@@ -86,6 +86,12 @@ pub enum CharScorer {
 
     #[cfg(feature = "tag-prediction")]
     BoundaryTag(CharScorerBoundaryTag),
+
+    // Variants are appended so that data serialized by older versions with the same features can
+    // still be decoded. Indices depend on the enabled features because `BoundaryTag` is
+    // cfg-gated.
+    BoundaryI16(CharScorerBoundary<i16>),
+    BoundaryI8(CharScorerBoundary<i8>),
 }
 
 impl CharScorer {
@@ -101,7 +107,7 @@ impl CharScorer {
 
         #[cfg(feature = "tag-prediction")]
         if tag_ngram_model.is_empty() {
-            Ok(Some(Self::Boundary(CharScorerBoundary::new(
+            Ok(Some(Self::narrow_boundary(CharScorerBoundary::new(
                 ngram_model,
                 dict_model,
                 window_size,
@@ -116,17 +122,30 @@ impl CharScorer {
         }
 
         #[cfg(not(feature = "tag-prediction"))]
-        Ok(Some(Self::Boundary(CharScorerBoundary::new(
+        Ok(Some(Self::narrow_boundary(CharScorerBoundary::new(
             ngram_model,
             dict_model,
             window_size,
         )?)))
     }
 
+    /// Stores weights in the narrowest type that can represent all of them.
+    fn narrow_boundary(scorer: CharScorerBoundary) -> Self {
+        match scorer.try_narrow() {
+            Ok(scorer) => Self::BoundaryI8(scorer),
+            Err(scorer) => match scorer.try_narrow() {
+                Ok(scorer) => Self::BoundaryI16(scorer),
+                Err(scorer) => Self::Boundary(scorer),
+            },
+        }
+    }
+
     #[inline]
     pub fn add_scores(&self, sentence: &mut Sentence) {
         match self {
             Self::Boundary(scorer) => scorer.add_scores(sentence),
+            Self::BoundaryI16(scorer) => scorer.add_scores(sentence),
+            Self::BoundaryI8(scorer) => scorer.add_scores(sentence),
 
             #[cfg(feature = "tag-prediction")]
             Self::BoundaryTag(scorer) => scorer.add_scores(sentence),
@@ -147,12 +166,31 @@ impl CharScorer {
         scores: &mut [i32],
     ) {
         match self {
-            Self::Boundary(_) => panic!("unsupported"),
+            Self::Boundary(_) | Self::BoundaryI16(_) | Self::BoundaryI8(_) => {
+                panic!("unsupported")
+            }
             Self::BoundaryTag(scorer) => scorer.add_tag_scores(token_id, pos, sentence, scores),
         }
     }
 }
 
+/// Returns the largest absolute weight stored by the boundary scorer, in which weights of n-grams
+/// and dictionary words sharing suffixes are summed up.
+#[cfg(feature = "train")]
+pub fn max_merged_weight(
+    ngram_model: NgramModel<String>,
+    dict_model: DictModel,
+    window_size: u8,
+) -> Result<i32> {
+    Ok(
+        boundary_scorer::merge_weights(ngram_model, dict_model, window_size)?
+            .iter()
+            .map(|(_, weight)| weight.max_abs())
+            .max()
+            .unwrap_or(0),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,6 +291,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_narrow_weights() {
+        let ngram_model = |weight| {
+            NgramModel(vec![
+                NgramData {
+                    ngram: "我ら".into(),
+                    weights: vec![1, 2, 3, 4, 5],
+                },
+                NgramData {
+                    ngram: "国民".into(),
+                    weights: vec![6, weight, 8],
+                },
+            ])
+        };
+        let char_scorer = |weight| {
+            CharScorer::new(
+                ngram_model(weight),
+                DictModel(vec![]),
+                3,
+                #[cfg(feature = "tag-prediction")]
+                vec![],
+            )
+            .unwrap()
+            .unwrap()
+        };
+        assert!(matches!(char_scorer(-128), CharScorer::BoundaryI8(_)));
+        assert!(matches!(char_scorer(128), CharScorer::BoundaryI16(_)));
+        assert!(matches!(char_scorer(32768), CharScorer::Boundary(_)));
+
+        let mut sentence = Sentence::from_raw("我らは全世界の国民").unwrap();
+        sentence.score_padding = WEIGHT_FIXED_LEN - 1;
+        sentence.boundary_scores.clear();
+        sentence
+            .boundary_scores
+            .resize(sentence.score_padding * 2 + sentence.len() - 1, 1);
+        char_scorer(-7).add_scores(&mut sentence);
+        assert_eq!(&[4, 5, 6, 1, 1, 7, -6, 9], sentence.boundary_scores());
+    }
+
     #[test]
     fn test_add_scores_2() {
         // input:  我  ら  は  全  世  界  の  国  民
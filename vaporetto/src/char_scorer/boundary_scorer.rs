@@ -16,21 +16,26 @@ use crate::char_scorer::CharWeightMerger;
 use crate::dict_model::DictModel;
 use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::NgramModel;
-use crate::predictor::{PositionalWeight, WeightVector};
+use crate::predictor::{PositionalWeight, WeightElement, WeightVector};
 use crate::sentence::Sentence;
 
-pub struct CharScorerBoundary {
+pub struct CharScorerBoundary<T = i32> {
     #[cfg(not(feature = "charwise-pma"))]
     pma: DoubleArrayAhoCorasick<u32>,
     #[cfg(feature = "charwise-pma")]
     pma: CharwiseDoubleArrayAhoCorasick<u32>,
-    weights: Vec<PositionalWeight<WeightVector>>,
+    weights: Vec<PositionalWeight<WeightVector<T>>>,
 }
 
-impl<'de, Context> BorrowDecode<'de, Context> for CharScorerBoundary {
+impl<'de, T, Context> BorrowDecode<'de, Context> for CharScorerBoundary<T>
+where
+    T: WeightElement + Decode<Context>,
+{
     /// WARNING: This function is inherently unsafe. Do not publish this function outside this
     /// crate.
-    fn borrow_decode<D: BorrowDecoder<'de>>(decoder: &mut D) -> Result<Self, DecodeError> {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
         let pma_data: &[u8] = BorrowDecode::borrow_decode(decoder)?;
         #[cfg(not(feature = "charwise-pma"))]
         let (pma, _) = unsafe { DoubleArrayAhoCorasick::deserialize_unchecked(pma_data) };
@@ -43,7 +48,10 @@ impl<'de, Context> BorrowDecode<'de, Context> for CharScorerBoundary {
     }
 }
 
-impl Encode for CharScorerBoundary {
+impl<T> Encode for CharScorerBoundary<T>
+where
+    T: WeightElement + Encode,
+{
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         let pma_data = self.pma.serialize();
         Encode::encode(&pma_data, encoder)?;
@@ -52,30 +60,37 @@ impl Encode for CharScorerBoundary {
     }
 }
 
+/// Sums up weights of n-grams and dictionary words sharing suffixes.
+pub fn merge_weights(
+    ngram_model: NgramModel<String>,
+    dict_model: DictModel,
+    window_size: u8,
+) -> Result<Vec<(String, PositionalWeight<Vec<i32>>)>> {
+    let mut merger = CharWeightMerger::default();
+    for d in ngram_model.0 {
+        let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
+        merger.add(d.ngram, weight);
+    }
+    for d in dict_model.0 {
+        let word_len = d.word.chars().count();
+        let word_len = i16::try_from(word_len).map_err(|_| {
+            VaporettoError::invalid_model("words must be shorter than or equal to 32767 characters")
+        })?;
+        let weight = PositionalWeight::new(-word_len, d.weights);
+        merger.add(d.word, weight);
+    }
+    Ok(merger.merge())
+}
+
 impl CharScorerBoundary {
     pub fn new(
         ngram_model: NgramModel<String>,
         dict_model: DictModel,
         window_size: u8,
     ) -> Result<Self> {
-        let mut merger = CharWeightMerger::default();
-        for d in ngram_model.0 {
-            let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
-            merger.add(d.ngram, weight);
-        }
-        for d in dict_model.0 {
-            let word_len = d.word.chars().count();
-            let word_len = i16::try_from(word_len).map_err(|_| {
-                VaporettoError::invalid_model(
-                    "words must be shorter than or equal to 32767 characters",
-                )
-            })?;
-            let weight = PositionalWeight::new(-word_len, d.weights);
-            merger.add(d.word, weight);
-        }
         let mut ngrams = vec![];
         let mut weights = vec![];
-        for (ngram, weight) in merger.merge() {
+        for (ngram, weight) in merge_weights(ngram_model, dict_model, window_size)? {
             ngrams.push(ngram);
             weights.push(weight.into());
         }
@@ -88,6 +103,24 @@ impl CharScorerBoundary {
         Ok(Self { pma, weights })
     }
 
+    /// Converts weights into a narrower type, or returns `self` if some weights do not fit in it.
+    pub fn try_narrow<T: WeightElement>(self) -> core::result::Result<CharScorerBoundary<T>, Self> {
+        match self
+            .weights
+            .iter()
+            .map(PositionalWeight::try_narrow)
+            .collect::<Option<_>>()
+        {
+            Some(weights) => Ok(CharScorerBoundary {
+                pma: self.pma,
+                weights,
+            }),
+            None => Err(self),
+        }
+    }
+}
+
+impl<T: WeightElement> CharScorerBoundary<T> {
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
     pub fn add_scores(&self, sentence: &mut Sentence) {
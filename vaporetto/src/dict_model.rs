@@ -14,9 +14,15 @@ pub struct DictWeight {
 }
 
 /// Record of weights for each word.
+///
+/// With the `serde` feature, weights are serialized as a space-separated string, such as
+/// `"1 2 3"`, so that records can be written to CSV files.
 #[derive(Clone, Debug, Decode, Encode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "WordWeightRecordUnchecked"))]
 pub struct WordWeightRecord {
     pub(crate) word: String,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_weights"))]
     pub(crate) weights: Vec<i32>,
     pub(crate) comment: String,
 }
@@ -64,6 +70,60 @@ impl WordWeightRecord {
     }
 }
 
+/// Record deserialized before validation.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct WordWeightRecordUnchecked {
+    word: String,
+    weights: SerializedWeights,
+    comment: String,
+}
+
+/// Weights given as a space-separated string or a sequence.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum SerializedWeights {
+    Joined(String),
+    Seq(Vec<i32>),
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<WordWeightRecordUnchecked> for WordWeightRecord {
+    type Error = VaporettoError;
+
+    fn try_from(record: WordWeightRecordUnchecked) -> Result<Self> {
+        let weights = match record.weights {
+            SerializedWeights::Joined(weights) => weights
+                .split_whitespace()
+                .map(|w| w.parse())
+                .collect::<core::result::Result<_, _>>()
+                .map_err(|_| {
+                    VaporettoError::invalid_argument("weights", "must be space-separated integers")
+                })?,
+            SerializedWeights::Seq(weights) => weights,
+        };
+        Self::new(record.word, weights, record.comment)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn serialize_weights<S>(weights: &[i32], serializer: S) -> core::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    use core::fmt::Write;
+
+    let mut joined = String::new();
+    for (i, w) in weights.iter().enumerate() {
+        if i != 0 {
+            joined.push(' ');
+        }
+        write!(joined, "{w}").unwrap();
+    }
+    serializer.serialize_str(&joined)
+}
+
 #[derive(Debug, Decode, Encode)]
 pub struct DictModel(pub(crate) Vec<WordWeightRecord>);
 
@@ -76,3 +136,29 @@ impl DictModel {
         &self.0
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_weight_record_serde() {
+        let record = WordWeightRecord::new("火星".into(), vec![1, 2, 3], "".into()).unwrap();
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(r#"{"word":"火星","weights":"1 2 3","comment":""}"#, json);
+        let record: WordWeightRecord = serde_json::from_str(&json).unwrap();
+        assert_eq!(&[1, 2, 3], record.get_weights());
+
+        let record: WordWeightRecord =
+            serde_json::from_str(r#"{"word":"火星","weights":[1,-2,3],"comment":""}"#).unwrap();
+        assert_eq!(&[1, -2, 3], record.get_weights());
+
+        let result: core::result::Result<WordWeightRecord, _> =
+            serde_json::from_str(r#"{"word":"火星","weights":"1 x 3","comment":""}"#);
+        assert!(result.is_err());
+
+        let result: core::result::Result<WordWeightRecord, _> =
+            serde_json::from_str(r#"{"word":"火星","weights":[1,2],"comment":""}"#);
+        assert!(result.is_err());
+    }
+}
@@ -12,6 +12,7 @@ pub type Result<T, E = VaporettoError> = core::result::Result<T, E>;
 
 /// The error type for Vaporetto.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum VaporettoError {
     /// The error variant for [`InvalidModelError`].
     InvalidModel(InvalidModelError),
@@ -19,6 +20,9 @@ pub enum VaporettoError {
     /// The error variant for [`InvalidArgumentError`].
     InvalidArgument(InvalidArgumentError),
 
+    /// The error variant for [`ParseError`].
+    Parse(ParseError),
+
     /// The error variant for [`FromUtf8Error`](alloc::string::FromUtf8Error).
     UTF8Error(alloc::string::FromUtf8Error),
 
@@ -54,6 +58,50 @@ impl VaporettoError {
             msg: msg.into(),
         })
     }
+
+    pub(crate) fn parse_error<S>(
+        arg: &'static str,
+        msg: S,
+        char_offset: usize,
+        byte_offset: usize,
+        character: Option<char>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::parse_error_in_line(
+            arg,
+            msg,
+            0,
+            char_offset,
+            char_offset,
+            byte_offset,
+            character,
+        )
+    }
+
+    pub(crate) fn parse_error_in_line<S>(
+        arg: &'static str,
+        msg: S,
+        line: usize,
+        column: usize,
+        char_offset: usize,
+        byte_offset: usize,
+        character: Option<char>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Parse(ParseError {
+            arg,
+            msg: msg.into(),
+            line,
+            column,
+            char_offset,
+            byte_offset,
+            character,
+        })
+    }
 }
 
 impl fmt::Display for VaporettoError {
@@ -61,6 +109,7 @@ impl fmt::Display for VaporettoError {
         match self {
             Self::InvalidModel(e) => e.fmt(f),
             Self::InvalidArgument(e) => e.fmt(f),
+            Self::Parse(e) => e.fmt(f),
             Self::UTF8Error(e) => e.fmt(f),
             Self::CastError(e) => e.fmt(f),
             Self::DecodeError(e) => e.fmt(f),
@@ -110,6 +159,87 @@ impl fmt::Display for InvalidArgumentError {
 #[cfg(feature = "std")]
 impl Error for InvalidArgumentError {}
 
+/// Error used when an annotated text is malformed.
+#[derive(Debug)]
+pub struct ParseError {
+    /// Name of the argument.
+    pub(crate) arg: &'static str,
+
+    /// Error message.
+    pub(crate) msg: String,
+
+    /// Line of the error, starting from 0.
+    pub(crate) line: usize,
+
+    /// Position of the error in characters from the beginning of the line.
+    pub(crate) column: usize,
+
+    /// Position of the error in characters.
+    pub(crate) char_offset: usize,
+
+    /// Position of the error in bytes.
+    pub(crate) byte_offset: usize,
+
+    /// The offending character, or `None` if the error is not caused by a specific character.
+    pub(crate) character: Option<char>,
+}
+
+impl ParseError {
+    /// Returns the name of the argument.
+    pub const fn arg(&self) -> &'static str {
+        self.arg
+    }
+
+    /// Returns the error message.
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    /// Returns the line of the error, starting from 0. This is always 0 for formats consisting
+    /// of a single line.
+    pub const fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the position of the error in characters from the beginning of the line.
+    pub const fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the position of the error in characters from the beginning of the text.
+    pub const fn char_offset(&self) -> usize {
+        self.char_offset
+    }
+
+    /// Returns the position of the error in bytes from the beginning of the text.
+    pub const fn byte_offset(&self) -> usize {
+        self.byte_offset
+    }
+
+    /// Returns the offending character, or `None` if the error is not caused by a specific
+    /// character.
+    pub const fn character(&self) -> Option<char> {
+        self.character
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ParseError: {}: {} (at char {}, byte {}",
+            self.arg, self.msg, self.char_offset, self.byte_offset,
+        )?;
+        if let Some(c) = self.character {
+            write!(f, ", found {c:?}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseError {}
+
 impl From<alloc::string::FromUtf8Error> for VaporettoError {
     fn from(error: alloc::string::FromUtf8Error) -> Self {
         Self::UTF8Error(error)
@@ -65,10 +65,14 @@ mod dict_model;
 mod model;
 mod ngram_model;
 mod predictor;
+mod sampler;
 mod sentence;
 mod type_scorer;
 mod utils;
 
+#[cfg(test)]
+mod test_utils;
+
 pub mod errors;
 
 #[cfg(feature = "train")]
@@ -80,12 +84,16 @@ mod trainer;
 mod kytea_model;
 
 pub use dict_model::WordWeightRecord;
-pub use model::Model;
+pub use model::{Model, TagColumn, TagColumnKind, TagModel};
 pub use predictor::Predictor;
-pub use sentence::{CharacterBoundary, CharacterType, Sentence, Token, TokenIterator};
+pub use sampler::{BoundaryQuery, UncertaintySampler};
+pub use sentence::{
+    AnalyzedSentence, CharacterBoundary, CharacterType, ConlluField, OwnedToken, Sentence, Token,
+    TokenIterator,
+};
 
 #[cfg(feature = "train")]
-pub use trainer::{SolverType, Trainer};
+pub use trainer::{ConfidenceMode, SolverType, Trainer, MAX_EXAMPLE_WEIGHT};
 
 #[cfg(feature = "kytea")]
 pub use kytea_model::KyteaModel;
@@ -1,4 +1,7 @@
-use alloc::string::String;
+use core::ops::Range;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
 #[cfg(feature = "std")]
@@ -12,7 +15,10 @@ use crate::ngram_model::{NgramModel, TagNgramModel};
 use crate::utils::VecWriter;
 
 /// Magic number.
-const MODEL_MAGIC: &[u8] = b"VaporettoTokenizer 0.5.0\n";
+const MODEL_MAGIC: &[u8] = b"VaporettoTokenizer 0.7.0\n";
+
+/// Magic number of models exported by Vaporetto 0.5 and 0.6.
+const MODEL_MAGIC_0_5: &[u8] = b"VaporettoTokenizer 0.5.0\n";
 
 // For each token, a model is trained for every tag independently, but the scores of all tags are
 // calculated in parallel during prediction.
@@ -36,6 +42,137 @@ const MODEL_MAGIC: &[u8] = b"VaporettoTokenizer 0.5.0\n";
 //   scores:  [              475,   1563 ]
 //
 //   results: ["名詞", "ケン"]
+//
+// The tag model of the empty token is a fallback model used for tokens that do not have their own
+// models. Its n-grams are not searched in the sentence but are features of the token itself, and
+// the kind of each feature is represented by its relative position:
+//
+//   char_ngram_model: 0 = the last character, 1 = the last two characters,
+//                     2 = the first character
+//   type_ngram_model: 0 = types of the last three characters, 1 = types of the first two characters
+pub(crate) const FALLBACK_TOKEN: &str = "";
+
+/// Returns character features of a token for the fallback tag model, indexed by their kinds.
+#[cfg(any(feature = "train", feature = "tag-prediction"))]
+pub(crate) fn fallback_char_features(surface: &str) -> [Option<&str>; 3] {
+    let mut it = surface.char_indices();
+    let last = it.next_back().map(|(i, _)| &surface[i..]);
+    let last_two = it.next_back().map(|(i, _)| &surface[i..]);
+    let first = surface.chars().next().map(|c| &surface[..c.len_utf8()]);
+    [last, last_two, first]
+}
+
+/// Returns character type features of a token for the fallback tag model, indexed by their
+/// kinds.
+#[cfg(any(feature = "train", feature = "tag-prediction"))]
+pub(crate) fn fallback_type_features(char_types: &[u8]) -> [&[u8]; 2] {
+    [
+        &char_types[char_types.len().saturating_sub(3)..],
+        &char_types[..char_types.len().min(2)],
+    ]
+}
+
+/// Returns the edit script that converts the surface into the lemma.
+///
+/// The script `-k+s` removes the last `k` characters of the surface and appends `s`, e.g., `-1+る`
+/// converts 食べた into 食べる. Unlike lemmas themselves, scripts are shared among words with
+/// the same inflection.
+#[cfg(feature = "train")]
+pub(crate) fn lemma_edit_script(surface: &str, lemma: &str) -> String {
+    let prefix_len = surface
+        .chars()
+        .zip(lemma.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let n_strip = surface.chars().count() - prefix_len;
+    let suffix_start = lemma
+        .char_indices()
+        .nth(prefix_len)
+        .map_or(lemma.len(), |(i, _)| i);
+    format!("-{n_strip}+{}", &lemma[suffix_start..])
+}
+
+/// Applies the edit script returned by [`lemma_edit_script()`] to the surface.
+///
+/// Returns `None` if the script is invalid or removes more characters than the surface has.
+#[cfg(feature = "tag-prediction")]
+pub(crate) fn apply_lemma_edit_script(surface: &str, script: &str) -> Option<String> {
+    let (n_strip, suffix) = script.strip_prefix('-')?.split_once('+')?;
+    let n_strip: usize = n_strip.parse().ok()?;
+    let end = if n_strip == 0 {
+        surface.len()
+    } else {
+        surface.char_indices().nth_back(n_strip - 1)?.0
+    };
+    let mut lemma = String::from(&surface[..end]);
+    lemma.push_str(suffix);
+    Some(lemma)
+}
+
+/// Kind of a tag column.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Decode, Encode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TagColumnKind {
+    /// Tags are predicted from the candidates of each token as they are.
+    #[default]
+    Plain,
+
+    /// Tags are lemmas, which are trained and predicted as edit scripts from the surfaces so that
+    /// they generalize to unseen inflections.
+    Lemma,
+}
+
+/// A column of the tag schema.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Decode, Encode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagColumn {
+    name: String,
+    kind: TagColumnKind,
+    values: Option<Vec<String>>,
+}
+
+impl TagColumn {
+    /// Creates a new column with the given name and no restriction on its values.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: TagColumnKind::Plain,
+            values: None,
+        }
+    }
+
+    /// Creates a new lemma column with the given name.
+    pub fn lemma(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind: TagColumnKind::Lemma,
+            values: None,
+        }
+    }
+
+    /// Returns the kind of this column.
+    pub const fn kind(&self) -> TagColumnKind {
+        self.kind
+    }
+
+    /// Restricts tags of this column to the given closed set of values. Lemma columns cannot
+    /// have value sets.
+    pub fn with_values(mut self, values: Vec<String>) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    /// Returns the name of this column. An unnamed column has an empty string.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the closed set of values, or `None` if the values are not restricted.
+    pub fn values(&self) -> Option<&[String]> {
+        self.values.as_deref()
+    }
+}
+
 /// Internal representation of a tag model.
 #[derive(Debug, Decode, Encode)]
 pub struct TagModel {
@@ -44,13 +181,173 @@ pub struct TagModel {
     pub(crate) char_ngram_model: TagNgramModel<String>,
     pub(crate) type_ngram_model: TagNgramModel<Vec<u8>>,
     pub(crate) bias: Vec<i32>,
+    // Multipliers that convert the quantized scores of each column into the raw scores of the
+    // classifier. Missing values and `None` mean that the multiplier is unknown.
+    pub(crate) score_scales: Vec<Option<f64>>,
+    // Combinations of tags observed in training, represented by the indices of the candidates of
+    // each column. `None` matches any tag. Empty if the combinations are not restricted.
+    pub(crate) tag_tuples: Vec<Vec<Option<u32>>>,
+}
+
+// Tag model data exported by Vaporetto 0.5 and 0.6.
+#[derive(Decode)]
+struct TagModel0_5 {
+    token: String,
+    tags: Vec<Vec<String>>,
+    char_ngram_model: TagNgramModel<String>,
+    type_ngram_model: TagNgramModel<Vec<u8>>,
+    bias: Vec<i32>,
+}
+
+impl From<TagModel0_5> for TagModel {
+    fn from(data: TagModel0_5) -> Self {
+        Self {
+            token: data.token,
+            tags: data.tags,
+            char_ngram_model: data.char_ngram_model,
+            type_ngram_model: data.type_ngram_model,
+            bias: data.bias,
+            score_scales: vec![],
+            tag_tuples: vec![],
+        }
+    }
+}
+
+/// Returns the indices of lemma columns in the schema.
+#[cfg(any(feature = "train", feature = "tag-prediction"))]
+pub(crate) fn lemma_columns(schema: &[TagColumn]) -> impl Iterator<Item = usize> + '_ {
+    schema
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.kind == TagColumnKind::Lemma)
+        .map(|(i, _)| i)
+}
+
+/// Returns the index of the named column in the schema. Unnamed columns are never matched.
+pub(crate) fn tag_column_index(schema: &[TagColumn], name: &str) -> Option<usize> {
+    if name.is_empty() {
+        return None;
+    }
+    schema.iter().position(|c| c.name == name)
+}
+
+/// Concatenates the class weights of the given columns.
+fn select_classes(weights: &[i32], ranges: &[Range<usize>], columns: &[usize]) -> Vec<i32> {
+    columns
+        .iter()
+        .filter_map(|&c| ranges.get(c))
+        .flat_map(|r| weights[r.clone()].iter().copied())
+        .collect()
 }
 
 impl TagModel {
-    /// Returns the token string.
+    /// Returns the token string. The fallback model for tokens without their own models has an
+    /// empty string.
     pub fn token(&self) -> &str {
         &self.token
     }
+
+    /// Returns `true` if this is the fallback model for tokens without their own models.
+    pub fn is_fallback(&self) -> bool {
+        self.token == FALLBACK_TOKEN
+    }
+
+    /// Returns the tag candidates of each column.
+    pub fn tags(&self) -> &[Vec<String>] {
+        &self.tags
+    }
+
+    /// Returns the ranges of the score array assigned to each column.
+    fn column_ranges(&self) -> Vec<Range<usize>> {
+        let mut offset = 0;
+        self.tags
+            .iter()
+            .map(|tags| {
+                let start = offset;
+                if tags.len() >= 2 {
+                    offset += tags.len();
+                }
+                start..offset
+            })
+            .collect()
+    }
+
+    fn n_classes(&self) -> usize {
+        self.column_ranges().last().map_or(0, |r| r.end)
+    }
+
+    /// Keeps only the given columns in the given order. Columns that this token does not have
+    /// become empty.
+    fn select_columns(&mut self, columns: &[usize]) {
+        let ranges = self.column_ranges();
+        let select = |weights: &[i32]| select_classes(weights, &ranges, columns);
+        self.bias = select_classes(&self.bias, &ranges, columns);
+        self.char_ngram_model.map_classes(select);
+        self.type_ngram_model.map_classes(select);
+        self.tags = columns
+            .iter()
+            .map(|&c| self.tags.get(c).cloned().unwrap_or_default())
+            .collect();
+        self.score_scales = columns
+            .iter()
+            .map(|&c| self.score_scales.get(c).copied().flatten())
+            .collect();
+        let mut tag_tuples: Vec<Vec<_>> = self
+            .tag_tuples
+            .iter()
+            .map(|tuple| {
+                columns
+                    .iter()
+                    .map(|&c| tuple.get(c).copied().flatten())
+                    .collect()
+            })
+            .collect();
+        tag_tuples.sort_unstable();
+        tag_tuples.dedup();
+        self.tag_tuples = tag_tuples;
+    }
+
+    /// Appends the columns of another model of the same token after the first `n_columns`
+    /// columns.
+    fn append_columns(&mut self, n_columns: usize, other: Self) {
+        let n_classes = self.n_classes();
+        let n_other_classes = other.n_classes();
+        self.score_scales.resize(n_columns, None);
+        self.score_scales
+            .extend((0..other.tags.len()).map(|c| other.score_scales.get(c).copied().flatten()));
+        // Columns of the two models are trained separately, so any combination of their tuples is
+        // allowed. A model without tuples is regarded as having a tuple that matches anything.
+        if !self.tag_tuples.is_empty() || !other.tag_tuples.is_empty() {
+            let n_all_columns = n_columns + other.tags.len();
+            let tuples = core::mem::take(&mut self.tag_tuples);
+            let tuples = if tuples.is_empty() {
+                vec![vec![]]
+            } else {
+                tuples
+            };
+            let other_tuples = if other.tag_tuples.is_empty() {
+                vec![vec![]]
+            } else {
+                other.tag_tuples
+            };
+            for tuple in &tuples {
+                for other_tuple in &other_tuples {
+                    let mut new_tuple = tuple.clone();
+                    new_tuple.resize(n_columns, None);
+                    new_tuple.extend_from_slice(other_tuple);
+                    new_tuple.resize(n_all_columns, None);
+                    self.tag_tuples.push(new_tuple);
+                }
+            }
+        }
+        self.tags.resize(n_columns, vec![]);
+        self.tags.extend(other.tags);
+        self.bias.extend(other.bias);
+        self.char_ngram_model
+            .concat_classes(n_classes, other.char_ngram_model, n_other_classes);
+        self.type_ngram_model
+            .concat_classes(n_classes, other.type_ngram_model, n_other_classes);
+    }
 }
 
 /// Model data.
@@ -67,6 +364,41 @@ pub struct ModelData {
     pub(crate) type_window_size: u8,
     // Instead of using Map, we use Vec to increase compression ratio and performance.
     pub(crate) tag_models: Vec<TagModel>,
+    // Dictionaries trained in their own feature spaces, identified by their names.
+    pub(crate) named_dict_models: Vec<(String, DictModel)>,
+    // The bit depth used to quantize weights in training, or None if unknown.
+    pub(crate) quantize_bit_depth: Option<u8>,
+    // Names and value sets of tag columns. Columns after the end of the schema are unnamed.
+    pub(crate) tag_schema: Vec<TagColumn>,
+}
+
+// Model data exported by Vaporetto 0.5 and 0.6.
+#[derive(Decode)]
+struct ModelData0_5 {
+    char_ngram_model: NgramModel<String>,
+    type_ngram_model: NgramModel<Vec<u8>>,
+    dict_model: DictModel,
+    bias: i32,
+    char_window_size: u8,
+    type_window_size: u8,
+    tag_models: Vec<TagModel0_5>,
+}
+
+impl From<ModelData0_5> for ModelData {
+    fn from(data: ModelData0_5) -> Self {
+        Self {
+            char_ngram_model: data.char_ngram_model,
+            type_ngram_model: data.type_ngram_model,
+            dict_model: data.dict_model,
+            bias: data.bias,
+            char_window_size: data.char_window_size,
+            type_window_size: data.type_window_size,
+            tag_models: data.tag_models.into_iter().map(TagModel::from).collect(),
+            named_dict_models: vec![],
+            quantize_bit_depth: None,
+            tag_schema: vec![],
+        }
+    }
 }
 
 impl Model {
@@ -88,6 +420,9 @@ impl Model {
             char_window_size,
             type_window_size,
             tag_models,
+            named_dict_models: vec![],
+            quantize_bit_depth: None,
+            tag_schema: vec![],
         })
     }
 
@@ -125,11 +460,19 @@ impl Model {
     ///
     /// When bincode generates an error, it will be returned as is.
     pub fn read_slice(slice: &[u8]) -> Result<(Self, &[u8])> {
-        if &slice[..MODEL_MAGIC.len()] != MODEL_MAGIC {
-            return Err(VaporettoError::invalid_model("model version mismatch"));
-        }
         let config = bincode::config::standard();
-        let (data, size) = bincode::decode_from_slice(&slice[MODEL_MAGIC.len()..], config)?;
+        let magic = slice
+            .get(..MODEL_MAGIC.len())
+            .ok_or_else(|| VaporettoError::invalid_model("model version mismatch"))?;
+        let (data, size) = if magic == MODEL_MAGIC {
+            bincode::decode_from_slice(&slice[MODEL_MAGIC.len()..], config)?
+        } else if magic == MODEL_MAGIC_0_5 {
+            let (data, size): (ModelData0_5, _) =
+                bincode::decode_from_slice(&slice[MODEL_MAGIC.len()..], config)?;
+            (data.into(), size)
+        } else {
+            return Err(VaporettoError::invalid_model("model version mismatch"));
+        };
         Ok((Self(data), &slice[MODEL_MAGIC.len() + size..]))
     }
 
@@ -145,11 +488,15 @@ impl Model {
     {
         let mut magic = [0; MODEL_MAGIC.len()];
         rdr.read_exact(&mut magic)?;
-        if magic != MODEL_MAGIC {
-            return Err(VaporettoError::invalid_model("model version mismatch"));
-        }
         let config = bincode::config::standard();
-        Ok(Self(bincode::decode_from_std_read(&mut rdr, config)?))
+        if magic == MODEL_MAGIC {
+            Ok(Self(bincode::decode_from_std_read(&mut rdr, config)?))
+        } else if magic == MODEL_MAGIC_0_5 {
+            let data: ModelData0_5 = bincode::decode_from_std_read(&mut rdr, config)?;
+            Ok(Self(data.into()))
+        } else {
+            Err(VaporettoError::invalid_model("model version mismatch"))
+        }
     }
 
     /// Returns the slice of dictionary words.
@@ -162,8 +509,272 @@ impl Model {
         self.0.dict_model = DictModel::new(dict);
     }
 
+    /// Returns an iterator of names of dictionaries trained in their own feature spaces.
+    pub fn dictionary_names(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .named_dict_models
+            .iter()
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the slice of words in the dictionary with the given name.
+    pub fn named_dictionary(&self, name: &str) -> Option<&[WordWeightRecord]> {
+        self.0
+            .named_dict_models
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, dict_model)| dict_model.dictionary())
+    }
+
+    /// Replaces the dictionary with the given name.
+    /// If the model does not contain the dictionary, a new dictionary will be added.
+    pub fn replace_named_dictionary(&mut self, name: &str, dict: Vec<WordWeightRecord>) {
+        if let Some((_, dict_model)) = self.0.named_dict_models.iter_mut().find(|(n, _)| n == name)
+        {
+            *dict_model = DictModel::new(dict);
+        } else {
+            self.0
+                .named_dict_models
+                .push((name.to_string(), DictModel::new(dict)));
+        }
+    }
+
+    /// Returns the bit depth used to quantize weights in training.
+    ///
+    /// Returns `None` if the model was not trained by Vaporetto or was exported by an old version.
+    pub const fn quantize_bit_depth(&self) -> Option<u8> {
+        self.0.quantize_bit_depth
+    }
+
+    /// Returns the schema of tag columns.
+    ///
+    /// The schema may be shorter than the number of tag columns, in which case the remaining
+    /// columns are unnamed.
+    pub fn tag_schema(&self) -> &[TagColumn] {
+        &self.0.tag_schema
+    }
+
+    /// Sets the schema of tag columns.
+    ///
+    /// # Errors
+    ///
+    /// If the schema has more columns than the model, contains duplicate names, has a lemma
+    /// column with a value set, or the model contains tags that are not in the value set of their
+    /// column, an error variant will be returned.
+    pub fn set_tag_schema(&mut self, schema: Vec<TagColumn>) -> Result<()> {
+        let n_columns = self.n_tag_columns();
+        if schema.len() > n_columns {
+            return Err(VaporettoError::invalid_argument(
+                "schema",
+                format!("must not have more than {n_columns} columns"),
+            ));
+        }
+        for (i, column) in schema.iter().enumerate() {
+            if !column.name.is_empty() && schema[..i].iter().any(|c| c.name == column.name) {
+                return Err(VaporettoError::invalid_argument(
+                    "schema",
+                    format!("duplicate column name: {}", column.name),
+                ));
+            }
+            let Some(values) = column.values.as_ref() else {
+                continue;
+            };
+            if column.kind == TagColumnKind::Lemma {
+                return Err(VaporettoError::invalid_argument(
+                    "schema",
+                    format!("lemma column {i} must not have values"),
+                ));
+            }
+            for tag in self
+                .0
+                .tag_models
+                .iter()
+                .filter_map(|m| m.tags.get(i))
+                .flatten()
+            {
+                if !values.contains(tag) {
+                    return Err(VaporettoError::invalid_argument(
+                        "schema",
+                        format!("tag {tag} is not in the values of column {i}"),
+                    ));
+                }
+            }
+        }
+        self.0.tag_schema = schema;
+        Ok(())
+    }
+
+    /// Returns the index of the tag column with the given name.
+    pub fn tag_column_index(&self, name: &str) -> Option<usize> {
+        tag_column_index(&self.0.tag_schema, name)
+    }
+
     /// Returns the slice of tag models.
     pub fn tag_models(&self) -> &[TagModel] {
         &self.0.tag_models
     }
+
+    /// Returns the number of tag columns.
+    pub fn n_tag_columns(&self) -> usize {
+        self.0
+            .tag_models
+            .iter()
+            .map(|m| m.tags.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn check_tag_models(&self, tag_models: &[TagModel]) -> Result<()> {
+        // N-grams of the fallback model are not positioned in the window.
+        for tag_model in tag_models.iter().filter(|m| !m.is_fallback()) {
+            if tag_model
+                .char_ngram_model
+                .max_rel_position()
+                .is_some_and(|p| p > self.0.char_window_size)
+                || tag_model
+                    .type_ngram_model
+                    .max_rel_position()
+                    .is_some_and(|p| p > self.0.type_window_size)
+            {
+                return Err(VaporettoError::invalid_argument(
+                    "tag_models",
+                    "n-gram positions must be within the window sizes of the model",
+                ));
+            }
+        }
+        let mut tokens: Vec<_> = tag_models.iter().map(|m| m.token.as_str()).collect();
+        tokens.sort_unstable();
+        if tokens.windows(2).any(|w| w[0] == w[1]) {
+            return Err(VaporettoError::invalid_argument(
+                "tag_models",
+                "tokens must not be duplicated",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the tag models with the given ones, e.g., those trained separately by
+    /// [`Trainer::train_tags()`](crate::Trainer::train_tags). The tag schema is truncated to the
+    /// number of the new tag columns.
+    ///
+    /// # Errors
+    ///
+    /// If the tag models use n-grams outside the window sizes of this model, or contain
+    /// duplicate tokens, an error variant will be returned.
+    pub fn replace_tag_models(&mut self, tag_models: Vec<TagModel>) -> Result<()> {
+        self.check_tag_models(&tag_models)?;
+        self.0.tag_models = tag_models;
+        let n_columns = self.n_tag_columns();
+        self.0.tag_schema.truncate(n_columns);
+        Ok(())
+    }
+
+    /// Appends the tag columns of the given tag models after the existing columns.
+    ///
+    /// # Errors
+    ///
+    /// If the tag models use n-grams outside the window sizes of this model, or contain
+    /// duplicate tokens, an error variant will be returned.
+    pub fn append_tag_columns(&mut self, tag_models: Vec<TagModel>) -> Result<()> {
+        self.check_tag_models(&tag_models)?;
+        let n_columns = self.n_tag_columns();
+        let mut merged: BTreeMap<_, _> = core::mem::take(&mut self.0.tag_models)
+            .into_iter()
+            .map(|m| (m.token.clone(), m))
+            .collect();
+        for other in tag_models {
+            merged
+                .entry(other.token.clone())
+                .or_insert_with(|| TagModel {
+                    token: other.token.clone(),
+                    tags: vec![],
+                    char_ngram_model: TagNgramModel::default(),
+                    type_ngram_model: TagNgramModel::default(),
+                    bias: vec![],
+                    score_scales: vec![],
+                    tag_tuples: vec![],
+                })
+                .append_columns(n_columns, other);
+        }
+        self.0.tag_models = merged.into_values().collect();
+        Ok(())
+    }
+
+    /// Keeps only the given tag columns in the given order. This can be used to drop or reorder
+    /// tag columns.
+    ///
+    /// # Errors
+    ///
+    /// If a column index is out of range, an error variant will be returned.
+    pub fn select_tag_columns(&mut self, columns: &[usize]) -> Result<()> {
+        let n_columns = self.n_tag_columns();
+        if columns.iter().any(|&c| c >= n_columns) {
+            return Err(VaporettoError::invalid_argument(
+                "columns",
+                format!("must be smaller than the number of tag columns {n_columns}"),
+            ));
+        }
+        for tag_model in &mut self.0.tag_models {
+            tag_model.select_columns(columns);
+        }
+        if !self.0.tag_schema.is_empty() {
+            self.0.tag_schema = columns
+                .iter()
+                .map(|&c| self.0.tag_schema.get(c).cloned().unwrap_or_default())
+                .collect();
+        }
+        self.0
+            .tag_models
+            .retain(|m| m.tags.iter().any(|tags| !tags.is_empty()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test_utils::create_test_model;
+
+    #[test]
+    fn test_tag_schema_invalid() {
+        let mut model = create_test_model();
+        assert!(model
+            .set_tag_schema(vec![
+                TagColumn::new("pos"),
+                TagColumn::new("pron"),
+                TagColumn::new("lemma"),
+            ])
+            .is_err());
+        assert!(model
+            .set_tag_schema(vec![TagColumn::new("pos"), TagColumn::new("pos")])
+            .is_err());
+        assert!(model
+            .set_tag_schema(vec![
+                TagColumn::new("pos").with_values(vec!["名詞".into(), "動詞".into()])
+            ])
+            .is_err());
+        model
+            .set_tag_schema(vec![
+                TagColumn::new("pos").with_values(vec!["名詞".into(), "接尾辞".into()]),
+                TagColumn::new(""),
+            ])
+            .unwrap();
+        assert_eq!(2, model.tag_schema().len());
+    }
+
+    #[test]
+    fn test_replace_tag_models_invalid() {
+        let mut model = create_test_model();
+        let mut tag_models = create_test_model().0.tag_models;
+        tag_models[0].char_ngram_model.0[0].weights[0].rel_position = 4;
+        assert!(model.replace_tag_models(tag_models).is_err());
+
+        let mut tag_models = create_test_model().0.tag_models;
+        tag_models[1].token = "人".into();
+        assert!(model.replace_tag_models(tag_models).is_err());
+
+        let tag_models = create_test_model().0.tag_models;
+        assert!(model.replace_tag_models(tag_models).is_ok());
+    }
 }
@@ -1,3 +1,4 @@
+use alloc::collections::BTreeMap;
 use alloc::vec::Vec;
 
 use bincode::{Decode, Encode};
@@ -25,3 +26,65 @@ pub struct TagNgramData<T> {
 
 #[derive(Default, Debug, Decode, Encode)]
 pub struct TagNgramModel<T: 'static>(pub Vec<TagNgramData<T>>);
+
+impl<T> TagNgramModel<T> {
+    /// Rearranges the class weights with the given function. Weights that become zero are
+    /// removed.
+    pub fn map_classes(&mut self, f: impl Fn(&[i32]) -> Vec<i32>) {
+        for d in &mut self.0 {
+            for w in &mut d.weights {
+                w.weights = f(&w.weights);
+            }
+            d.weights.retain(|w| w.weights.iter().any(|&x| x != 0));
+        }
+        self.0.retain(|d| !d.weights.is_empty());
+    }
+
+    /// Returns the largest relative position of n-grams.
+    pub fn max_rel_position(&self) -> Option<u8> {
+        self.0
+            .iter()
+            .flat_map(|d| &d.weights)
+            .map(|w| w.rel_position)
+            .max()
+    }
+}
+
+impl<T: Ord> TagNgramModel<T> {
+    /// Appends classes of another model after `n_classes` classes of this model.
+    pub fn concat_classes(&mut self, n_classes: usize, other: Self, n_other_classes: usize) {
+        let n_total = n_classes + n_other_classes;
+        let mut merged: BTreeMap<T, BTreeMap<u8, Vec<i32>>> = BTreeMap::new();
+        for d in core::mem::take(&mut self.0) {
+            let weights = merged.entry(d.ngram).or_default();
+            for mut w in d.weights {
+                w.weights.resize(n_total, 0);
+                weights.insert(w.rel_position, w.weights);
+            }
+        }
+        for d in other.0 {
+            let weights = merged.entry(d.ngram).or_default();
+            for w in d.weights {
+                let dst = weights
+                    .entry(w.rel_position)
+                    .or_insert_with(|| vec![0; n_total]);
+                for (x, y) in dst[n_classes..].iter_mut().zip(w.weights) {
+                    *x = y;
+                }
+            }
+        }
+        self.0 = merged
+            .into_iter()
+            .map(|(ngram, weights)| TagNgramData {
+                ngram,
+                weights: weights
+                    .into_iter()
+                    .map(|(rel_position, weights)| TagWeight {
+                        rel_position,
+                        weights,
+                    })
+                    .collect(),
+            })
+            .collect();
+    }
+}
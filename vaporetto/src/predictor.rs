@@ -23,6 +23,11 @@ use hashbrown::HashMap;
 use crate::char_scorer::CharScorer;
 use crate::errors::Result;
 use crate::model::Model;
+#[cfg(feature = "tag-prediction")]
+use crate::model::{
+    apply_lemma_edit_script, fallback_char_features, fallback_type_features, lemma_columns,
+    tag_column_index, TagColumn, TagColumnKind, TagModel,
+};
 use crate::sentence::{CharacterBoundary, Sentence};
 use crate::type_scorer::TypeScorer;
 
@@ -31,34 +36,58 @@ use crate::utils::SerializableHashMap;
 
 pub const WEIGHT_FIXED_LEN: usize = 8;
 
-#[cfg(all(feature = "fix-weight-length", not(feature = "portable-simd")))]
-pub type I32Simd = [i32; WEIGHT_FIXED_LEN];
 #[cfg(all(feature = "fix-weight-length", feature = "portable-simd"))]
 pub type I32Simd = Simd<i32, WEIGHT_FIXED_LEN>;
 
+/// Integer types used to store weights in the predictor.
+///
+/// Weights are stored in the narrowest type that can represent all of them to reduce memory
+/// and cache footprint.
+pub trait WeightElement: Copy + Default + PartialEq + Into<i32> + TryFrom<i32> {}
+
+impl WeightElement for i8 {}
+impl WeightElement for i16 {}
+impl WeightElement for i32 {}
+
 #[derive(Clone, Debug)]
-pub enum WeightVector {
-    Variable(Vec<i32>),
+pub enum WeightVector<T = i32> {
+    Variable(Vec<T>),
 
     #[cfg(feature = "fix-weight-length")]
-    Fixed(I32Simd),
+    Fixed([T; WEIGHT_FIXED_LEN]),
 }
 
-impl Default for WeightVector {
+impl<T> Default for WeightVector<T> {
     fn default() -> Self {
         Self::Variable(vec![])
     }
 }
 
-impl<Context> Decode<Context> for WeightVector {
-    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
-        let weight: Vec<i32> = Decode::decode(decoder)?;
+impl<T, Context> Decode<Context> for WeightVector<T>
+where
+    T: WeightElement + Decode<Context>,
+{
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        let weight: Vec<T> = Decode::decode(decoder)?;
         Ok(Self::from(weight))
     }
 }
-bincode::impl_borrow_decode!(WeightVector);
 
-impl Encode for WeightVector {
+impl<'de, T, Context> BorrowDecode<'de, Context> for WeightVector<T>
+where
+    T: WeightElement + Decode<Context>,
+{
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
+}
+
+impl<T> Encode for WeightVector<T>
+where
+    T: WeightElement + Encode,
+{
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         match self {
             Self::Variable(w) => {
@@ -67,9 +96,6 @@ impl Encode for WeightVector {
 
             #[cfg(feature = "fix-weight-length")]
             Self::Fixed(w) => {
-                #[cfg(feature = "portable-simd")]
-                let w = w.as_array();
-
                 Encode::encode(&crate::utils::trim_end_zeros(w).to_vec(), encoder)?;
             }
         }
@@ -98,7 +124,7 @@ impl WeightVector {
                 {
                     let ys = &mut ys[..WEIGHT_FIXED_LEN];
                     let mut y = I32Simd::from_slice(ys);
-                    y += w;
+                    y += I32Simd::from_array(*w);
                     ys.copy_from_slice(y.as_array());
                 }
             }
@@ -115,17 +141,13 @@ impl WeightVector {
     }
 }
 
-impl From<Vec<i32>> for WeightVector {
-    fn from(src: Vec<i32>) -> Self {
+impl<T: WeightElement> From<Vec<T>> for WeightVector<T> {
+    fn from(src: Vec<T>) -> Self {
         match src.len() {
             #[cfg(feature = "fix-weight-length")]
             0..=WEIGHT_FIXED_LEN => {
-                let mut weight = [0; WEIGHT_FIXED_LEN];
+                let mut weight = [T::default(); WEIGHT_FIXED_LEN];
                 weight[..src.len()].copy_from_slice(&src);
-
-                #[cfg(feature = "portable-simd")]
-                let weight = I32Simd::from(weight);
-
                 Self::Fixed(weight)
             }
 
@@ -144,6 +166,12 @@ impl PositionalWeight<Vec<i32>> {
     pub const fn new(offset: i16, weight: Vec<i32>) -> Self {
         Self { offset, weight }
     }
+
+    /// Returns the largest absolute value of the weights.
+    #[cfg(feature = "train")]
+    pub fn max_abs(&self) -> i32 {
+        self.weight.iter().map(|w| w.abs()).max().unwrap_or(0)
+    }
 }
 
 impl AddAssign<&Self> for PositionalWeight<Vec<i32>> {
@@ -174,6 +202,32 @@ impl From<PositionalWeight<Vec<i32>>> for PositionalWeight<WeightVector> {
 }
 
 impl PositionalWeight<WeightVector> {
+    /// Converts weights into a narrower type, or returns `None` if some weights do not fit in it.
+    pub fn try_narrow<T: WeightElement>(&self) -> Option<PositionalWeight<WeightVector<T>>> {
+        let weight = match &self.weight {
+            WeightVector::Variable(w) => WeightVector::Variable(
+                w.iter()
+                    .map(|&x| T::try_from(x).ok())
+                    .collect::<Option<_>>()?,
+            ),
+
+            #[cfg(feature = "fix-weight-length")]
+            WeightVector::Fixed(w) => {
+                let mut weight = [T::default(); WEIGHT_FIXED_LEN];
+                for (y, &x) in weight.iter_mut().zip(w) {
+                    *y = T::try_from(x).ok()?;
+                }
+                WeightVector::Fixed(weight)
+            }
+        };
+        Some(PositionalWeight {
+            offset: self.offset,
+            weight,
+        })
+    }
+}
+
+impl<T: WeightElement> PositionalWeight<WeightVector<T>> {
     #[inline(always)]
     pub fn add_score(&self, end: isize, ys: &mut [i32]) {
         let pos = end + isize::from(self.offset);
@@ -181,11 +235,11 @@ impl PositionalWeight<WeightVector> {
             WeightVector::Variable(w) => {
                 if pos >= 0 {
                     for (y, x) in ys[pos as usize..].iter_mut().zip(w) {
-                        *y += *x;
+                        *y += (*x).into();
                     }
                 } else if let Some(xs) = w.get((-pos) as usize..) {
                     for (y, x) in ys.iter_mut().zip(xs) {
-                        *y += *x;
+                        *y += (*x).into();
                     }
                 }
             }
@@ -197,14 +251,14 @@ impl PositionalWeight<WeightVector> {
                     .iter_mut()
                     .zip(w)
                 {
-                    *y += *x
+                    *y += (*x).into();
                 }
 
                 #[cfg(feature = "portable-simd")]
                 {
                     let ys = &mut ys[pos as usize..pos as usize + WEIGHT_FIXED_LEN];
                     let mut y = I32Simd::from_slice(ys);
-                    y += w;
+                    y += I32Simd::from_array(w.map(Into::into));
                     ys.copy_from_slice(y.as_array());
                 }
             }
@@ -266,14 +320,39 @@ impl AddAssign<&Self> for PositionalWeightWithTag {
 struct TagPredictor {
     tags: Vec<Vec<String>>,
     bias: WeightVector,
+    score_scales: Vec<Option<f64>>,
+    tag_tuples: Vec<Vec<Option<u32>>>,
+}
+
+/// Returns the index of the largest score. The first one is returned if there are ties.
+#[cfg(feature = "tag-prediction")]
+#[inline]
+fn argmax(scores: &[i32]) -> usize {
+    let mut idx = 0;
+    let mut max_score = i32::MIN;
+    for (i, &s) in scores.iter().enumerate() {
+        if s > max_score {
+            idx = i;
+            max_score = s;
+        }
+    }
+    idx
 }
 
 #[cfg(feature = "tag-prediction")]
 impl TagPredictor {
-    pub fn new(tags: Vec<Vec<String>>, bias: Vec<i32>) -> Self {
+    pub fn new(
+        tags: Vec<Vec<String>>,
+        bias: Vec<i32>,
+        mut score_scales: Vec<Option<f64>>,
+        tag_tuples: Vec<Vec<Option<u32>>>,
+    ) -> Self {
+        score_scales.resize(tags.len(), None);
         Self {
             tags,
             bias: bias.into(),
+            score_scales,
+            tag_tuples,
         }
     }
 
@@ -282,19 +361,81 @@ impl TagPredictor {
         &self.bias
     }
 
+    /// Predicts tags. If `constrained` is `true`, only combinations of tags observed in training
+    /// are predicted.
+    #[inline]
+    pub fn predict<'a>(
+        &'a self,
+        scores: &[i32],
+        tags: &mut [Option<Cow<'a, str>>],
+        constrained: bool,
+    ) {
+        if constrained && !self.tag_tuples.is_empty() {
+            self.predict_constrained(scores, tags);
+        } else {
+            self.predict_independent(scores, tags);
+        }
+    }
+
+    /// Predicts the best tag of each column independently.
     #[inline]
-    pub fn predict<'a>(&'a self, scores: &[i32], tags: &mut [Option<Cow<'a, str>>]) {
+    fn predict_independent<'a>(&'a self, scores: &[i32], tags: &mut [Option<Cow<'a, str>>]) {
         let mut offset = 0;
         for (tag_cands, tag) in self.tags.iter().zip(tags) {
             if tag_cands.len() >= 2 {
-                let mut idx = 0;
-                let mut max_score = i32::MIN;
-                for (i, &s) in scores[offset..offset + tag_cands.len()].iter().enumerate() {
-                    if s > max_score {
-                        idx = i;
-                        max_score = s;
-                    }
+                let idx = argmax(&scores[offset..offset + tag_cands.len()]);
+                tag.replace(Cow::Borrowed(&tag_cands[idx]));
+                offset += tag_cands.len();
+            } else {
+                *tag = tag_cands.first().map(|t| Cow::Borrowed(t.as_str()));
+            }
+        }
+    }
+
+    /// Predicts the best combination of tags among those observed in training.
+    ///
+    /// The score of a combination is the sum of the scores of its tags scaled back to the raw
+    /// scores, and columns not specified in the combination take the best tags independently.
+    fn predict_constrained<'a>(&'a self, scores: &[i32], tags: &mut [Option<Cow<'a, str>>]) {
+        let mut best_tuple = None;
+        let mut best_score = f64::NEG_INFINITY;
+        for tuple in &self.tag_tuples {
+            let mut score = 0.;
+            let mut offset = 0;
+            for (i, (tag_cands, &scale)) in self.tags.iter().zip(&self.score_scales).enumerate() {
+                if tag_cands.len() >= 2 {
+                    let column_scores = &scores[offset..offset + tag_cands.len()];
+                    let idx = tuple
+                        .get(i)
+                        .copied()
+                        .flatten()
+                        .map_or_else(|| argmax(column_scores), |idx| idx as usize);
+                    let Some(&s) = column_scores.get(idx) else {
+                        // The model is broken.
+                        score = f64::NEG_INFINITY;
+                        break;
+                    };
+                    // Scores of a model without scales are compared as they are.
+                    score += f64::from(s) * scale.unwrap_or(1.);
+                    offset += tag_cands.len();
                 }
+            }
+            if score > best_score {
+                best_tuple = Some(tuple);
+                best_score = score;
+            }
+        }
+        let Some(best_tuple) = best_tuple else {
+            self.predict_independent(scores, tags);
+            return;
+        };
+        let mut offset = 0;
+        for (i, (tag_cands, tag)) in self.tags.iter().zip(tags).enumerate() {
+            if tag_cands.len() >= 2 {
+                let idx = best_tuple.get(i).copied().flatten().map_or_else(
+                    || argmax(&scores[offset..offset + tag_cands.len()]),
+                    |idx| idx as usize,
+                );
                 tag.replace(Cow::Borrowed(&tag_cands[idx]));
                 offset += tag_cands.len();
             } else {
@@ -304,6 +445,77 @@ impl TagPredictor {
     }
 }
 
+/// Tag predictor for tokens that do not have their own tag models.
+#[cfg(feature = "tag-prediction")]
+#[derive(Decode, Encode)]
+struct FallbackTagPredictor {
+    predictor: TagPredictor,
+    // Weights indexed by the kinds of features returned by fallback_char_features() and
+    // fallback_type_features().
+    char_weights: Vec<SerializableHashMap<String, WeightVector>>,
+    type_weights: Vec<SerializableHashMap<Vec<u8>, WeightVector>>,
+}
+
+#[cfg(feature = "tag-prediction")]
+impl FallbackTagPredictor {
+    fn new(tag_model: TagModel) -> Self {
+        let mut char_weights = vec![];
+        for d in tag_model.char_ngram_model.0 {
+            for w in d.weights {
+                let kind = usize::from(w.rel_position);
+                if char_weights.len() <= kind {
+                    char_weights.resize_with(kind + 1, SerializableHashMap::default);
+                }
+                char_weights[kind].insert(d.ngram.clone(), w.weights.into());
+            }
+        }
+        let mut type_weights = vec![];
+        for d in tag_model.type_ngram_model.0 {
+            for w in d.weights {
+                let kind = usize::from(w.rel_position);
+                if type_weights.len() <= kind {
+                    type_weights.resize_with(kind + 1, SerializableHashMap::default);
+                }
+                type_weights[kind].insert(d.ngram.clone(), w.weights.into());
+            }
+        }
+        Self {
+            predictor: TagPredictor::new(
+                tag_model.tags,
+                tag_model.bias,
+                tag_model.score_scales,
+                tag_model.tag_tuples,
+            ),
+            char_weights,
+            type_weights,
+        }
+    }
+
+    fn compute_scores(&self, surface: &str, char_types: &[u8], scores: &mut Vec<i32>) {
+        scores.clear();
+        scores.resize(self.predictor.bias().len(), 0);
+        self.predictor.bias().add_scores(scores);
+        for (weights, ngram) in self
+            .char_weights
+            .iter()
+            .zip(fallback_char_features(surface))
+        {
+            if let Some(w) = ngram.and_then(|ngram| weights.get(ngram)) {
+                w.add_scores(scores);
+            }
+        }
+        for (weights, ngram) in self
+            .type_weights
+            .iter()
+            .zip(fallback_type_features(char_types))
+        {
+            if let Some(w) = weights.get(ngram) {
+                w.add_scores(scores);
+            }
+        }
+    }
+}
+
 pub struct PredictorData {
     char_scorer: Option<CharScorer>,
     type_scorer: Option<TypeScorer>,
@@ -312,7 +524,11 @@ pub struct PredictorData {
     #[cfg(feature = "tag-prediction")]
     tag_predictor: Option<SerializableHashMap<String, (u32, TagPredictor)>>,
     #[cfg(feature = "tag-prediction")]
+    fallback_tag_predictor: Option<FallbackTagPredictor>,
+    #[cfg(feature = "tag-prediction")]
     n_tags: usize,
+    #[cfg(feature = "tag-prediction")]
+    tag_schema: Vec<TagColumn>,
 }
 
 impl<'de, Context> BorrowDecode<'de, Context> for PredictorData {
@@ -336,7 +552,11 @@ impl<'de, Context> BorrowDecode<'de, Context> for PredictorData {
         #[cfg(feature = "tag-prediction")]
         let tag_predictor = Decode::decode(decoder)?;
         #[cfg(feature = "tag-prediction")]
+        let fallback_tag_predictor = Decode::decode(decoder)?;
+        #[cfg(feature = "tag-prediction")]
         let n_tags = Decode::decode(decoder)?;
+        #[cfg(feature = "tag-prediction")]
+        let tag_schema = Decode::decode(decoder)?;
         Ok(Self {
             char_scorer,
             type_scorer,
@@ -344,7 +564,11 @@ impl<'de, Context> BorrowDecode<'de, Context> for PredictorData {
             #[cfg(feature = "tag-prediction")]
             tag_predictor,
             #[cfg(feature = "tag-prediction")]
+            fallback_tag_predictor,
+            #[cfg(feature = "tag-prediction")]
             n_tags,
+            #[cfg(feature = "tag-prediction")]
+            tag_schema,
         })
     }
 }
@@ -368,7 +592,11 @@ impl Encode for PredictorData {
         #[cfg(feature = "tag-prediction")]
         Encode::encode(&self.tag_predictor, encoder)?;
         #[cfg(feature = "tag-prediction")]
+        Encode::encode(&self.fallback_tag_predictor, encoder)?;
+        #[cfg(feature = "tag-prediction")]
         Encode::encode(&self.n_tags, encoder)?;
+        #[cfg(feature = "tag-prediction")]
+        Encode::encode(&self.tag_schema, encoder)?;
         Ok(())
     }
 }
@@ -434,6 +662,8 @@ pub struct Predictor {
     data: PredictorData,
     #[cfg(feature = "tag-prediction")]
     tag_scores: bool,
+    #[cfg(feature = "tag-prediction")]
+    constrain_tag_tuples: bool,
 }
 
 impl Predictor {
@@ -454,6 +684,8 @@ impl Predictor {
         let mut tag_type_ngram_model = vec![];
         #[cfg(feature = "tag-prediction")]
         let mut n_tags = 0;
+        #[cfg(feature = "tag-prediction")]
+        let mut fallback_tag_predictor = None;
 
         #[cfg(not(feature = "tag-prediction"))]
         if predict_tags {
@@ -462,14 +694,24 @@ impl Predictor {
         #[cfg(feature = "tag-prediction")]
         let tag_predictor = predict_tags.then(|| {
             let mut tag_predictor = HashMap::new();
-            for (i, tag_model) in model.0.tag_models.into_iter().enumerate() {
+            for tag_model in model.0.tag_models {
                 n_tags = n_tags.max(tag_model.tags.len());
+                if tag_model.is_fallback() {
+                    fallback_tag_predictor = Some(FallbackTagPredictor::new(tag_model));
+                    continue;
+                }
+                let token_id = u32::try_from(tag_char_ngram_model.len()).unwrap();
                 // token does not duplicate in the model.
                 tag_predictor.insert(
                     tag_model.token,
                     (
-                        u32::try_from(i).unwrap(),
-                        TagPredictor::new(tag_model.tags, tag_model.bias),
+                        token_id,
+                        TagPredictor::new(
+                            tag_model.tags,
+                            tag_model.bias,
+                            tag_model.score_scales,
+                            tag_model.tag_tuples,
+                        ),
                     ),
                 );
                 tag_char_ngram_model.push(tag_model.char_ngram_model);
@@ -478,9 +720,15 @@ impl Predictor {
             SerializableHashMap(tag_predictor)
         });
 
+        // Weights of the same word in different dictionaries are summed up by the scorer.
+        let mut dict_model = model.0.dict_model;
+        for (_, named_dict_model) in model.0.named_dict_models {
+            dict_model.0.extend(named_dict_model.0);
+        }
+
         let char_scorer = CharScorer::new(
             model.0.char_ngram_model,
-            model.0.dict_model,
+            dict_model,
             model.0.char_window_size,
             #[cfg(feature = "tag-prediction")]
             tag_char_ngram_model,
@@ -500,19 +748,71 @@ impl Predictor {
                 #[cfg(feature = "tag-prediction")]
                 tag_predictor,
                 #[cfg(feature = "tag-prediction")]
+                fallback_tag_predictor,
+                #[cfg(feature = "tag-prediction")]
                 n_tags,
+                #[cfg(feature = "tag-prediction")]
+                tag_schema: model.0.tag_schema,
             },
             #[cfg(feature = "tag-prediction")]
             tag_scores: false,
+            #[cfg(feature = "tag-prediction")]
+            constrain_tag_tuples: false,
         })
     }
 
+    /// Returns the schema of tag columns. See [`Model::tag_schema()`].
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn tag_schema(&self) -> &[TagColumn] {
+        &self.data.tag_schema
+    }
+
+    /// Returns the index of the tag column with the given name.
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn tag_column_index(&self, name: &str) -> Option<usize> {
+        tag_column_index(&self.data.tag_schema, name)
+    }
+
+    /// Returns the index of the first lemma column.
+    #[cfg(feature = "tag-prediction")]
+    pub(crate) fn lemma_column(&self) -> Option<usize> {
+        lemma_columns(&self.data.tag_schema).next()
+    }
+
+    /// Decodes a tag candidate of the given column predicted for the given surface.
+    ///
+    /// Edit scripts in lemma columns are converted into lemmas. Returns `None` if the script
+    /// cannot be applied to the surface.
+    #[cfg(feature = "tag-prediction")]
+    pub(crate) fn decode_tag_candidate<'b>(
+        &self,
+        column: usize,
+        surface: &str,
+        candidate: &'b str,
+    ) -> Option<Cow<'b, str>> {
+        match self.data.tag_schema.get(column) {
+            Some(c) if c.kind() == TagColumnKind::Lemma => {
+                apply_lemma_edit_script(surface, candidate).map(Cow::Owned)
+            }
+            _ => Some(Cow::Borrowed(candidate)),
+        }
+    }
+
     /// Stores tag scores if the given `flag` is `true`.
     #[cfg(feature = "tag-prediction")]
     pub const fn store_tag_scores(&mut self, flag: bool) {
         self.tag_scores = flag;
     }
 
+    /// Restricts predicted tags of each token to combinations observed in training if the given
+    /// `flag` is `true`. Otherwise, each tag column is predicted independently.
+    #[cfg(feature = "tag-prediction")]
+    pub const fn constrain_tag_tuples(&mut self, flag: bool) {
+        self.constrain_tag_tuples = flag;
+    }
+
     /// Predicts word boundaries of the given sentence.
     /// If necessary, this function also prepares for predicting tags.
     pub fn predict<'a>(&'a self, sentence: &mut Sentence<'_, 'a>) {
@@ -595,9 +895,33 @@ impl Predictor {
                         tag_predictor.predict(
                             &scores,
                             &mut sentence.tags[i * self.data.n_tags..(i + 1) * self.data.n_tags],
+                            self.constrain_tag_tuples,
                         );
                         if !sentence.tag_scores.is_empty() {
-                            sentence.tag_scores[i].replace((&tag_predictor.tags, scores));
+                            sentence.tag_scores[i].replace((
+                                &tag_predictor.tags,
+                                &tag_predictor.score_scales,
+                                scores,
+                            ));
+                            scores = vec![];
+                        }
+                    } else if let Some(fallback) = self.data.fallback_tag_predictor.as_ref() {
+                        fallback.compute_scores(
+                            token,
+                            &sentence.char_types[range_start..i + 1],
+                            &mut scores,
+                        );
+                        fallback.predictor.predict(
+                            &scores,
+                            &mut sentence.tags[i * self.data.n_tags..(i + 1) * self.data.n_tags],
+                            self.constrain_tag_tuples,
+                        );
+                        if !sentence.tag_scores.is_empty() {
+                            sentence.tag_scores[i].replace((
+                                &fallback.predictor.tags,
+                                &fallback.predictor.score_scales,
+                                scores,
+                            ));
                             scores = vec![];
                         }
                     }
@@ -628,12 +952,60 @@ impl Predictor {
                     }
                 }
                 let i = sentence.len() - 1;
-                tag_predictor.predict(&scores, &mut sentence.tags[i * self.data.n_tags..]);
+                tag_predictor.predict(
+                    &scores,
+                    &mut sentence.tags[i * self.data.n_tags..],
+                    self.constrain_tag_tuples,
+                );
                 if !sentence.tag_scores.is_empty() {
-                    sentence.tag_scores[i].replace((&tag_predictor.tags, scores));
+                    sentence.tag_scores[i].replace((
+                        &tag_predictor.tags,
+                        &tag_predictor.score_scales,
+                        scores,
+                    ));
+                }
+            } else if let Some(fallback) = self.data.fallback_tag_predictor.as_ref() {
+                fallback.compute_scores(token, &sentence.char_types[range_start..], &mut scores);
+                let i = sentence.len() - 1;
+                fallback.predictor.predict(
+                    &scores,
+                    &mut sentence.tags[i * self.data.n_tags..],
+                    self.constrain_tag_tuples,
+                );
+                if !sentence.tag_scores.is_empty() {
+                    sentence.tag_scores[i].replace((
+                        &fallback.predictor.tags,
+                        &fallback.predictor.score_scales,
+                        scores,
+                    ));
                 }
             }
         }
+        self.decode_lemmas(sentence);
+    }
+
+    /// Replaces edit scripts predicted in lemma columns with lemmas of the tokens.
+    #[cfg(feature = "tag-prediction")]
+    fn decode_lemmas(&self, sentence: &mut Sentence<'_, '_>) {
+        if self.lemma_column().is_none() {
+            return;
+        }
+        let mut start = 0;
+        for end in 1..=sentence.len() {
+            if end != sentence.len()
+                && sentence.boundaries[end - 1] != CharacterBoundary::WordBoundary
+            {
+                continue;
+            }
+            for column in lemma_columns(&self.data.tag_schema).filter(|&c| c < self.data.n_tags) {
+                let idx = (end - 1) * self.data.n_tags + column;
+                let lemma = sentence.tags[idx].as_deref().and_then(|script| {
+                    apply_lemma_edit_script(sentence.text_substring(start, end), script)
+                });
+                sentence.tags[idx] = lemma.map(Cow::Owned);
+            }
+            start = end;
+        }
     }
 
     /// Serializes the predictor into a Vec.
@@ -658,6 +1030,8 @@ impl Predictor {
                 data: predictor_data,
                 #[cfg(feature = "tag-prediction")]
                 tag_scores: false,
+                #[cfg(feature = "tag-prediction")]
+                constrain_tag_tuples: false,
             },
             &data[size..],
         ))
@@ -668,11 +1042,10 @@ impl Predictor {
 mod tests {
     use super::*;
 
-    use crate::dict_model::{DictModel, WordWeightRecord};
-    use crate::model::TagModel;
-    use crate::ngram_model::{NgramData, NgramModel, TagNgramData, TagNgramModel, TagWeight};
+    use crate::dict_model::WordWeightRecord;
+    use crate::model::TagColumn;
+    use crate::test_utils::create_test_model;
     use crate::CharacterBoundary::*;
-    use crate::CharacterType::*;
 
     #[test]
     fn test_positional_weight_add_assign_1() {
@@ -746,97 +1119,6 @@ mod tests {
         assert_eq!(vec![2, 4, 8, 0, 0, 1, 2, 3, 4], y.weight);
     }
 
-    fn create_test_model() -> Model {
-        // input:    こ  の  人  は  地  球  人  だ
-        // n-grams:
-        //   この人:   -2   3   4
-        //   人だ:                     -5   6   7
-        // n-grams:
-        //   HHK:     -11  12  13
-        //   KH:      -14  15  16  17 -18
-        //                            -14  15  16
-        // dict:
-        //   人:           19  20          19  20
-        //   地球:                 21 -22  23
-        Model::new(
-            NgramModel(vec![
-                NgramData {
-                    ngram: "この人".into(),
-                    weights: vec![1, -2, 3, 4],
-                },
-                NgramData {
-                    ngram: "人だ".into(),
-                    weights: vec![-5, 6, 7, 8, 9],
-                },
-            ]),
-            NgramModel(vec![
-                NgramData {
-                    ngram: vec![Hiragana as u8, Hiragana as u8, Kanji as u8],
-                    weights: vec![10, -11, 12, 13],
-                },
-                NgramData {
-                    ngram: vec![Kanji as u8, Hiragana as u8],
-                    weights: vec![-14, 15, 16, 17, -18],
-                },
-            ]),
-            DictModel(vec![
-                WordWeightRecord {
-                    word: "人".into(),
-                    weights: vec![19, 20],
-                    comment: "".into(),
-                },
-                WordWeightRecord {
-                    word: "地球".into(),
-                    weights: vec![21, -22, 23],
-                    comment: "".into(),
-                },
-            ]),
-            5,
-            3,
-            3,
-            vec![
-                TagModel {
-                    token: "人".into(),
-                    tags: vec![
-                        vec!["名詞".into(), "接尾辞".into()],
-                        vec!["ジン".into(), "ヒト".into()],
-                    ],
-                    char_ngram_model: TagNgramModel(vec![TagNgramData {
-                        ngram: "は地球人".into(),
-                        weights: vec![TagWeight {
-                            rel_position: 0,
-                            weights: vec![-32, 33, 34, -35],
-                        }],
-                    }]),
-                    type_ngram_model: TagNgramModel(vec![TagNgramData {
-                        ngram: vec![Hiragana as u8, Kanji as u8, Hiragana as u8],
-                        weights: vec![TagWeight {
-                            rel_position: 1,
-                            weights: vec![36, -37, -38, 39],
-                        }],
-                    }]),
-                    bias: vec![40, 41, 42, 43],
-                },
-                TagModel {
-                    token: "地球".into(),
-                    tags: vec![
-                        vec!["名詞".into()],
-                        vec!["マンホーム".into(), "チキュー".into()],
-                    ],
-                    char_ngram_model: TagNgramModel(vec![TagNgramData {
-                        ngram: "は地球人".into(),
-                        weights: vec![TagWeight {
-                            rel_position: 1,
-                            weights: vec![-44, 45],
-                        }],
-                    }]),
-                    type_ngram_model: TagNgramModel(vec![]),
-                    bias: vec![46, 47],
-                },
-            ],
-        )
-    }
-
     #[test]
     fn test_predict_boundaries() {
         let model = create_test_model();
@@ -858,6 +1140,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_predict_boundaries_named_dict() {
+        let mut model = create_test_model();
+        model.replace_named_dictionary(
+            "extra",
+            vec![
+                WordWeightRecord::new("地球".into(), vec![1, 2, 3], "".into()).unwrap(),
+                WordWeightRecord::new("は".into(), vec![-100, 0], "".into()).unwrap(),
+            ],
+        );
+        assert_eq!(vec!["extra"], model.dictionary_names().collect::<Vec<_>>());
+        let predictor = Predictor::new(model, false).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        assert_eq!(&[-22, 54, -42, 44, -52, 71, 48], sentence.boundary_scores(),);
+        assert_eq!(
+            &[
+                NotWordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+                WordBoundary,
+                WordBoundary
+            ],
+            sentence.boundaries(),
+        );
+    }
+
     #[cfg(feature = "tag-prediction")]
     #[test]
     fn test_predict_tags() {
@@ -971,6 +1282,328 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_serialization_tags_variant_indices() {
+        // Predictors serialized by older versions store tag scorers at these variant indices.
+        let predictor = Predictor::new(create_test_model(), true).unwrap();
+        let config = bincode::config::standard();
+        let char_scorer =
+            bincode::encode_to_vec(predictor.data.char_scorer.as_ref().unwrap(), config).unwrap();
+        assert_eq!(1, char_scorer[0]);
+        let type_scorer =
+            bincode::encode_to_vec(predictor.data.type_scorer.as_ref().unwrap(), config).unwrap();
+        assert_eq!(
+            if cfg!(feature = "cache-type-score") {
+                2
+            } else {
+                1
+            },
+            type_scorer[0],
+        );
+
+        let data = predictor.serialize_to_vec().unwrap();
+        let (predictor, _) = unsafe { Predictor::deserialize_from_slice_unchecked(&data).unwrap() };
+        assert!(matches!(
+            predictor.data.char_scorer,
+            Some(CharScorer::BoundaryTag(_)),
+        ));
+        assert!(matches!(
+            predictor.data.type_scorer,
+            Some(TypeScorer::BoundaryTag(_)),
+        ));
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    fn predict_tags(model: Model) -> Vec<Option<String>> {
+        let predictor = Predictor::new(model, true).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        sentence
+            .tags()
+            .iter()
+            .map(|t| t.as_deref().map(String::from))
+            .collect()
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    fn swapped_tags() -> Vec<Option<String>> {
+        [
+            None,
+            None,
+            None,
+            None,
+            Some("ヒト"),
+            Some("名詞"),
+            None,
+            None,
+            None,
+            None,
+            Some("チキュー"),
+            Some("名詞"),
+            Some("ジン"),
+            Some("接尾辞"),
+            None,
+            None,
+        ]
+        .into_iter()
+        .map(|t| t.map(String::from))
+        .collect()
+    }
+
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[test]
+    fn test_tag_probabilities() {
+        let mut model = create_test_model();
+        model.0.tag_models[1].score_scales = vec![Some(1.), Some(1.)];
+        let mut predictor = Predictor::new(model, true).unwrap();
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence.iter_tokens().nth(3).unwrap();
+        assert_eq!("地球", token.surface());
+        assert!(token.raw_tag_candidates().is_none());
+        assert!(token.decoded_tag_candidates().is_none());
+        assert!(token.tag_probabilities().is_none());
+        assert!(token.tag_confidence(1).is_none());
+
+        predictor.store_tag_scores(true);
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence.iter_tokens().next().unwrap();
+        assert_eq!("この", token.surface());
+        assert!(token.raw_tag_candidates().is_none());
+        assert!(token.tag_probabilities().is_none());
+
+        let token = sentence.iter_tokens().nth(3).unwrap();
+        let cands = token.raw_tag_candidates().unwrap();
+        let probs = token.tag_probabilities().unwrap();
+        assert_eq!(vec![("名詞".into(), 1.)], probs[0]);
+        let (s0, s1) = (f64::from(cands[1][0].1), f64::from(cands[1][1].1));
+        let expected = [1. / (1. + (s1 - s0).exp()), 1. / (1. + (s0 - s1).exp())];
+        assert_eq!("マンホーム", probs[1][0].0);
+        assert_eq!("チキュー", probs[1][1].0);
+        assert!((expected[0] - probs[1][0].1).abs() < 1e-9);
+        assert!((expected[1] - probs[1][1].1).abs() < 1e-9);
+        assert_eq!(Some(1.), token.tag_confidence(0));
+        assert_eq!(Some(expected[0].max(expected[1])), token.tag_confidence(1));
+        assert!(token.tag_confidence(2).is_none());
+    }
+
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[test]
+    fn test_tag_probabilities_without_scales() {
+        let model = create_test_model();
+        let mut predictor = Predictor::new(model, true).unwrap();
+        predictor.store_tag_scores(true);
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence.iter_tokens().nth(3).unwrap();
+        assert_eq!(2, token.raw_tag_candidates().unwrap()[1].len());
+        assert!(token.tag_probabilities().is_none());
+        assert!(token.tag_confidence(1).is_none());
+    }
+
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[test]
+    fn test_tag_probabilities_0_5_model() {
+        let mut f = std::fs::File::open("../resources/model.bin").unwrap();
+        let mut magic = [0; 25];
+        std::io::Read::read_exact(&mut f, &mut magic).unwrap();
+        assert_eq!(b"VaporettoTokenizer 0.5.0\n", &magic);
+
+        let f = std::fs::File::open("../resources/model.bin").unwrap();
+        let model = Model::read(f).unwrap();
+        let mut predictor = Predictor::new(model, true).unwrap();
+        predictor.store_tag_scores(true);
+        let mut sentence = Sentence::from_raw("まぁ社長は火星猫だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence
+            .iter_tokens()
+            .find(|t| {
+                t.raw_tag_candidates()
+                    .is_some_and(|cands| cands.iter().any(|c| c.len() >= 2))
+            })
+            .unwrap();
+        assert!(token.tag_probabilities().is_none());
+    }
+
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[test]
+    fn test_tag_probabilities_scaled() {
+        let mut model = create_test_model();
+        model.0.tag_models[1].score_scales = vec![Some(1.), Some(0.)];
+        let mut predictor = Predictor::new(model, true).unwrap();
+        predictor.store_tag_scores(true);
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence.iter_tokens().nth(3).unwrap();
+        assert_eq!(
+            vec![("マンホーム".into(), 0.5), ("チキュー".into(), 0.5)],
+            token.tag_probabilities().unwrap()[1],
+        );
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_constrained() {
+        let mut model = create_test_model();
+        model.0.tag_models[0].tag_tuples = vec![vec![Some(0), Some(0)], vec![Some(1), Some(1)]];
+        let mut predictor = Predictor::new(model, true).unwrap();
+        predictor.constrain_tag_tuples(true);
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        assert_eq!(
+            &[
+                None,
+                None,
+                None,
+                None,
+                Some(Cow::Borrowed("接尾辞")),
+                Some(Cow::Borrowed("ヒト")),
+                None,
+                None,
+                None,
+                None,
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("チキュー")),
+                Some(Cow::Borrowed("名詞")),
+                Some(Cow::Borrowed("ジン")),
+                None,
+                None,
+            ],
+            sentence.tags()
+        );
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_reordered_columns() {
+        let mut model = create_test_model();
+        model.select_tag_columns(&[1, 0]).unwrap();
+        assert_eq!(2, model.n_tag_columns());
+        assert_eq!(swapped_tags(), predict_tags(model));
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_tag_schema() {
+        let mut model = create_test_model();
+        model
+            .set_tag_schema(vec![TagColumn::new("pos"), TagColumn::new("pron")])
+            .unwrap();
+        assert_eq!(Some(1), model.tag_column_index("pron"));
+        assert_eq!(None, model.tag_column_index(""));
+
+        let predictor = Predictor::new(model, true).unwrap();
+        let data = predictor.serialize_to_vec().unwrap();
+        let (predictor, _) = unsafe { Predictor::deserialize_from_slice_unchecked(&data).unwrap() };
+        assert_eq!("pos", predictor.tag_schema()[0].name());
+        let mut sentence = Sentence::from_raw("この人は地球人だ").unwrap();
+        predictor.predict(&mut sentence);
+        sentence.fill_tags();
+        let token = sentence.iter_tokens().nth(3).unwrap();
+        assert_eq!("地球", token.surface());
+        assert_eq!(Some("名詞"), token.tag_by_name("pos"));
+        assert_eq!(Some("チキュー"), token.tag_by_name("pron"));
+        assert_eq!(None, token.tag_by_name("lemma"));
+
+        let s = Sentence::from_tokenized("地球/名詞/チキュー").unwrap();
+        assert_eq!(None, s.iter_tokens().next().unwrap().tag_by_name("pos"));
+    }
+
+    #[test]
+    fn test_tag_schema_reordered_columns() {
+        let mut model = create_test_model();
+        model
+            .set_tag_schema(vec![TagColumn::new("pos"), TagColumn::new("pron")])
+            .unwrap();
+        model.select_tag_columns(&[1]).unwrap();
+        assert_eq!(&[TagColumn::new("pron")], model.tag_schema());
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_dropped_column() {
+        let mut model = create_test_model();
+        model.select_tag_columns(&[1]).unwrap();
+        assert_eq!(1, model.n_tag_columns());
+        assert!(model.select_tag_columns(&[1]).is_err());
+        assert_eq!(
+            vec![
+                None,
+                None,
+                Some(String::from("ヒト")),
+                None,
+                None,
+                Some(String::from("チキュー")),
+                Some(String::from("ジン")),
+                None,
+            ],
+            predict_tags(model),
+        );
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn test_predict_tags_appended_columns() {
+        let mut model = create_test_model();
+        model.select_tag_columns(&[1]).unwrap();
+        let mut pos_model = create_test_model();
+        pos_model.select_tag_columns(&[0]).unwrap();
+        model.append_tag_columns(pos_model.0.tag_models).unwrap();
+        assert_eq!(2, model.n_tag_columns());
+        assert_eq!(swapped_tags(), predict_tags(model));
+    }
+
+    #[cfg(feature = "train")]
+    #[test]
+    fn test_quantized_weights_narrowed() {
+        use crate::char_scorer::CharScorer;
+        use crate::trainer::{SolverType, Trainer};
+
+        let sents: Vec<_> = [
+            "まぁ 社長 は 火星 猫 だ",
+            "まぁ 良い だろう",
+            "この 人 は 地球 人 だ",
+            "火星 猫 の 生態 の 調査 結果",
+        ]
+        .iter()
+        .map(|s| Sentence::from_tokenized(s).unwrap())
+        .collect();
+        let char_scorer = |bit_depth, narrow| {
+            let mut trainer = Trainer::new(3, 3, 3, 3, vec![], 0, &[]).unwrap();
+            trainer.set_quantize_bit_depth(bit_depth).unwrap();
+            trainer.set_narrow_weights(narrow);
+            for s in &sents {
+                trainer.add_example(s);
+            }
+            let model = trainer
+                .train(0.01, 1., SolverType::L2RegularizedL2LossSVCDual)
+                .unwrap();
+            Predictor::new(model, false).unwrap().data.char_scorer
+        };
+        assert!(matches!(
+            char_scorer(8, true),
+            Some(CharScorer::BoundaryI8(_))
+        ));
+        assert!(matches!(
+            char_scorer(16, true),
+            Some(CharScorer::BoundaryI16(_))
+        ));
+        // Without narrowing, summed weights can exceed the bit depth.
+        assert!(matches!(
+            char_scorer(8, false),
+            Some(CharScorer::BoundaryI16(_))
+        ));
+    }
+
     #[cfg(feature = "tag-prediction")]
     #[test]
     #[should_panic]
@@ -0,0 +1,246 @@
+//! Selection of uncertain boundaries for active learning.
+
+use alloc::collections::BinaryHeap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::sentence::Sentence;
+
+/// A character boundary selected for annotation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoundaryQuery {
+    /// Identifier of the sentence given to [`UncertaintySampler::add_sentence()`].
+    pub sentence_id: usize,
+
+    /// Position of the boundary, where `i` is the boundary between the `i`-th and `i+1`-th
+    /// characters.
+    pub position: usize,
+
+    /// The predicted boundary score. A positive value means a word boundary.
+    pub score: i32,
+}
+
+impl BoundaryQuery {
+    /// Writes the context of the queried boundary in the partial annotation format.
+    ///
+    /// Only the queried boundary is annotated with its predicted value, and the other boundaries
+    /// are left unknown. At most `window_size` characters on each side of the boundary are
+    /// written.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentence` - The sentence containing the queried boundary.
+    /// * `window_size` - The number of context characters on each side.
+    /// * `buf` - A buffer to write the text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{BoundaryQuery, Sentence};
+    ///
+    /// let s = Sentence::from_raw("火星猫の生態").unwrap();
+    /// let query = BoundaryQuery {
+    ///     sentence_id: 0,
+    ///     position: 2,
+    ///     score: 5,
+    /// };
+    ///
+    /// let mut buf = String::new();
+    /// query.write_partial_annotation_text(&s, 2, &mut buf);
+    /// assert_eq!("星 猫|の 生", buf);
+    /// ```
+    pub fn write_partial_annotation_text(
+        &self,
+        sentence: &Sentence,
+        window_size: usize,
+        buf: &mut String,
+    ) {
+        buf.clear();
+        let start = (self.position + 1).saturating_sub(window_size);
+        let end = self.position + 1 + window_size;
+        for (i, c) in sentence
+            .as_raw_text()
+            .chars()
+            .enumerate()
+            .skip(start)
+            .take(end - start)
+        {
+            if i != start {
+                buf.push(if i != self.position + 1 {
+                    ' '
+                } else if self.score > 0 {
+                    '|'
+                } else {
+                    '-'
+                });
+            }
+            buf.push(c);
+        }
+    }
+}
+
+/// Uncertainty sampler that selects boundaries with the lowest margins.
+///
+/// The margin of a boundary is the absolute value of its predicted score, so the boundaries
+/// closest to the decision threshold are selected.
+///
+#[cfg_attr(
+    feature = "std",
+    doc = "
+# Examples
+
+```
+use std::fs::File;
+
+use vaporetto::{Model, Predictor, Sentence, UncertaintySampler};
+
+let f = File::open(\"../resources/model.bin\").unwrap();
+let model = Model::read(f).unwrap();
+let predictor = Predictor::new(model, false).unwrap();
+
+let mut sampler = UncertaintySampler::new(2);
+for (i, text) in [\"まぁ社長は火星猫だ\", \"まぁ良いだろう\"].into_iter().enumerate() {
+    let mut s = Sentence::from_raw(text).unwrap();
+    predictor.predict(&mut s);
+    sampler.add_sentence(i, &s);
+}
+let queries = sampler.into_queries();
+assert_eq!(2, queries.len());
+assert!(queries[0].score.unsigned_abs() <= queries[1].score.unsigned_abs());
+```
+"
+)]
+pub struct UncertaintySampler {
+    n_queries: usize,
+    heap: BinaryHeap<(u32, usize, usize, i32)>,
+}
+
+impl UncertaintySampler {
+    /// Creates a new sampler that selects at most `n_queries` boundaries.
+    pub fn new(n_queries: usize) -> Self {
+        Self {
+            n_queries,
+            heap: BinaryHeap::with_capacity(n_queries + 1),
+        }
+    }
+
+    /// Adds candidate boundaries of the given sentence.
+    ///
+    /// The sentence must be predicted by [`Predictor::predict()`](crate::Predictor::predict)
+    /// beforehand; otherwise, no boundary is added.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentence_id` - An identifier of the sentence that will be stored in the queries.
+    /// * `sentence` - A predicted sentence.
+    pub fn add_sentence(&mut self, sentence_id: usize, sentence: &Sentence) {
+        if self.n_queries == 0 {
+            return;
+        }
+        for (position, &score) in sentence.boundary_scores().iter().enumerate() {
+            let item = (score.unsigned_abs(), sentence_id, position, score);
+            if self.heap.len() < self.n_queries {
+                self.heap.push(item);
+            } else if let Some(mut top) = self.heap.peek_mut() {
+                if item < *top {
+                    *top = item;
+                }
+            }
+        }
+    }
+
+    /// Returns the selected boundaries in ascending order of margins.
+    pub fn into_queries(self) -> Vec<BoundaryQuery> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(_, sentence_id, position, score)| BoundaryQuery {
+                sentence_id,
+                position,
+                score,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sentence::CharacterBoundary;
+
+    fn predicted_sentence(text: &'static str, scores: &[i32]) -> Sentence<'static, 'static> {
+        let mut s = Sentence::from_raw(text).unwrap();
+        s.score_padding = 0;
+        s.boundary_scores = scores.to_vec();
+        for (b, &score) in s.boundaries.iter_mut().zip(scores) {
+            *b = if score > 0 {
+                CharacterBoundary::WordBoundary
+            } else {
+                CharacterBoundary::NotWordBoundary
+            };
+        }
+        s
+    }
+
+    #[test]
+    fn test_select_lowest_margins() {
+        let s1 = predicted_sentence("火星猫", &[-5, 1]);
+        let s2 = predicted_sentence("の生態", &[-2, 8]);
+        let mut sampler = UncertaintySampler::new(3);
+        sampler.add_sentence(0, &s1);
+        sampler.add_sentence(1, &s2);
+
+        assert_eq!(
+            vec![
+                BoundaryQuery {
+                    sentence_id: 0,
+                    position: 1,
+                    score: 1,
+                },
+                BoundaryQuery {
+                    sentence_id: 1,
+                    position: 0,
+                    score: -2,
+                },
+                BoundaryQuery {
+                    sentence_id: 0,
+                    position: 0,
+                    score: -5,
+                },
+            ],
+            sampler.into_queries(),
+        );
+    }
+
+    #[test]
+    fn test_select_no_queries() {
+        let s = predicted_sentence("火星猫", &[-5, 1]);
+        let mut sampler = UncertaintySampler::new(0);
+        sampler.add_sentence(0, &s);
+
+        assert!(sampler.into_queries().is_empty());
+    }
+
+    #[test]
+    fn test_write_partial_annotation_text() {
+        let s = Sentence::from_raw("火星猫の生態").unwrap();
+        let mut buf = String::new();
+
+        let query = BoundaryQuery {
+            sentence_id: 0,
+            position: 0,
+            score: -3,
+        };
+        query.write_partial_annotation_text(&s, 2, &mut buf);
+        assert_eq!("火-星 猫", buf);
+
+        let query = BoundaryQuery {
+            sentence_id: 0,
+            position: 4,
+            score: 0,
+        };
+        query.write_partial_annotation_text(&s, 10, &mut buf);
+        assert_eq!("火 星 猫 の 生-態", buf);
+    }
+}
@@ -1,12 +1,18 @@
 use alloc::borrow::Cow;
 use alloc::string::String;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::ops::Range;
+use core::str::FromStr;
+
+use hashbrown::HashMap;
 
 use crate::errors::{Result, VaporettoError};
 use crate::predictor::Predictor;
 
 /// Character type.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum CharacterType {
     /// Digit character. (e.g. 0, 1, 2, ...)
@@ -69,6 +75,7 @@ impl CharacterType {
 
 /// Boundary type.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum CharacterBoundary {
     /// Inner of a word.
@@ -81,6 +88,87 @@ pub enum CharacterBoundary {
     Unknown = 2,
 }
 
+/// Field of the CoNLL-U format corresponding to a tag column.
+///
+/// The FORM field is always used as the surface of each token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ConlluField {
+    /// LEMMA field.
+    Lemma,
+
+    /// UPOS field.
+    Upos,
+
+    /// XPOS field.
+    Xpos,
+
+    /// FEATS field.
+    Feats,
+
+    /// Value of the given attribute in the MISC field, e.g., `Reading` for `Reading=カセー`.
+    Misc(String),
+}
+
+impl ConlluField {
+    /// Returns the name of this field, which is parsed back by [`str::parse()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::ConlluField;
+    ///
+    /// assert_eq!("upos", ConlluField::Upos.name());
+    /// assert_eq!("Reading", ConlluField::Misc("Reading".into()).name());
+    /// assert_eq!(ConlluField::Upos, "UPOS".parse().unwrap());
+    /// assert_eq!(ConlluField::Misc("Reading".into()), "Reading".parse().unwrap());
+    /// ```
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Lemma => "lemma",
+            Self::Upos => "upos",
+            Self::Xpos => "xpos",
+            Self::Feats => "feats",
+            Self::Misc(key) => key,
+        }
+    }
+
+    fn value<'a>(&self, fields: &[&'a str]) -> Option<&'a str> {
+        let value = match self {
+            Self::Lemma => fields[2],
+            Self::Upos => fields[3],
+            Self::Xpos => fields[4],
+            Self::Feats => fields[5],
+            Self::Misc(key) => fields[9].split('|').find_map(|attr| {
+                attr.split_once('=')
+                    .and_then(|(k, v)| (k == key).then_some(v))
+            })?,
+        };
+        (!value.is_empty() && value != "_").then_some(value)
+    }
+}
+
+impl FromStr for ConlluField {
+    type Err = VaporettoError;
+
+    /// Parses a field name. `lemma`, `upos`, `xpos`, and `feats` are case-insensitive, and other
+    /// names are regarded as attributes of the MISC field.
+    fn from_str(name: &str) -> Result<Self> {
+        if name.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "name",
+                "must not be empty",
+            ));
+        }
+        Ok(match name.to_ascii_lowercase().as_str() {
+            "lemma" => Self::Lemma,
+            "upos" => Self::Upos,
+            "xpos" => Self::Xpos,
+            "feats" => Self::Feats,
+            _ => Self::Misc(name.into()),
+        })
+    }
+}
+
 /// Sentence data containing boundary and tag annotations.
 pub struct Sentence<'a, 'b> {
     pub(crate) text: Cow<'a, str>,
@@ -93,7 +181,7 @@ pub struct Sentence<'a, 'b> {
     pub(crate) tags: Vec<Option<Cow<'b, str>>>,
     #[cfg(feature = "tag-prediction")]
     #[allow(clippy::type_complexity)]
-    pub(crate) tag_scores: Vec<Option<(&'b [Vec<String>], Vec<i32>)>>,
+    pub(crate) tag_scores: Vec<Option<(&'b [Vec<String>], &'b [Option<f64>], Vec<i32>)>>,
     pub(crate) n_tags: usize,
     predictor: Option<&'b Predictor>,
     str_to_char_pos: Vec<usize>,
@@ -292,9 +380,12 @@ impl<'a, 'b> Sentence<'a, 'b> {
         tags: &mut Vec<Option<Cow<'b, str>>>,
     ) -> Result<()> {
         if tokenized_text.is_empty() {
-            return Err(VaporettoError::invalid_argument(
+            return Err(VaporettoError::parse_error(
                 "tokenized_text",
                 "must contain at least one character",
+                0,
+                0,
+                None,
             ));
         }
         text.clear();
@@ -308,7 +399,9 @@ impl<'a, 'b> Sentence<'a, 'b> {
         let mut escape = false;
         let mut tags_tmp: Vec<Vec<_>> = vec![];
         let mut pos = 0;
-        for c in tokenized_text.chars() {
+        let mut n_chars = 0;
+        for (i, (byte_offset, c)) in tokenized_text.char_indices().enumerate() {
+            n_chars += 1;
             match (escape, c) {
                 // escape a following character
                 (false, '\\') => {
@@ -317,15 +410,21 @@ impl<'a, 'b> Sentence<'a, 'b> {
                 // token boundary
                 (false, ' ') => {
                     if text.is_empty() {
-                        return Err(VaporettoError::invalid_argument(
+                        return Err(VaporettoError::parse_error(
                             "tokenized_text",
                             "must not start with a whitespace",
+                            i,
+                            byte_offset,
+                            Some(c),
                         ));
                     }
                     if prev_boundary {
-                        return Err(VaporettoError::invalid_argument(
+                        return Err(VaporettoError::parse_error(
                             "tokenized_text",
                             "must not contain consecutive whitespaces",
+                            i,
+                            byte_offset,
+                            Some(c),
                         ));
                     }
                     if let Some(tag) = tag_str.take() {
@@ -336,9 +435,12 @@ impl<'a, 'b> Sentence<'a, 'b> {
                 // tag
                 (false, '/') => {
                     if text.is_empty() || prev_boundary {
-                        return Err(VaporettoError::invalid_argument(
+                        return Err(VaporettoError::parse_error(
                             "tokenized_text",
                             "a slash must follow a character",
+                            i,
+                            byte_offset,
+                            Some(c),
                         ));
                     }
                     if let Some(tag) = tag_str.replace(String::new()) {
@@ -349,9 +451,12 @@ impl<'a, 'b> Sentence<'a, 'b> {
                 (_, _) => {
                     escape = false;
                     if c == '\0' {
-                        return Err(VaporettoError::invalid_argument(
+                        return Err(VaporettoError::parse_error(
                             "tokenized_text",
                             "must not contain NULL",
+                            i,
+                            byte_offset,
+                            Some(c),
                         ));
                     }
                     if let Some(tag) = tag_str.as_mut() {
@@ -375,9 +480,13 @@ impl<'a, 'b> Sentence<'a, 'b> {
             };
         }
         if prev_boundary {
-            return Err(VaporettoError::invalid_argument(
+            // The last character is the whitespace.
+            return Err(VaporettoError::parse_error(
                 "tokenized_text",
                 "must not end with a whitespace",
+                n_chars - 1,
+                tokenized_text.len() - 1,
+                Some(' '),
             ));
         }
         str_to_char_pos.resize(pos + 1, 0);
@@ -523,9 +632,12 @@ impl<'a, 'b> Sentence<'a, 'b> {
         tags: &mut Vec<Option<Cow<'b, str>>>,
     ) -> Result<()> {
         if partial_annotation_text.is_empty() {
-            return Err(VaporettoError::invalid_argument(
+            return Err(VaporettoError::parse_error(
                 "partial_annotation_text",
                 "must contain at least one character",
+                0,
+                0,
+                None,
             ));
         }
         text.clear();
@@ -539,12 +651,17 @@ impl<'a, 'b> Sentence<'a, 'b> {
         let mut tags_tmp: Vec<Vec<_>> = vec![];
         let mut pos = 0;
         let mut is_char = true;
-        for c in partial_annotation_text.chars() {
+        let mut n_chars = 0;
+        for (i, (byte_offset, c)) in partial_annotation_text.char_indices().enumerate() {
+            n_chars += 1;
             if is_char {
                 if c == '\0' {
-                    return Err(VaporettoError::invalid_argument(
+                    return Err(VaporettoError::parse_error(
                         "partial_annotation_text",
                         "must not contain NULL",
+                        i,
+                        byte_offset,
+                        Some(c),
                     ));
                 }
                 text.push(c);
@@ -591,18 +708,25 @@ impl<'a, 'b> Sentence<'a, 'b> {
                     if let Some(tag) = tag_str.as_mut() {
                         tag.push(c);
                     } else {
-                        return Err(VaporettoError::invalid_argument(
+                        return Err(VaporettoError::parse_error(
                             "partial_annotation_text",
-                            format!("contains an invalid boundary character: '{c}'"),
+                            "contains an invalid boundary character",
+                            i,
+                            byte_offset,
+                            Some(c),
                         ));
                     }
                 }
             }
         }
         if is_char {
-            return Err(VaporettoError::invalid_argument(
+            // A character is missing after the last boundary.
+            return Err(VaporettoError::parse_error(
                 "partial_annotation_text",
                 "invalid annotation",
+                n_chars,
+                partial_annotation_text.len(),
+                None,
             ));
         }
         str_to_char_pos.resize(pos + 1, 0);
@@ -768,6 +892,252 @@ impl<'a, 'b> Sentence<'a, 'b> {
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
+    fn parse_conllu(
+        conllu_text: &str,
+        fields: &[ConlluField],
+        text: &mut String,
+        char_types: &mut Vec<u8>,
+        boundaries: &mut Vec<CharacterBoundary>,
+        str_to_char_pos: &mut Vec<usize>,
+        char_to_str_pos: &mut Vec<usize>,
+        tags: &mut Vec<Option<Cow<'b, str>>>,
+    ) -> Result<()> {
+        // Returns an error at the given byte position of the given line.
+        let error = |msg: &str, line_no: usize, line_start: usize, pos: usize, c: Option<char>| {
+            let line = &conllu_text[line_start..pos];
+            VaporettoError::parse_error_in_line(
+                "conllu_text",
+                msg,
+                line_no,
+                line.chars().count(),
+                conllu_text[..pos].chars().count(),
+                pos,
+                c,
+            )
+        };
+        // Tokens of the raw text: the surface, tags, whether a space follows the token, the line
+        // number, and the byte positions of the line and the surface.
+        let mut tokens = vec![];
+        // The last word ID covered by the preceding multiword token.
+        let mut multiword_end = 0;
+        let mut line_start = 0;
+        for (line_no, line) in conllu_text.split_inclusive('\n').enumerate() {
+            let start = line_start;
+            line_start += line.len();
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let cols: Vec<_> = line.split('\t').collect();
+            if cols.len() != 10 {
+                return Err(error(
+                    "each token line must contain 10 tab-separated fields",
+                    line_no,
+                    start,
+                    start,
+                    None,
+                ));
+            }
+            let space_after = !cols[9].split('|').any(|attr| attr == "SpaceAfter=No");
+            // Empty nodes do not appear in the raw text.
+            if cols[0].contains('.') {
+                continue;
+            }
+            let form_start = start + cols[0].len() + 1;
+            // Multiword tokens appear in the raw text instead of their syntactic words, so they
+            // are used as tokens without tags.
+            if let Some((_, end)) = cols[0].split_once('-') {
+                multiword_end = end
+                    .parse()
+                    .map_err(|_| error("contains an invalid ID", line_no, start, start, None))?;
+                tokens.push((
+                    cols[1],
+                    vec![None; fields.len()],
+                    space_after,
+                    line_no,
+                    start,
+                    form_start,
+                ));
+                continue;
+            }
+            let id: usize = cols[0]
+                .parse()
+                .map_err(|_| error("contains an invalid ID", line_no, start, start, None))?;
+            if id <= multiword_end {
+                continue;
+            }
+            let token_tags = fields.iter().map(|field| field.value(&cols)).collect();
+            tokens.push((cols[1], token_tags, space_after, line_no, start, form_start));
+        }
+        if tokens.is_empty() {
+            return Err(error("must contain at least one token", 0, 0, 0, None));
+        }
+        text.clear();
+        char_types.clear();
+        boundaries.clear();
+        str_to_char_pos.clear();
+        char_to_str_pos.clear();
+        char_to_str_pos.push(0);
+        tags.clear();
+        let n_tokens = tokens.len();
+        let mut pos = 0;
+        for (i, (surface, token_tags, space_after, line_no, start, form_start)) in
+            tokens.into_iter().enumerate()
+        {
+            if surface.is_empty() {
+                return Err(error(
+                    "FORM must not be empty",
+                    line_no,
+                    start,
+                    form_start,
+                    None,
+                ));
+            }
+            if let Some(j) = surface.find('\0') {
+                return Err(error(
+                    "must not contain NULL",
+                    line_no,
+                    start,
+                    form_start + j,
+                    Some('\0'),
+                ));
+            }
+            // A space between tokens is inserted as a token without tags.
+            let space = (space_after && i != n_tokens - 1).then_some((" ", vec![]));
+            for (surface, token_tags) in core::iter::once((surface, token_tags)).chain(space) {
+                for (j, c) in surface.chars().enumerate() {
+                    if !text.is_empty() {
+                        boundaries.push(if j == 0 {
+                            CharacterBoundary::WordBoundary
+                        } else {
+                            CharacterBoundary::NotWordBoundary
+                        });
+                    }
+                    text.push(c);
+                    char_types.push(CharacterType::get_type(c) as u8);
+                    pos += c.len_utf8();
+                    char_to_str_pos.push(pos);
+                    tags.resize(tags.len() + fields.len(), None);
+                }
+                let token_tags = token_tags
+                    .into_iter()
+                    .map(|t| t.map(|t| Cow::Owned(t.into())));
+                let start = tags.len() - fields.len();
+                for (dst, tag) in tags[start..].iter_mut().zip(token_tags) {
+                    *dst = tag;
+                }
+            }
+        }
+        str_to_char_pos.resize(pos + 1, 0);
+        for (i, &pos) in char_to_str_pos.iter().enumerate() {
+            str_to_char_pos[pos] = i;
+        }
+        Ok(())
+    }
+
+    /// Creates a new [`Sentence`] from a sentence in the CoNLL-U format.
+    ///
+    /// Each token line of the given text is parsed by the following rules:
+    ///   - FORM is used as the surface of each token.
+    ///   - The fields in `fields` are stored in the tag columns in the same order.
+    ///     Underscores (`_`) are regarded as missing tags.
+    ///   - A whitespace token is inserted after each token without `SpaceAfter=No` in MISC,
+    ///     except for the last token.
+    ///   - Multiword tokens are used as tokens without tags instead of their syntactic words,
+    ///     and empty nodes and comments are ignored.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error variant when the text does not contain any token, or
+    /// a token line is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{ConlluField, Sentence};
+    ///
+    /// let conllu = "\
+    /// ## text = 火星猫 だ
+    /// 1\t火星\t火星\tNOUN\t名詞\t_\t_\t_\t_\tSpaceAfter=No
+    /// 2\t猫\t猫\tNOUN\t名詞\t_\t_\t_\t_\t_
+    /// 3\tだ\tだ\tAUX\t助動詞\t_\t_\t_\t_\tSpaceAfter=No
+    /// ";
+    /// let s = Sentence::from_conllu(conllu, &[ConlluField::Upos, ConlluField::Lemma]).unwrap();
+    /// assert_eq!("火星猫 だ", s.as_raw_text());
+    ///
+    /// let mut buf = String::new();
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("火星/NOUN/火星 猫/NOUN/猫 \\  だ/AUX/だ", buf);
+    /// ```
+    pub fn from_conllu(conllu_text: &str, fields: &[ConlluField]) -> Result<Self> {
+        let mut text = String::new();
+        let mut char_types = vec![];
+        let mut boundaries = vec![];
+        let mut str_to_char_pos = vec![];
+        let mut char_to_str_pos = vec![];
+        let mut tags = vec![];
+        Self::parse_conllu(
+            conllu_text,
+            fields,
+            &mut text,
+            &mut char_types,
+            &mut boundaries,
+            &mut str_to_char_pos,
+            &mut char_to_str_pos,
+            &mut tags,
+        )?;
+        Ok(Self {
+            text: Cow::Owned(text),
+            char_types,
+            boundaries,
+            boundary_scores: vec![],
+            score_padding: 0,
+            char_pma_states: vec![],
+            type_pma_states: vec![],
+            predictor: None,
+            tags,
+            #[cfg(feature = "tag-prediction")]
+            tag_scores: vec![],
+            n_tags: fields.len(),
+            str_to_char_pos,
+            char_to_str_pos,
+        })
+    }
+
+    /// Updates the [`Sentence`] using a sentence in the CoNLL-U format.
+    ///
+    /// See [`Sentence::from_conllu()`] for the rules of parsing.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error variant when the text does not contain any token, or
+    /// a token line is invalid.
+    /// When an error is occurred, the sentence will be replaced with a white space.
+    pub fn update_conllu(&mut self, conllu_text: &str, fields: &[ConlluField]) -> Result<()> {
+        if let Err(e) = Self::parse_conllu(
+            conllu_text,
+            fields,
+            self.text.to_mut(),
+            &mut self.char_types,
+            &mut self.boundaries,
+            &mut self.str_to_char_pos,
+            &mut self.char_to_str_pos,
+            &mut self.tags,
+        ) {
+            self.set_default();
+            return Err(e);
+        }
+        self.boundary_scores.clear();
+        self.score_padding = 0;
+        self.char_pma_states.clear();
+        self.type_pma_states.clear();
+        self.predictor.take();
+        self.n_tags = fields.len();
+        Ok(())
+    }
+
     /// Gets a text without any annotation.
     ///
     /// # Examples
@@ -943,6 +1313,137 @@ impl<'a, 'b> Sentence<'a, 'b> {
         }
     }
 
+    /// Writes a sentence in the CoNLL-U format, which consists of the `# text` comment and token
+    /// lines, each of which ends with a line feed.
+    ///
+    /// The tag columns are written to the fields in `fields` in the same order. Whitespace tokens
+    /// are not written and are represented by the absence of `SpaceAfter=No` in MISC instead.
+    /// Tokens adjacent to [`CharacterBoundary::Unknown`] will be skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::{ConlluField, Sentence};
+    ///
+    /// let s = Sentence::from_tokenized("火星/NOUN/カセー 猫/NOUN \\  だ").unwrap();
+    /// let mut buf = String::new();
+    /// s.write_conllu(
+    ///     &mut buf,
+    ///     &[ConlluField::Upos, ConlluField::Misc("Reading".into())],
+    /// );
+    /// assert_eq!("\
+    /// ## text = 火星猫 だ
+    /// 1\t火星\t_\tNOUN\t_\t_\t_\t_\t_\tReading=カセー|SpaceAfter=No
+    /// 2\t猫\t_\tNOUN\t_\t_\t_\t_\t_\t_
+    /// 3\tだ\t_\t_\t_\t_\t_\t_\t_\t_
+    /// ", buf);
+    /// ```
+    pub fn write_conllu(&self, buf: &mut String, fields: &[ConlluField]) {
+        buf.clear();
+        // Tokens to write and whether a space follows each of them.
+        let mut tokens = vec![];
+        for token in self.iter_tokens() {
+            if token.surface().chars().all(char::is_whitespace) {
+                if let Some((_, space_after)) = tokens.last_mut() {
+                    *space_after = true;
+                }
+            } else {
+                tokens.push((token, false));
+            }
+        }
+        buf.push_str("# text = ");
+        for (token, space_after) in &tokens {
+            buf.push_str(token.surface());
+            if *space_after {
+                buf.push(' ');
+            }
+        }
+        // The text must not end with a whitespace.
+        buf.truncate(buf.trim_end().len());
+        buf.push('\n');
+        let n_tokens = tokens.len();
+        for (i, (token, space_after)) in tokens.into_iter().enumerate() {
+            let mut cols = ["_"; 10];
+            let mut misc = vec![];
+            for (field, tag) in fields.iter().zip(token.tags()) {
+                let Some(tag) = tag.as_deref() else {
+                    continue;
+                };
+                match field {
+                    ConlluField::Lemma => cols[2] = tag,
+                    ConlluField::Upos => cols[3] = tag,
+                    ConlluField::Xpos => cols[4] = tag,
+                    ConlluField::Feats => cols[5] = tag,
+                    ConlluField::Misc(key) => misc.push(format!("{key}={tag}")),
+                }
+            }
+            if !space_after && i != n_tokens - 1 {
+                misc.push("SpaceAfter=No".into());
+            }
+            let misc = misc.join("|");
+            if !misc.is_empty() {
+                cols[9] = &misc;
+            }
+            buf.push_str(&format!("{}", i + 1));
+            buf.push('\t');
+            buf.push_str(token.surface());
+            for col in &cols[2..] {
+                buf.push('\t');
+                buf.push_str(col);
+            }
+            buf.push('\n');
+        }
+    }
+
+    /// Writes a sentence in the MeCab format, which consists of token lines and `EOS`, each of
+    /// which ends with a line feed.
+    ///
+    /// Each token line consists of a surface and comma-separated tags separated by a tab. Missing
+    /// tags are written as `*`, and tags containing commas or double quotes are quoted following
+    /// the CSV format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let s = Sentence::from_tokenized("火星/名詞/カセー 猫 ,/補助記号/,").unwrap();
+    /// let mut buf = String::new();
+    /// s.write_mecab(&mut buf);
+    /// assert_eq!("\
+    /// 火星\t名詞,カセー
+    /// 猫\t*,*
+    /// ,\t補助記号,\",\"
+    /// EOS
+    /// ", buf);
+    /// ```
+    pub fn write_mecab(&self, buf: &mut String) {
+        buf.clear();
+        for token in self.iter_tokens() {
+            buf.push_str(token.surface());
+            buf.push('\t');
+            if token.tags().is_empty() {
+                buf.push('*');
+            }
+            for (i, tag) in token.tags().iter().enumerate() {
+                if i != 0 {
+                    buf.push(',');
+                }
+                match tag.as_deref() {
+                    None => buf.push('*'),
+                    Some(tag) if tag.contains([',', '"']) => {
+                        buf.push('"');
+                        buf.push_str(&tag.replace('"', "\"\""));
+                        buf.push('"');
+                    }
+                    Some(tag) => buf.push_str(tag),
+                }
+            }
+            buf.push('\n');
+        }
+        buf.push_str("EOS\n");
+    }
+
     /// Removes tag information and updates the number of tags.
     ///
     /// # Examples
@@ -970,6 +1471,37 @@ impl<'a, 'b> Sentence<'a, 'b> {
         self.n_tags = n_tags;
     }
 
+    /// Keeps only the given tag columns in the given order and updates the number of tags.
+    ///
+    /// Columns out of range are filled with `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use vaporetto::Sentence;
+    ///
+    /// let mut s = Sentence::from_tokenized("火星/名詞/カセー に 行き/動詞 まし/助動詞/マシ た").unwrap();
+    /// let mut buf = String::new();
+    ///
+    /// s.select_tag_columns(&[1, 0, 2]);
+    /// assert_eq!(3, s.n_tags());
+    /// s.write_tokenized_text(&mut buf);
+    /// assert_eq!("火星/カセー/名詞 に 行き//動詞 まし/マシ/助動詞 た", buf);
+    /// ```
+    pub fn select_tag_columns(&mut self, columns: &[usize]) {
+        let n_tags = self.n_tags;
+        let tags = core::mem::take(&mut self.tags);
+        self.reset_tags(columns.len());
+        if n_tags == 0 || columns.is_empty() {
+            return;
+        }
+        for (dst, src) in self.tags.chunks_mut(columns.len()).zip(tags.chunks(n_tags)) {
+            for (d, &c) in dst.iter_mut().zip(columns) {
+                d.clone_from(&src.get(c).cloned().flatten());
+            }
+        }
+    }
+
     /// Returns a slice of character types.
     ///
     /// # Examples
@@ -1191,6 +1723,39 @@ assert_eq!(
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Sentence<'_, '_> {
+    /// Serializes the raw text, boundaries, boundary scores, tags, and tokens of this sentence.
+    /// Boundary scores are empty unless the sentence is predicted, and tags are stored in the
+    /// same layout as [`Sentence::tags()`].
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        struct Tokens<'a, 'b>(&'a Sentence<'a, 'b>);
+
+        impl serde::Serialize for Tokens<'_, '_> {
+            fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                serializer.collect_seq(self.0.iter_tokens())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("Sentence", 6)?;
+        state.serialize_field("text", self.as_raw_text())?;
+        state.serialize_field("boundaries", self.boundaries())?;
+        state.serialize_field("boundary_scores", self.boundary_scores())?;
+        state.serialize_field("n_tags", &self.n_tags)?;
+        state.serialize_field("tags", self.tags())?;
+        state.serialize_field("tokens", &Tokens(self))?;
+        state.end()
+    }
+}
+
 /// A Token information.
 #[derive(Clone, Copy)]
 pub struct Token<'a, 'b> {
@@ -1214,39 +1779,270 @@ impl<'a, 'b> Token<'a, 'b> {
         &self.sentence.tags[start..end]
     }
 
+    /// Returns the tag of the column with the given name.
+    ///
+    /// Column names are given by the schema of the model (see [`Model::tag_schema()`]).
+    /// Returns `None` if the sentence was not predicted by a [`Predictor`], the model does not
+    /// have such a column, or the tag is not assigned.
+    ///
+    /// [`Model::tag_schema()`]: crate::Model::tag_schema
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn tag_by_name(&self, name: &str) -> Option<&'a str> {
+        let column = self.sentence.predictor?.tag_column_index(name)?;
+        self.tags().get(column)?.as_deref()
+    }
+
+    /// Returns the lemma of this token.
+    ///
+    /// The lemma is the tag of the first [`TagColumnKind::Lemma`] column in the schema of the
+    /// model. Returns `None` if the sentence was not predicted by a [`Predictor`], the model does
+    /// not have a lemma column, or the lemma is not predicted.
+    ///
+    /// [`TagColumnKind::Lemma`]: crate::TagColumnKind::Lemma
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn lemma(&self) -> Option<&'a str> {
+        let column = self.sentence.predictor?.lemma_column()?;
+        self.tags().get(column)?.as_deref()
+    }
+
+    /// Returns decoded tag candidates of each column with their scores, and the score scale of the
+    /// column if known.
+    ///
+    /// Candidates whose edit scripts cannot be applied to the surface are removed.
+    #[cfg(feature = "tag-prediction")]
+    #[allow(clippy::type_complexity)]
+    fn decoded_candidates_with_scales(
+        &self,
+    ) -> Option<Vec<(Vec<(Cow<'b, str>, i32)>, Option<f64>)>> {
+        let (tags, score_scales, scores) = self.sentence.tag_scores.get(self.end - 1)?.as_ref()?;
+        let surface = self.surface();
+        let decode = |column, cand: &'b String| match self.sentence.predictor {
+            Some(predictor) => predictor.decode_tag_candidate(column, surface, cand),
+            None => Some(Cow::Borrowed(cand.as_str())),
+        };
+        let mut results = vec![];
+        let mut i = 0;
+        for (column, cands) in tags.iter().enumerate() {
+            let scale = score_scales.get(column).copied().flatten();
+            let mut inner = vec![];
+            if cands.len() == 1 {
+                inner.extend(decode(column, &cands[0]).map(|tag| (tag, 0)));
+            } else {
+                for cand in cands.iter() {
+                    inner.extend(decode(column, cand).map(|tag| (tag, scores[i])));
+                    i += 1;
+                }
+            }
+            results.push((inner, scale));
+        }
+        Some(results)
+    }
+
     /// Returns tag candidates with scores.
     ///
     /// The return value is a two-dimensional array. The outer array index corresponding to the
     /// return value of [`Token::tags()`]. The inner array is a candidate set, where each element
     /// is a tuple of the tag name and its score.
     ///
+    /// Candidates of [`TagColumnKind::Lemma`] columns are edit scripts stored in the model. Use
+    /// [`Token::decoded_tag_candidates()`] to obtain them as lemmas.
+    ///
     /// # Panics
     ///
     /// This function panics if [`Predictor::store_tag_scores()`] is set to false.
+    ///
+    /// [`TagColumnKind::Lemma`]: crate::TagColumnKind::Lemma
     #[cfg(feature = "tag-prediction")]
     #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    #[deprecated(
+        since = "0.7.0",
+        note = "use `Token::raw_tag_candidates()` or `Token::decoded_tag_candidates()` instead"
+    )]
     pub fn tag_candidates(&self) -> Vec<Vec<(&'b str, i32)>> {
-        let mut results = vec![];
         assert!(
             !self.sentence.tag_scores.is_empty(),
             "Predictor::store_tag_scores() must be set to true to use this function.",
         );
-        if let Some((tags, scores)) = self.sentence.tag_scores[self.end - 1].as_ref() {
-            let mut i = 0;
-            for cands in *tags {
-                let mut inner = vec![];
-                if cands.len() == 1 {
-                    inner.push((cands[0].as_str(), 0));
-                } else {
-                    for cand in cands {
-                        inner.push((cand.as_str(), scores[i]));
-                        i += 1;
-                    }
+        self.raw_tag_candidates().unwrap_or_default()
+    }
+
+    /// Returns tag candidates with scores.
+    ///
+    /// The return value has the same structure as the deprecated [`Token::tag_candidates()`], and
+    /// candidates of [`TagColumnKind::Lemma`] columns are edit scripts stored in the model.
+    /// Returns `None` if [`Predictor::store_tag_scores()`] is set to false or tags of this token
+    /// are not predicted by the model.
+    ///
+    /// [`TagColumnKind::Lemma`]: crate::TagColumnKind::Lemma
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    pub fn raw_tag_candidates(&self) -> Option<Vec<Vec<(&'b str, i32)>>> {
+        let (tags, _, scores) = self.sentence.tag_scores.get(self.end - 1)?.as_ref()?;
+        let mut results = vec![];
+        let mut i = 0;
+        for cands in *tags {
+            let mut inner = vec![];
+            if cands.len() == 1 {
+                inner.push((cands[0].as_str(), 0));
+            } else {
+                for cand in cands {
+                    inner.push((cand.as_str(), scores[i]));
+                    i += 1;
                 }
-                results.push(inner);
             }
+            results.push(inner);
+        }
+        Some(results)
+    }
+
+    /// Returns tag candidates with scores, where candidates of [`TagColumnKind::Lemma`] columns
+    /// are decoded into lemmas in the same way as [`Token::tags()`].
+    ///
+    /// The return value has the same structure as [`Token::raw_tag_candidates()`]. Candidates
+    /// whose edit scripts cannot be applied to the surface are removed. Returns `None` if
+    /// [`Predictor::store_tag_scores()`] is set to false or tags of this token are not predicted
+    /// by the model.
+    ///
+    /// [`TagColumnKind::Lemma`]: crate::TagColumnKind::Lemma
+    #[cfg(feature = "tag-prediction")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tag-prediction")))]
+    #[allow(clippy::type_complexity)]
+    pub fn decoded_tag_candidates(&self) -> Option<Vec<Vec<(Cow<'b, str>, i32)>>> {
+        Some(
+            self.decoded_candidates_with_scales()?
+                .into_iter()
+                .map(|(inner, _)| inner)
+                .collect(),
+        )
+    }
+
+    /// Returns tag candidates with their probabilities.
+    ///
+    /// The return value has the same structure as [`Token::decoded_tag_candidates()`], but the
+    /// scores of each tag column are normalized into probabilities using the softmax function.
+    /// The probability of a tag without other candidates is always 1.
+    ///
+    /// Returns `None` if [`Predictor::store_tag_scores()`] is set to false, tags of this token
+    /// are not predicted by the model, or the model does not know the scales of the scores of a
+    /// column with multiple candidates. Scores of models exported by Vaporetto 0.6 or earlier and
+    /// those converted from KyTea have no scales, so the probabilities are not available for them.
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "tag-prediction"))))]
+    #[allow(clippy::type_complexity)]
+    pub fn tag_probabilities(&self) -> Option<Vec<Vec<(Cow<'b, str>, f64)>>> {
+        let mut results = vec![];
+        for (cands, scale) in self.decoded_candidates_with_scales()? {
+            if cands.len() <= 1 {
+                results.push(cands.into_iter().map(|(cand, _)| (cand, 1.)).collect());
+                continue;
+            }
+            // Softmax over quantized scores without the scale is meaningless.
+            let scale = scale?;
+            let max_score = f64::from(cands.iter().map(|&(_, s)| s).max().unwrap_or(0));
+            let exps: Vec<_> = cands
+                .iter()
+                .map(|&(_, s)| ((f64::from(s) - max_score) * scale).exp())
+                .collect();
+            let sum: f64 = exps.iter().sum();
+            results.push(
+                cands
+                    .into_iter()
+                    .zip(exps)
+                    .map(|((cand, _), e)| (cand, e / sum))
+                    .collect(),
+            );
         }
-        results
+        Some(results)
+    }
+
+    /// Returns the probability of the most likely tag in the given column.
+    ///
+    /// Returns `None` if [`Token::tag_probabilities()`] returns `None` or the column has no
+    /// candidates.
+    #[cfg(all(feature = "std", feature = "tag-prediction"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "std", feature = "tag-prediction"))))]
+    pub fn tag_confidence(&self, column: usize) -> Option<f64> {
+        self.tag_probabilities()?
+            .get(column)?
+            .iter()
+            .map(|&(_, p)| p)
+            .reduce(f64::max)
+    }
+
+    /// Returns the start position of this token in characters.
+    #[inline]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the end position of this token in characters.
+    #[inline]
+    pub const fn end(&self) -> usize {
+        self.end
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Token<'_, '_> {
+    /// Serializes this token in the same structure as [`OwnedToken`].
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Token", 4)?;
+        state.serialize_field("surface", self.surface())?;
+        state.serialize_field("start", &self.start)?;
+        state.serialize_field("end", &self.end)?;
+        state.serialize_field("tags", self.tags())?;
+        state.end()
+    }
+}
+
+/// A token detached from its [`Sentence`].
+///
+/// The surface is a range of the text shared by tokens of the same [`AnalyzedSentence`], and
+/// tags are reference-counted strings, so cloning this token is cheap.
+///
+/// # Examples
+///
+/// ```
+/// use vaporetto::{OwnedToken, Sentence};
+///
+/// let tokens: Vec<OwnedToken> = {
+///     let s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+///     s.iter_tokens().map(OwnedToken::from).collect()
+/// };
+/// assert_eq!("火星", tokens[0].surface());
+/// assert_eq!(2, tokens[1].start());
+/// assert_eq!(Some("名詞"), tokens[0].tags()[0].as_deref());
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "OwnedTokenData"))]
+pub struct OwnedToken {
+    text: Arc<str>,
+    // The range of the surface in `text` in bytes.
+    byte_range: Range<usize>,
+    start: usize,
+    end: usize,
+    tags: Vec<Option<Arc<str>>>,
+}
+
+impl OwnedToken {
+    /// Returns the surface of this token.
+    #[inline]
+    pub fn surface(&self) -> &str {
+        &self.text[self.byte_range.clone()]
+    }
+
+    /// Returns tags of this token.
+    #[inline]
+    pub fn tags(&self) -> &[Option<Arc<str>>] {
+        &self.tags
     }
 
     /// Returns the start position of this token in characters.
@@ -1262,6 +2058,212 @@ impl<'a, 'b> Token<'a, 'b> {
     }
 }
 
+impl PartialEq for OwnedToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.surface() == other.surface()
+            && self.start == other.start
+            && self.end == other.end
+            && self.tags == other.tags
+    }
+}
+
+impl Eq for OwnedToken {}
+
+impl From<Token<'_, '_>> for OwnedToken {
+    fn from(token: Token<'_, '_>) -> Self {
+        let surface = token.surface();
+        Self {
+            text: Arc::from(surface),
+            byte_range: 0..surface.len(),
+            start: token.start(),
+            end: token.end(),
+            tags: token
+                .tags()
+                .iter()
+                .map(|tag| tag.as_deref().map(Arc::from))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for OwnedToken {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let tags: Vec<_> = self.tags.iter().map(|tag| tag.as_deref()).collect();
+        let mut state = serializer.serialize_struct("OwnedToken", 4)?;
+        state.serialize_field("surface", self.surface())?;
+        state.serialize_field("start", &self.start)?;
+        state.serialize_field("end", &self.end)?;
+        state.serialize_field("tags", &tags)?;
+        state.end()
+    }
+}
+
+/// Serialized form of [`OwnedToken`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct OwnedTokenData {
+    surface: String,
+    start: usize,
+    end: usize,
+    tags: Vec<Option<String>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<OwnedTokenData> for OwnedToken {
+    fn from(data: OwnedTokenData) -> Self {
+        Self {
+            byte_range: 0..data.surface.len(),
+            text: Arc::from(data.surface),
+            start: data.start,
+            end: data.end,
+            tags: data
+                .tags
+                .into_iter()
+                .map(|tag| tag.map(Arc::from))
+                .collect(),
+        }
+    }
+}
+
+/// An analysis result detached from its [`Sentence`] and [`Predictor`].
+///
+/// Unlike [`Sentence`], this type does not borrow anything, so it can be stored or sent to other
+/// threads. The text, tokens, and scores are reference-counted, so cloning is cheap.
+#[cfg_attr(
+    feature = "std",
+    doc = "
+# Examples
+
+```
+use std::fs::File;
+use std::thread;
+
+use vaporetto::{AnalyzedSentence, Model, Predictor, Sentence};
+
+let f = File::open(\"../resources/model.bin\").unwrap();
+let model = Model::read(f).unwrap();
+let predictor = Predictor::new(model, false).unwrap();
+
+let mut s = Sentence::from_raw(\"まぁ社長は火星猫だ\").unwrap();
+predictor.predict(&mut s);
+let result = AnalyzedSentence::from(&s);
+
+let handle = thread::spawn(move || {
+    result.tokens().iter().map(|t| t.surface().to_string()).collect::<Vec<_>>()
+});
+assert_eq!(
+    vec![\"まぁ\", \"社長\", \"は\", \"火星\", \"猫\", \"だ\"],
+    handle.join().unwrap(),
+);
+```
+"
+)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "AnalyzedSentenceData"))]
+pub struct AnalyzedSentence {
+    text: Arc<str>,
+    tokens: Arc<[OwnedToken]>,
+    boundary_scores: Option<Arc<[i32]>>,
+}
+
+impl AnalyzedSentence {
+    /// Returns the text without any annotation.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the tokens. Tokens adjacent to [`CharacterBoundary::Unknown`] are not contained.
+    #[inline]
+    pub fn tokens(&self) -> &[OwnedToken] {
+        &self.tokens
+    }
+
+    /// Returns the boundary scores, or `None` if the sentence was not predicted.
+    #[inline]
+    pub fn boundary_scores(&self) -> Option<&[i32]> {
+        self.boundary_scores.as_deref()
+    }
+}
+
+impl From<&Sentence<'_, '_>> for AnalyzedSentence {
+    fn from(s: &Sentence<'_, '_>) -> Self {
+        let text = Arc::<str>::from(s.as_raw_text());
+        // Tags are shared among tokens because most of them are repeated.
+        let mut tag_strs: HashMap<&str, Arc<str>> = HashMap::new();
+        let tokens = s
+            .iter_tokens()
+            .map(|token| OwnedToken {
+                text: text.clone(),
+                byte_range: s.char_to_str_pos[token.start()]..s.char_to_str_pos[token.end()],
+                start: token.start(),
+                end: token.end(),
+                tags: token
+                    .tags()
+                    .iter()
+                    .map(|tag| {
+                        tag.as_deref().map(|tag| {
+                            tag_strs
+                                .entry(tag)
+                                .or_insert_with(|| Arc::from(tag))
+                                .clone()
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+        let boundary_scores = (!s.boundary_scores().is_empty()).then(|| s.boundary_scores().into());
+        Self {
+            text,
+            tokens,
+            boundary_scores,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for AnalyzedSentence {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("AnalyzedSentence", 3)?;
+        state.serialize_field("text", self.text())?;
+        state.serialize_field("tokens", self.tokens())?;
+        state.serialize_field("boundary_scores", &self.boundary_scores())?;
+        state.end()
+    }
+}
+
+/// Serialized form of [`AnalyzedSentence`].
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct AnalyzedSentenceData {
+    text: String,
+    tokens: Vec<OwnedToken>,
+    boundary_scores: Option<Vec<i32>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<AnalyzedSentenceData> for AnalyzedSentence {
+    fn from(data: AnalyzedSentenceData) -> Self {
+        Self {
+            text: data.text.into(),
+            tokens: data.tokens.into(),
+            boundary_scores: data.boundary_scores.map(Arc::from),
+        }
+    }
+}
+
 /// Iterator returned by [`Sentence::iter_tokens()`].
 pub struct TokenIterator<'a, 'b> {
     token: Token<'a, 'b>,
@@ -1481,7 +2483,7 @@ mod tests {
         let s = Sentence::from_tokenized("");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must contain at least one character",
+            "ParseError: tokenized_text: must contain at least one character (at char 0, byte 0)",
             &s.err().unwrap().to_string()
         );
     }
@@ -1492,7 +2494,7 @@ mod tests {
         let result = s.update_tokenized("");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must contain at least one character",
+            "ParseError: tokenized_text: must contain at least one character (at char 0, byte 0)",
             &result.err().unwrap().to_string()
         );
 
@@ -1509,7 +2511,7 @@ mod tests {
         let s = Sentence::from_tokenized("A1あ\0ア亜");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not contain NULL",
+            "ParseError: tokenized_text: must not contain NULL (at char 3, byte 5, found '\\0')",
             &s.err().unwrap().to_string()
         );
     }
@@ -1520,7 +2522,7 @@ mod tests {
         let result = s.update_tokenized("A1あ\0ア亜");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not contain NULL",
+            "ParseError: tokenized_text: must not contain NULL (at char 3, byte 5, found '\\0')",
             &result.err().unwrap().to_string()
         );
 
@@ -1537,7 +2539,7 @@ mod tests {
         let s = Sentence::from_tokenized(" Rust で 良い プログラミング 体験 を ！");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not start with a whitespace",
+            "ParseError: tokenized_text: must not start with a whitespace (at char 0, byte 0, found ' ')",
             &s.err().unwrap().to_string()
         );
     }
@@ -1548,7 +2550,7 @@ mod tests {
         let result = s.update_tokenized(" Rust で 良い プログラミング 体験 を ！");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not start with a whitespace",
+            "ParseError: tokenized_text: must not start with a whitespace (at char 0, byte 0, found ' ')",
             &result.err().unwrap().to_string()
         );
 
@@ -1565,7 +2567,7 @@ mod tests {
         let s = Sentence::from_tokenized("Rust で 良い プログラミング 体験 を ！ ");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not end with a whitespace",
+            "ParseError: tokenized_text: must not end with a whitespace (at char 24, byte 52, found ' ')",
             &s.err().unwrap().to_string()
         );
     }
@@ -1576,7 +2578,7 @@ mod tests {
         let result = s.update_tokenized("Rust で 良い プログラミング 体験 を ！ ");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not end with a whitespace",
+            "ParseError: tokenized_text: must not end with a whitespace (at char 24, byte 52, found ' ')",
             &result.err().unwrap().to_string()
         );
 
@@ -1593,7 +2595,7 @@ mod tests {
         let s = Sentence::from_tokenized("Rust で 良い  プログラミング 体験 を ！");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not contain consecutive whitespaces",
+            "ParseError: tokenized_text: must not contain consecutive whitespaces (at char 10, byte 16, found ' ')",
             &s.err().unwrap().to_string()
         );
     }
@@ -1604,7 +2606,7 @@ mod tests {
         let result = s.update_tokenized("Rust で 良い  プログラミング 体験 を ！");
 
         assert_eq!(
-            "InvalidArgumentError: tokenized_text: must not contain consecutive whitespaces",
+            "ParseError: tokenized_text: must not contain consecutive whitespaces (at char 10, byte 16, found ' ')",
             &result.err().unwrap().to_string()
         );
 
@@ -2786,7 +3788,7 @@ mod tests {
         let s = Sentence::from_partial_annotation("");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: must contain at least one character",
+            "ParseError: partial_annotation_text: must contain at least one character (at char 0, byte 0)",
             &s.err().unwrap().to_string()
         );
     }
@@ -2797,7 +3799,7 @@ mod tests {
         let result = s.update_partial_annotation("");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: must contain at least one character",
+            "ParseError: partial_annotation_text: must contain at least one character (at char 0, byte 0)",
             &result.err().unwrap().to_string()
         );
 
@@ -2809,12 +3811,28 @@ mod tests {
         assert!(s.boundary_scores().is_empty());
     }
 
+    #[test]
+    fn test_sentence_from_tokenized_parse_error() {
+        let s = Sentence::from_tokenized("火星 猫 /の 生態");
+
+        match s.err().unwrap() {
+            VaporettoError::Parse(e) => {
+                assert_eq!("tokenized_text", e.arg());
+                assert_eq!("a slash must follow a character", e.msg());
+                assert_eq!(5, e.char_offset());
+                assert_eq!(11, e.byte_offset());
+                assert_eq!(Some('/'), e.character());
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
     #[test]
     fn test_sentence_from_partial_annotation_null() {
         let s = Sentence::from_partial_annotation("A-1-あ-\0-ア-亜");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: must not contain NULL",
+            "ParseError: partial_annotation_text: must not contain NULL (at char 6, byte 8, found '\\0')",
             &s.err().unwrap().to_string()
         );
     }
@@ -2825,7 +3843,7 @@ mod tests {
         let result = s.update_partial_annotation("A-1-あ-\0-ア-亜");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: must not contain NULL",
+            "ParseError: partial_annotation_text: must not contain NULL (at char 6, byte 8, found '\\0')",
             &result.err().unwrap().to_string()
         );
     }
@@ -2835,7 +3853,7 @@ mod tests {
         let result = Sentence::from_partial_annotation("火-星 猫|の|生-態 ");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: invalid annotation",
+            "ParseError: partial_annotation_text: invalid annotation (at char 12, byte 24)",
             &result.err().unwrap().to_string()
         );
     }
@@ -2846,7 +3864,7 @@ mod tests {
         let result = s.update_partial_annotation("火-星 猫|の|生-態 ");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: invalid annotation",
+            "ParseError: partial_annotation_text: invalid annotation (at char 12, byte 24)",
             &result.err().unwrap().to_string()
         );
     }
@@ -2856,7 +3874,7 @@ mod tests {
         let s = Sentence::from_partial_annotation("火-星?猫|の|生-態");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: contains an invalid boundary character: '?'",
+            "ParseError: partial_annotation_text: contains an invalid boundary character (at char 3, byte 7, found '?')",
             &s.err().unwrap().to_string()
         );
     }
@@ -2867,7 +3885,7 @@ mod tests {
         let result = s.update_partial_annotation("火-星?猫|の|生-態");
 
         assert_eq!(
-            "InvalidArgumentError: partial_annotation_text: contains an invalid boundary character: '?'",
+            "ParseError: partial_annotation_text: contains an invalid boundary character (at char 3, byte 7, found '?')",
             &result.err().unwrap().to_string()
         );
     }
@@ -2940,4 +3958,247 @@ mod tests {
         );
         assert!(s.boundary_scores().is_empty());
     }
+
+    #[test]
+    fn test_sentence_from_conllu() {
+        let conllu = "\
+# sent_id = test
+# text = 火星猫の生態
+1\t火星\t火星\tNOUN\t名詞-普通名詞\t_\t2\tcompound\t_\tSpaceAfter=No|Reading=カセー
+2\t猫\t猫\tNOUN\t名詞-普通名詞\t_\t4\tnmod\t_\tSpaceAfter=No
+3\tの\tの\tADP\t助詞-格助詞\t_\t2\tcase\t_\tSpaceAfter=No|Reading=ノ
+4\t生態\t生態\tNOUN\t名詞-普通名詞\t_\t0\troot\t_\tSpaceAfter=No
+";
+        let fields = [
+            ConlluField::Upos,
+            ConlluField::Misc("Reading".into()),
+            ConlluField::Lemma,
+        ];
+        let s = Sentence::from_conllu(conllu, &fields).unwrap();
+
+        assert_eq!("火星猫の生態", s.as_raw_text());
+        assert_eq!(
+            &[0, 0, 0, 1, 0, 0, 2, 0, 0, 3, 0, 0, 4, 0, 0, 5, 0, 0, 6],
+            s.str_to_char_pos.as_slice(),
+        );
+        assert_eq!([0, 3, 6, 9, 12, 15, 18], s.char_to_str_pos());
+        assert_eq!(
+            [
+                NotWordBoundary,
+                WordBoundary,
+                WordBoundary,
+                WordBoundary,
+                NotWordBoundary,
+            ],
+            s.boundaries()
+        );
+        assert_eq!(3, s.n_tags());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!(
+            "火星/NOUN/カセー/火星 猫/NOUN//猫 の/ADP/ノ/の 生態/NOUN//生態",
+            buf
+        );
+    }
+
+    #[test]
+    fn test_sentence_from_conllu_multiword_tokens() {
+        let conllu = "\
+1-2\tvámonos\t_\t_\t_\t_\t_\t_\t_\t_
+1\tvamos\tir\tVERB\t_\t_\t0\troot\t_\t_
+2\tnos\tnosotros\tPRON\t_\t_\t1\tobj\t_\t_
+2.1\tya\tya\tADV\t_\t_\t_\t_\t_\t_
+3\tal\tal\tADP\t_\t_\t1\tobl\t_\tSpaceAfter=No
+4\t!\t!\tPUNCT\t_\t_\t1\tpunct\t_\t_
+";
+        let s = Sentence::from_conllu(conllu, &[ConlluField::Upos]).unwrap();
+
+        assert_eq!("vámonos al!", s.as_raw_text());
+        let mut buf = String::new();
+        s.write_tokenized_text(&mut buf);
+        assert_eq!("vámonos \\  al/ADP !/PUNCT", buf);
+    }
+
+    #[test]
+    fn test_sentence_from_conllu_invalid() {
+        let s = Sentence::from_conllu("# text = 猫\n", &[]);
+        assert_eq!(
+            "ParseError: conllu_text: must contain at least one token (at char 0, byte 0)",
+            &s.err().unwrap().to_string()
+        );
+
+        let s = Sentence::from_conllu("1\t猫\tNOUN\n", &[]);
+        assert_eq!(
+            "ParseError: conllu_text: each token line must contain 10 tab-separated fields (at char 0, byte 0)",
+            &s.err().unwrap().to_string()
+        );
+
+        let s = Sentence::from_conllu("x\t猫\t_\t_\t_\t_\t_\t_\t_\t_\n", &[]);
+        assert_eq!(
+            "ParseError: conllu_text: contains an invalid ID (at char 0, byte 0)",
+            &s.err().unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_sentence_from_conllu_parse_error() {
+        let conllu = "\
+# text = 火星猫
+1\t火星\t_\t_\t_\t_\t_\t_\t_\tSpaceAfter=No
+2\t猫\0\t_\t_\t_\t_\t_\t_\t_\t_
+";
+        match Sentence::from_conllu(conllu, &[]).err().unwrap() {
+            VaporettoError::Parse(e) => {
+                assert_eq!("conllu_text", e.arg());
+                assert_eq!("must not contain NULL", e.msg());
+                assert_eq!(2, e.line());
+                assert_eq!(3, e.column());
+                assert_eq!(49, e.char_offset());
+                assert_eq!(61, e.byte_offset());
+                assert_eq!(Some('\0'), e.character());
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+
+        match Sentence::from_conllu("# 猫\n1\t猫\n", &[]).err().unwrap() {
+            VaporettoError::Parse(e) => {
+                assert_eq!(1, e.line());
+                assert_eq!(0, e.column());
+                assert_eq!(4, e.char_offset());
+                assert_eq!(6, e.byte_offset());
+            }
+            e => panic!("unexpected error: {e}"),
+        }
+    }
+
+    #[test]
+    fn test_sentence_update_conllu_invalid() {
+        let mut s = Sentence::from_raw("12345").unwrap();
+        let result = s.update_conllu("1\t\t_\t_\t_\t_\t_\t_\t_\t_\n", &[]);
+        assert_eq!(
+            "ParseError: conllu_text: FORM must not be empty (at char 2, byte 2)",
+            &result.err().unwrap().to_string()
+        );
+        assert_eq!(" ", s.as_raw_text());
+    }
+
+    #[test]
+    fn test_sentence_write_conllu_round_trip() {
+        let conllu = "\
+# text = Vaporetto is fast.
+1\tVaporetto\tVaporetto\tPROPN\t_\t_\t_\t_\t_\t_
+2\tis\tbe\tAUX\t_\tMood=Ind\t_\t_\t_\t_
+3\tfast\tfast\tADJ\t_\t_\t_\t_\t_\tSpaceAfter=No
+4\t.\t.\tPUNCT\t_\t_\t_\t_\t_\t_
+";
+        let fields = [ConlluField::Lemma, ConlluField::Upos, ConlluField::Feats];
+        let mut s = Sentence::default();
+        s.update_conllu(conllu, &fields).unwrap();
+        assert_eq!("Vaporetto is fast.", s.as_raw_text());
+
+        let mut buf = String::new();
+        s.write_conllu(&mut buf, &fields);
+        assert_eq!(conllu, buf);
+    }
+
+    #[test]
+    fn test_sentence_write_conllu_unknown() {
+        let s = Sentence::from_partial_annotation("火-星 猫|の|生-態").unwrap();
+        let mut buf = String::new();
+        s.write_conllu(&mut buf, &[]);
+        assert_eq!(
+            "# text = の生態\n1\tの\t_\t_\t_\t_\t_\t_\t_\tSpaceAfter=No\n2\t生態\t_\t_\t_\t_\t_\t_\t_\t_\n",
+            buf
+        );
+    }
+
+    #[test]
+    fn test_conllu_field_from_str() {
+        assert_eq!(ConlluField::Lemma, "Lemma".parse().unwrap());
+        assert_eq!(ConlluField::Xpos, "xpos".parse().unwrap());
+        assert_eq!(ConlluField::Feats, "FEATS".parse().unwrap());
+        assert_eq!(
+            ConlluField::Misc("pos".into()),
+            "pos".parse::<ConlluField>().unwrap()
+        );
+        assert!("".parse::<ConlluField>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sentence_serialize() {
+        let s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "text": "火星猫",
+                "boundaries": ["NotWordBoundary", "WordBoundary"],
+                "boundary_scores": [],
+                "n_tags": 1,
+                "tags": [null, "名詞", null],
+                "tokens": [
+                    {"surface": "火星", "start": 0, "end": 2, "tags": ["名詞"]},
+                    {"surface": "猫", "start": 2, "end": 3, "tags": [null]},
+                ],
+            }),
+            serde_json::to_value(&s).unwrap(),
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_owned_token_serde() {
+        let s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+        let tokens: Vec<OwnedToken> = s.iter_tokens().map(OwnedToken::from).collect();
+        let json = serde_json::to_string(&tokens).unwrap();
+        let borrowed_tokens: Vec<_> = s.iter_tokens().collect();
+        assert_eq!(json, serde_json::to_string(&borrowed_tokens).unwrap());
+        let deserialized: Vec<OwnedToken> = serde_json::from_str(&json).unwrap();
+        assert_eq!(tokens, deserialized);
+        assert_eq!(
+            CharacterType::Kanji,
+            serde_json::from_str("\"Kanji\"").unwrap(),
+        );
+    }
+
+    #[test]
+    fn test_analyzed_sentence() {
+        fn assert_send_sync<T: Send + Sync + 'static>(_: &T) {}
+
+        let s = Sentence::from_partial_annotation("火-星/名詞|猫/名詞|の|生 態").unwrap();
+        let result = AnalyzedSentence::from(&s);
+        drop(s);
+        assert_send_sync(&result);
+
+        assert_eq!("火星猫の生態", result.text());
+        assert_eq!(None, result.boundary_scores());
+        let surfaces: Vec<_> = result.tokens().iter().map(|t| t.surface()).collect();
+        assert_eq!(vec!["火星", "猫", "の"], surfaces);
+        let token = &result.tokens()[1];
+        assert_eq!((2, 3), (token.start(), token.end()));
+        assert_eq!(Some("名詞"), token.tags()[0].as_deref());
+        assert_eq!(&[None], result.tokens()[2].tags());
+        // Identical tags are shared.
+        assert!(Arc::ptr_eq(
+            result.tokens()[0].tags()[0].as_ref().unwrap(),
+            result.tokens()[1].tags()[0].as_ref().unwrap(),
+        ));
+
+        let cloned = result.clone();
+        assert!(Arc::ptr_eq(&result.tokens, &cloned.tokens));
+        assert_eq!(result, cloned);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_analyzed_sentence_serde() {
+        let s = Sentence::from_tokenized("火星/名詞 猫").unwrap();
+        let result = AnalyzedSentence::from(&s);
+        let json = serde_json::to_string(&result).unwrap();
+        assert_eq!(
+            r#"{"text":"火星猫","tokens":[{"surface":"火星","start":0,"end":2,"tags":["名詞"]},{"surface":"猫","start":2,"end":3,"tags":[null]}],"boundary_scores":null}"#,
+            json,
+        );
+        let deserialized: AnalyzedSentence = serde_json::from_str(&json).unwrap();
+        assert_eq!(result, deserialized);
+    }
 }
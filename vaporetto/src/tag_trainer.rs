@@ -1,17 +1,17 @@
 use alloc::borrow::Cow;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::ToString;
 
 use hashbrown::HashMap;
 use liblinear::LibLinearModel;
 
 use crate::errors::{Result, VaporettoError};
-use crate::model::TagModel;
+use crate::model::{
+    fallback_char_features, fallback_type_features, lemma_edit_script, TagModel, FALLBACK_TOKEN,
+};
 use crate::ngram_model::{TagNgramData, TagNgramModel, TagWeight};
-use crate::sentence::Sentence;
-use crate::trainer::{NgramFeature, SolverType};
-
-use crate::trainer::QUANTIZE_BIT_DEPTH;
+use crate::sentence::{Sentence, Token};
+use crate::trainer::{build_liblinear_model, NgramFeature, SolverType};
 
 #[derive(Debug, Eq, Hash, PartialEq)]
 enum TagFeature<'a> {
@@ -37,8 +37,19 @@ impl<'a> TagFeature<'a> {
 
 #[derive(Debug)]
 struct TagExample<'a> {
-    tags: &'a [Option<Cow<'a, str>>],
+    tags: Cow<'a, [Option<Cow<'a, str>>]>,
     features: Vec<TagFeature<'a>>,
+    weight: u32,
+    // The index of the sentence, which is `None` for examples given by the tag dictionary.
+    sentence_id: Option<usize>,
+}
+
+/// Hyperparameters of the solver.
+#[derive(Clone, Copy, Debug)]
+pub struct SolverParams {
+    pub epsilon: f64,
+    pub cost: f64,
+    pub solver: SolverType,
 }
 
 pub struct TagTrainer<'a> {
@@ -49,6 +60,11 @@ pub struct TagTrainer<'a> {
     default_tags: HashMap<&'a str, &'a [Option<Cow<'a, str>>]>,
     // Uses BTreeMap to improve compression ratio.
     examples: BTreeMap<&'a str, Vec<TagExample<'a>>>,
+    // Tag columns predicted by the fallback model. Empty if the fallback model is disabled.
+    fallback_columns: Vec<usize>,
+    fallback_examples: Vec<TagExample<'a>>,
+    // Tag columns whose lemmas are replaced with edit scripts.
+    lemma_columns: Vec<usize>,
 }
 
 impl<'a> TagTrainer<'a> {
@@ -66,14 +82,54 @@ impl<'a> TagTrainer<'a> {
             type_ngram_size,
             default_tags,
             examples: BTreeMap::new(),
+            fallback_columns: vec![],
+            fallback_examples: vec![],
+            lemma_columns: vec![],
+        }
+    }
+
+    pub fn set_fallback_columns(&mut self, columns: Vec<usize>) {
+        self.fallback_columns = columns;
+    }
+
+    pub fn set_lemma_columns(&mut self, columns: Vec<usize>) {
+        self.lemma_columns = columns;
+    }
+
+    /// Replaces lemmas in the given tags with edit scripts from the surface.
+    fn encode_lemmas(
+        &self,
+        surface: &str,
+        tags: &'a [Option<Cow<'a, str>>],
+    ) -> Cow<'a, [Option<Cow<'a, str>>]> {
+        if !self.lemma_columns.iter().any(|&c| c < tags.len()) {
+            return Cow::Borrowed(tags);
         }
+        let mut tags = tags.to_vec();
+        for &c in &self.lemma_columns {
+            if let Some(tag) = tags.get_mut(c) {
+                if let Some(lemma) = tag.as_ref() {
+                    *tag = Some(Cow::Owned(lemma_edit_script(surface, lemma)));
+                }
+            }
+        }
+        Cow::Owned(tags)
     }
 
-    pub fn add_example<'b>(&mut self, sentence: &'a Sentence<'a, 'b>) {
+    pub fn add_example<'b>(
+        &mut self,
+        sentence: &'a Sentence<'a, 'b>,
+        sentence_id: usize,
+        token_weight: impl Fn(&Token) -> u32,
+    ) {
         for token in sentence.iter_tokens() {
             if token.tags().is_empty() {
                 continue;
             }
+            let weight = token_weight(&token);
+            if weight == 0 {
+                continue;
+            }
             let mut features = vec![];
             let token_len = token.end() - token.start();
             for n in 0..usize::from(self.char_ngram_size) {
@@ -98,21 +154,56 @@ impl<'a> TagTrainer<'a> {
                     ));
                 }
             }
+            let tags = self.encode_lemmas(token.surface(), token.tags());
+            if !self.fallback_columns.is_empty() {
+                self.add_fallback_example(sentence, sentence_id, &token, tags.clone(), weight);
+            }
             self.examples
                 .entry(token.surface())
                 .or_default()
                 .push(TagExample {
-                    tags: token.tags(),
+                    tags,
                     features,
+                    weight,
+                    sentence_id: Some(sentence_id),
                 });
         }
     }
 
+    fn add_fallback_example(
+        &mut self,
+        sentence: &'a Sentence<'a, '_>,
+        sentence_id: usize,
+        token: &Token<'a, '_>,
+        tags: Cow<'a, [Option<Cow<'a, str>>]>,
+        weight: u32,
+    ) {
+        let mut features = vec![];
+        for (i, ngram) in fallback_char_features(token.surface())
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(ngram) = ngram {
+                features.push(TagFeature::char_ngram(ngram, isize::try_from(i).unwrap()));
+            }
+        }
+        let char_types = &sentence.char_types()[token.start()..token.end()];
+        for (i, ngram) in fallback_type_features(char_types).into_iter().enumerate() {
+            features.push(TagFeature::type_ngram(ngram, isize::try_from(i).unwrap()));
+        }
+        self.fallback_examples.push(TagExample {
+            tags,
+            features,
+            weight,
+            sentence_id: Some(sentence_id),
+        });
+    }
+
     #[allow(clippy::type_complexity)]
     fn gen_feature_vecs<'b>(
-        examples: &'b [TagExample<'a>],
+        examples: &[&'b TagExample<'a>],
         idx: usize,
-        tag_ids: &HashMap<&'a str, usize>,
+        tag_ids: &HashMap<&'b str, usize>,
     ) -> (
         HashMap<&'b TagFeature<'a>, u32>,
         Vec<Vec<(u32, f64)>>,
@@ -133,6 +224,11 @@ impl<'a> TagTrainer<'a> {
                 let feature_id = *feature_ids.entry(feature).or_insert(new_id);
                 feature_vec.push((feature_id, 1f64));
             }
+            feature_vec.sort_unstable_by_key(|&(fid, _)| fid);
+            for _ in 1..example.weight {
+                ys.push(*ys.last().unwrap());
+                xs.push(feature_vec.clone());
+            }
             xs.push(feature_vec);
         }
         (feature_ids, xs, ys)
@@ -140,16 +236,27 @@ impl<'a> TagTrainer<'a> {
 
     fn train_tag(
         token: String,
-        examples: &[TagExample<'a>],
-        epsilon: f64,
-        cost: f64,
-        solver: SolverType,
+        examples: &[&TagExample<'a>],
+        params: SolverParams,
+        column_params: &[Option<SolverParams>],
+        quantize_bit_depth: u8,
+        columns: Option<&[usize]>,
     ) -> Result<TagModel> {
         let n_tags = examples.iter().fold(0, |acc, x| acc.max(x.tags.len()));
         let mut tag_ids = vec![HashMap::new(); n_tags];
         let mut tags = vec![vec![]; n_tags];
         for example in examples {
-            for ((tag, tag_ids), tags) in example.tags.iter().zip(&mut tag_ids).zip(&mut tags) {
+            for (i, ((tag, tag_ids), tags)) in example
+                .tags
+                .iter()
+                .zip(&mut tag_ids)
+                .zip(&mut tags)
+                .enumerate()
+            {
+                // Columns not to be trained are left empty.
+                if columns.is_some_and(|columns| !columns.contains(&i)) {
+                    continue;
+                }
                 if let Some(tag) = tag {
                     if !tag_ids.contains_key(tag.as_ref()) {
                         let new_id = tag_ids.len();
@@ -159,11 +266,35 @@ impl<'a> TagTrainer<'a> {
                 }
             }
         }
+        // Combinations only restrict predictions of two or more trained columns.
+        let tag_tuples = if tags.iter().filter(|tags| tags.len() >= 2).count() >= 2 {
+            // Uses BTreeSet to make the order deterministic.
+            let tag_tuples: BTreeSet<Vec<_>> = examples
+                .iter()
+                .map(|example| {
+                    tag_ids
+                        .iter()
+                        .enumerate()
+                        .map(|(i, tag_ids)| {
+                            let tag = example.tags.get(i)?.as_ref()?;
+                            tag_ids
+                                .get(tag.as_ref())
+                                .map(|&id| u32::try_from(id).unwrap())
+                        })
+                        .collect()
+                })
+                .collect();
+            tag_tuples.into_iter().collect()
+        } else {
+            vec![]
+        };
+
         let n_class = tags
             .iter()
             .fold(0, |acc, x| acc + if x.len() >= 2 { x.len() } else { 0 });
 
         let mut bias = vec![0; n_class];
+        let mut score_scales = vec![None; n_tags];
 
         // Uses BTreeMap to increase compression ratio.
         let mut char_ngram_weights = BTreeMap::new();
@@ -178,6 +309,11 @@ impl<'a> TagTrainer<'a> {
 
             // train
             let (feature_ids, xs, ys) = Self::gen_feature_vecs(examples, i, tag_ids);
+            let SolverParams {
+                epsilon,
+                cost,
+                solver,
+            } = column_params.get(i).copied().flatten().unwrap_or(params);
 
             let mut builder = liblinear::Builder::new();
             let training_input = liblinear::util::TrainingInput::from_sparse_features(ys, xs)
@@ -188,9 +324,7 @@ impl<'a> TagTrainer<'a> {
                 .solver_type(solver.into())
                 .stopping_criterion(epsilon)
                 .constraints_violation_cost(cost);
-            let model = builder
-                .build_model()
-                .map_err(|e| VaporettoError::invalid_model(e.to_string()))?;
+            let model = build_liblinear_model(builder)?;
 
             // Calculates the quantize multiplier
             let mut weight_max = 1e-6f64;
@@ -202,7 +336,8 @@ impl<'a> TagTrainer<'a> {
                     weight_max = weight_max.max(weight);
                 }
             }
-            let quantize_multiplier = weight_max / f64::from((1 << (QUANTIZE_BIT_DEPTH - 1)) - 1);
+            let quantize_multiplier = weight_max / f64::from((1 << (quantize_bit_depth - 1)) - 1);
+            score_scales[i] = Some(quantize_multiplier);
 
             for (i, &cls) in model.labels().iter().enumerate() {
                 bias[class_offset + usize::try_from(cls).unwrap()] = unsafe {
@@ -295,31 +430,67 @@ impl<'a> TagTrainer<'a> {
                     .collect(),
             ),
             bias,
+            score_scales,
+            tag_tuples,
         })
     }
 
-    pub fn train(mut self, epsilon: f64, cost: f64, solver: SolverType) -> Result<Vec<TagModel>> {
-        for (token, tags) in self.default_tags {
-            if tags.iter().any(|t| t.is_some()) && !self.examples.contains_key(token) {
-                self.examples.insert(
+    pub fn train(
+        &self,
+        params: SolverParams,
+        column_params: &[Option<SolverParams>],
+        quantize_bit_depth: u8,
+        filter: &dyn Fn(usize) -> bool,
+    ) -> Result<Vec<TagModel>> {
+        let is_used = |example: &&TagExample| example.sentence_id.is_none_or(filter);
+        let mut examples: BTreeMap<&str, Vec<&TagExample>> = BTreeMap::new();
+        for (&token, token_examples) in &self.examples {
+            let token_examples: Vec<_> = token_examples.iter().filter(is_used).collect();
+            if !token_examples.is_empty() {
+                examples.insert(token, token_examples);
+            }
+        }
+        let mut default_examples = vec![];
+        for (&token, tags) in &self.default_tags {
+            if tags.iter().any(|t| t.is_some()) && !examples.contains_key(token) {
+                let tags = self.encode_lemmas(token, tags);
+                default_examples.push((
                     token,
-                    vec![TagExample {
+                    TagExample {
                         tags,
                         features: vec![],
-                    }],
-                );
+                        weight: 1,
+                        sentence_id: None,
+                    },
+                ));
             }
         }
+        for (token, example) in &default_examples {
+            examples.insert(token, vec![example]);
+        }
+        let fallback_examples: Vec<_> = self.fallback_examples.iter().filter(is_used).collect();
+
         let mut tag_models = vec![];
         liblinear::toggle_liblinear_stdout_output(false);
-        let n_tokens = self.examples.len();
-        for (i, (token, examples)) in self.examples.into_iter().enumerate() {
+        if !fallback_examples.is_empty() {
+            tag_models.push(Self::train_tag(
+                FALLBACK_TOKEN.into(),
+                &fallback_examples,
+                params,
+                column_params,
+                quantize_bit_depth,
+                Some(&self.fallback_columns),
+            )?);
+        }
+        let n_tokens = examples.len();
+        for (i, (token, examples)) in examples.into_iter().enumerate() {
             tag_models.push(Self::train_tag(
                 token.into(),
                 &examples,
-                epsilon,
-                cost,
-                solver,
+                params,
+                column_params,
+                quantize_bit_depth,
+                None,
             )?);
             eprint!("Tags: {i}/{n_tokens}\r");
         }
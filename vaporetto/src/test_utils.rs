@@ -0,0 +1,100 @@
+use crate::dict_model::{DictModel, WordWeightRecord};
+use crate::model::{Model, TagModel};
+use crate::ngram_model::{NgramData, NgramModel, TagNgramData, TagNgramModel, TagWeight};
+use crate::CharacterType::*;
+
+/// Creates a small model shared by tests.
+pub fn create_test_model() -> Model {
+    // input:    こ  の  人  は  地  球  人  だ
+    // n-grams:
+    //   この人:   -2   3   4
+    //   人だ:                     -5   6   7
+    // n-grams:
+    //   HHK:     -11  12  13
+    //   KH:      -14  15  16  17 -18
+    //                            -14  15  16
+    // dict:
+    //   人:           19  20          19  20
+    //   地球:                 21 -22  23
+    Model::new(
+        NgramModel(vec![
+            NgramData {
+                ngram: "この人".into(),
+                weights: vec![1, -2, 3, 4],
+            },
+            NgramData {
+                ngram: "人だ".into(),
+                weights: vec![-5, 6, 7, 8, 9],
+            },
+        ]),
+        NgramModel(vec![
+            NgramData {
+                ngram: vec![Hiragana as u8, Hiragana as u8, Kanji as u8],
+                weights: vec![10, -11, 12, 13],
+            },
+            NgramData {
+                ngram: vec![Kanji as u8, Hiragana as u8],
+                weights: vec![-14, 15, 16, 17, -18],
+            },
+        ]),
+        DictModel(vec![
+            WordWeightRecord {
+                word: "人".into(),
+                weights: vec![19, 20],
+                comment: "".into(),
+            },
+            WordWeightRecord {
+                word: "地球".into(),
+                weights: vec![21, -22, 23],
+                comment: "".into(),
+            },
+        ]),
+        5,
+        3,
+        3,
+        vec![
+            TagModel {
+                token: "人".into(),
+                tags: vec![
+                    vec!["名詞".into(), "接尾辞".into()],
+                    vec!["ジン".into(), "ヒト".into()],
+                ],
+                char_ngram_model: TagNgramModel(vec![TagNgramData {
+                    ngram: "は地球人".into(),
+                    weights: vec![TagWeight {
+                        rel_position: 0,
+                        weights: vec![-32, 33, 34, -35],
+                    }],
+                }]),
+                type_ngram_model: TagNgramModel(vec![TagNgramData {
+                    ngram: vec![Hiragana as u8, Kanji as u8, Hiragana as u8],
+                    weights: vec![TagWeight {
+                        rel_position: 1,
+                        weights: vec![36, -37, -38, 39],
+                    }],
+                }]),
+                bias: vec![40, 41, 42, 43],
+                score_scales: vec![],
+                tag_tuples: vec![],
+            },
+            TagModel {
+                token: "地球".into(),
+                tags: vec![
+                    vec!["名詞".into()],
+                    vec!["マンホーム".into(), "チキュー".into()],
+                ],
+                char_ngram_model: TagNgramModel(vec![TagNgramData {
+                    ngram: "は地球人".into(),
+                    weights: vec![TagWeight {
+                        rel_position: 1,
+                        weights: vec![-44, 45],
+                    }],
+                }]),
+                type_ngram_model: TagNgramModel(vec![]),
+                bias: vec![46, 47],
+                score_scales: vec![],
+                tag_tuples: vec![],
+            },
+        ],
+    )
+}
@@ -7,15 +7,48 @@ use hashbrown::HashMap;
 use daachorse::DoubleArrayAhoCorasick;
 use liblinear::LibLinearModel;
 
+use crate::char_scorer;
 use crate::dict_model::{DictModel, WordWeightRecord};
 use crate::errors::{Result, VaporettoError};
-use crate::model::Model;
+use crate::model::{lemma_columns, Model, TagColumn, TagModel};
 use crate::ngram_model::{NgramData, NgramModel};
 use crate::sentence::{CharacterBoundary, Sentence};
-use crate::tag_trainer::TagTrainer;
+use crate::tag_trainer::{SolverParams, TagTrainer};
+use crate::type_scorer;
 
-// Bit depth for weight quantization.
-pub const QUANTIZE_BIT_DEPTH: u8 = 16;
+// Default bit depth for weight quantization.
+const DEFAULT_QUANTIZE_BIT_DEPTH: u8 = 16;
+
+// Scores are the sum of dozens of weights, so larger bit depths may cause overflows.
+const MAX_QUANTIZE_BIT_DEPTH: u8 = 24;
+
+/// The maximum weight of an example.
+///
+/// liblinear does not support weights of instances, so weighted examples are trained as copies of
+/// them. Training time and memory grow linearly with the weight.
+#[cfg_attr(docsrs, doc(cfg(feature = "train")))]
+pub const MAX_EXAMPLE_WEIGHT: u32 = 100;
+
+/// Builds a liblinear model.
+pub(crate) fn build_liblinear_model(builder: liblinear::Builder) -> Result<impl LibLinearModel> {
+    builder
+        .build_model()
+        .map_err(|e| VaporettoError::invalid_model(e.to_string()))
+}
+
+/// How to use confidences given to [`Trainer::add_example_with_confidences()`].
+#[cfg_attr(docsrs, doc(cfg(feature = "train")))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfidenceMode {
+    /// Boundaries with confidences lower than the given threshold are ignored as if they were
+    /// [`CharacterBoundary::Unknown`], and the others are used as hard labels.
+    Threshold(f64),
+
+    /// Each boundary is weighted by its confidence multiplied by the given scale and rounded to
+    /// the nearest integer. Boundaries with weight 0 are ignored. The scale must not exceed
+    /// [`MAX_EXAMPLE_WEIGHT`].
+    Weight(u32),
+}
 
 /// Solver type.
 #[cfg_attr(docsrs, doc(cfg(feature = "train")))]
@@ -79,30 +112,50 @@ impl From<SolverType> for liblinear::SolverType {
     }
 }
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct NgramFeature<T> {
     pub ngram: T,
     pub rel_position: isize,
 }
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum DictionaryWordPosition {
     Left,
     Inside,
     Right,
 }
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct DictionaryWordFeature {
+    pub(crate) dict_id: usize,
     pub(crate) length: usize,
     pub(crate) position: DictionaryWordPosition,
 }
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+impl DictionaryWordPosition {
+    fn set_weight(self, weights: &mut (i32, i32, i32), weight: i32) {
+        match self {
+            Self::Left => weights.0 = weight,
+            Self::Inside => weights.1 = weight,
+            Self::Right => weights.2 = weight,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct DictionaryIdFeature {
+    pub(crate) dict_id: usize,
+    pub(crate) id: usize,
+    pub(crate) position: DictionaryWordPosition,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 enum BoundaryFeature<'a> {
     CharacterNgram(NgramFeature<&'a str>),
     CharacterTypeNgram(NgramFeature<&'a [u8]>),
     DictionaryWord(DictionaryWordFeature),
+    DictionaryWordId(DictionaryIdFeature),
+    DictionaryCategory(DictionaryIdFeature),
 }
 
 impl<'a> BoundaryFeature<'a> {
@@ -120,37 +173,105 @@ impl<'a> BoundaryFeature<'a> {
         })
     }
 
-    pub const fn dict_word_left(length: usize) -> Self {
+    pub const fn dict_word_left(dict_id: usize, length: usize) -> Self {
         Self::DictionaryWord(DictionaryWordFeature {
+            dict_id,
             length,
             position: DictionaryWordPosition::Left,
         })
     }
 
-    pub const fn dict_word_inside(length: usize) -> Self {
+    pub const fn dict_word_inside(dict_id: usize, length: usize) -> Self {
         Self::DictionaryWord(DictionaryWordFeature {
+            dict_id,
             length,
             position: DictionaryWordPosition::Inside,
         })
     }
 
-    pub const fn dict_word_right(length: usize) -> Self {
+    pub const fn dict_word_right(dict_id: usize, length: usize) -> Self {
         Self::DictionaryWord(DictionaryWordFeature {
+            dict_id,
             length,
             position: DictionaryWordPosition::Right,
         })
     }
+
+    pub const fn dict_word_id(dict_id: usize, id: usize, position: DictionaryWordPosition) -> Self {
+        Self::DictionaryWordId(DictionaryIdFeature {
+            dict_id,
+            id,
+            position,
+        })
+    }
+
+    pub const fn dict_category(
+        dict_id: usize,
+        id: usize,
+        position: DictionaryWordPosition,
+    ) -> Self {
+        Self::DictionaryCategory(DictionaryIdFeature {
+            dict_id,
+            id,
+            position,
+        })
+    }
 }
 
-#[derive(Clone, Copy)]
-struct DummyValue;
+// A word dictionary that has its own feature space.
+struct TrainerDictionary {
+    name: String,
+    words: Vec<String>,
+    pma: Option<DoubleArrayAhoCorasick<u32>>,
+    // Occurrences of words as tokens, each of which is a tuple of the sentence index, the word
+    // ID, and the weight. Only recorded if individual weights are learned.
+    word_occurrences: Vec<(usize, usize, u32)>,
+    word_categories: Vec<Option<usize>>,
+    categories: Vec<String>,
+}
 
-impl From<usize> for DummyValue {
-    fn from(_: usize) -> Self {
-        Self
+impl TrainerDictionary {
+    fn new(name: String, words: Vec<String>) -> Result<Self> {
+        let pma = if words.is_empty() {
+            None
+        } else {
+            Some(
+                DoubleArrayAhoCorasick::new(&words)
+                    .map_err(|e| VaporettoError::invalid_argument("dict_words", e.to_string()))?,
+            )
+        };
+        Ok(Self {
+            name,
+            words,
+            pma,
+            word_occurrences: vec![],
+            word_categories: vec![],
+            categories: vec![],
+        })
+    }
+
+    /// Counts occurrences of each word in the sentences whose indices satisfy `filter`.
+    fn word_freqs(&self, filter: impl Fn(usize) -> bool) -> Vec<u32> {
+        let mut freqs = vec![0; self.words.len()];
+        for &(sentence_id, word_id, weight) in &self.word_occurrences {
+            if filter(sentence_id) {
+                freqs[word_id] += weight;
+            }
+        }
+        freqs
     }
 }
 
+/// Returns `true` if the range of characters is annotated as a token in the sentence.
+fn is_token(sentence: &Sentence, start: usize, end: usize) -> bool {
+    let boundaries = sentence.boundaries();
+    (start == 0 || boundaries[start - 1] == CharacterBoundary::WordBoundary)
+        && (end == sentence.len() || boundaries[end - 1] == CharacterBoundary::WordBoundary)
+        && boundaries[start..end - 1]
+            .iter()
+            .all(|&b| b == CharacterBoundary::NotWordBoundary)
+}
+
 /// Trainer.
 ///
 /// # Examples
@@ -177,6 +298,14 @@ impl From<usize> for DummyValue {
 /// let mut f = BufWriter::new(File::create("model.bin").unwrap());
 /// model.write(&mut f).unwrap();
 /// ```
+///
+/// # Reproducibility
+///
+/// Features are numbered in a deterministic order, so solvers that do not shuffle examples,
+/// [`SolverType::L2RegularizedLogistic`] and [`SolverType::L2RegularizedL2LossSVC`],
+/// produce the same model from the same examples added in the same order with the same options.
+/// The other solvers shuffle examples with `rand()` of the C library, so byte-identical models are
+/// not guaranteed for them.
 #[cfg_attr(docsrs, doc(cfg(feature = "train")))]
 pub struct Trainer<'a> {
     char_window_size: u8,
@@ -184,13 +313,21 @@ pub struct Trainer<'a> {
     type_window_size: u8,
     type_ngram_size: u8,
     feature_ids: HashMap<BoundaryFeature<'a>, u32>,
-    dict_words: Vec<String>,
-    dict_pma: Option<DoubleArrayAhoCorasick<DummyValue>>,
+    // The first dictionary is the unnamed one given to new().
+    dicts: Vec<TrainerDictionary>,
     dict_word_max_len: u8,
+    dict_word_min_freq: Option<u32>,
+    quantize_bit_depth: u8,
+    narrow_weights: bool,
     xs: Vec<Vec<(u32, f64)>>,
     ys: Vec<f64>,
+    // The index of the sentence of each example in `xs` and `ys`.
+    sentence_ids: Vec<usize>,
+    n_sentences: usize,
 
     tag_trainer: TagTrainer<'a>,
+    tag_column_params: Vec<Option<SolverParams>>,
+    tag_schema: Vec<TagColumn>,
 }
 
 impl<'a> Trainer<'a> {
@@ -220,14 +357,6 @@ impl<'a> Trainer<'a> {
         dict_word_max_len: u8,
         tag_dictionary: &'a [Sentence<'a, '_>],
     ) -> Result<Self> {
-        let dict_pma = if dict_words.is_empty() {
-            None
-        } else {
-            Some(
-                DoubleArrayAhoCorasick::new(&dict_words)
-                    .map_err(|e| VaporettoError::invalid_argument("dict_words", e.to_string()))?,
-            )
-        };
         let mut default_tags = HashMap::new();
         for s in tag_dictionary {
             for token in s.iter_tokens() {
@@ -242,11 +371,15 @@ impl<'a> Trainer<'a> {
             type_window_size,
             type_ngram_size,
             feature_ids: HashMap::new(),
-            dict_words,
-            dict_pma,
+            dicts: vec![TrainerDictionary::new(String::new(), dict_words)?],
             dict_word_max_len,
+            dict_word_min_freq: None,
+            quantize_bit_depth: DEFAULT_QUANTIZE_BIT_DEPTH,
+            narrow_weights: false,
             xs: vec![],
             ys: vec![],
+            sentence_ids: vec![],
+            n_sentences: 0,
             tag_trainer: TagTrainer::new(
                 char_window_size,
                 char_ngram_size,
@@ -254,9 +387,253 @@ impl<'a> Trainer<'a> {
                 type_ngram_size,
                 default_tags,
             ),
+            tag_column_params: vec![],
+            tag_schema: vec![],
         })
     }
 
+    /// Adds a named word dictionary.
+    ///
+    /// Each named dictionary has its own feature space, so its words are weighted independently
+    /// of the other dictionaries. The trained weights are stored in the model with the given name
+    /// and can be replaced by [`Model::replace_named_dictionary()`].
+    ///
+    /// # Errors
+    ///
+    /// If the name is empty or already used, or examples have already been added, an error
+    /// variant will be returned.
+    pub fn add_dictionary(&mut self, name: String, words: Vec<String>) -> Result<()> {
+        if name.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "name",
+                "must not be empty",
+            ));
+        }
+        if self.dicts.iter().any(|dict| dict.name == name) {
+            return Err(VaporettoError::invalid_argument(
+                "name",
+                format!("dictionary `{name}` already exists"),
+            ));
+        }
+        if !self.ys.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "name",
+                "dictionaries must be added before adding examples",
+            ));
+        }
+        self.dicts.push(TrainerDictionary::new(name, words)?);
+        Ok(())
+    }
+
+    /// Learns individual weights of dictionary words that appear at least `min_freq` times as
+    /// tokens in the training examples. Occurrences that do not match the annotated word
+    /// boundaries, e.g., a part of a longer token, are not counted.
+    ///
+    /// By default, all dictionary words of the same length share the same weights. When this
+    /// option is enabled, the individual weights are added to the shared weights, so rare words
+    /// fall back to the weights of their length.
+    ///
+    /// # Errors
+    ///
+    /// If examples have already been added, an error variant will be returned.
+    pub fn learn_dict_word_weights(&mut self, min_freq: u32) -> Result<()> {
+        if !self.ys.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "min_freq",
+                "must be specified before adding examples",
+            ));
+        }
+        self.dict_word_min_freq = Some(min_freq);
+        Ok(())
+    }
+
+    /// Sets the bit depth used to quantize weights. The default is 16.
+    ///
+    /// Smaller bit depths make models smaller. The bit depth is recorded in the model.
+    ///
+    /// # Errors
+    ///
+    /// If `bit_depth` is not in the range of 2 to 24, an error variant will be returned.
+    pub fn set_quantize_bit_depth(&mut self, bit_depth: u8) -> Result<()> {
+        if !(2..=MAX_QUANTIZE_BIT_DEPTH).contains(&bit_depth) {
+            return Err(VaporettoError::invalid_argument(
+                "bit_depth",
+                format!("must be between 2 and {MAX_QUANTIZE_BIT_DEPTH}"),
+            ));
+        }
+        self.quantize_bit_depth = bit_depth;
+        Ok(())
+    }
+
+    /// Makes weights summed up by the predictor fit in the quantize bit depth, so that the
+    /// predictor stores them in 8-bit or 16-bit integers for bit depths up to 8 or 16,
+    /// respectively. The default is `false`.
+    ///
+    /// The summed weights are fitted by quantizing all weights more coarsely, which loses
+    /// precision. Without this option, the predictor stores the summed weights in 32-bit integers
+    /// if they do not fit in 16 bits.
+    ///
+    /// Training fails if this option is enabled and the bit depth is larger than 16.
+    pub fn set_narrow_weights(&mut self, narrow: bool) {
+        self.narrow_weights = narrow;
+    }
+
+    /// Sets hyperparameters used to train the given tag column instead of those given to
+    /// [`Trainer::train()`] or [`Trainer::train_tags()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `column` - The index of the tag column.
+    /// * `epsilon` - The tolerance of the termination criterion.
+    /// * `cost` - The parameter C.
+    /// * `solver` - Solver type.
+    pub fn set_tag_column_params(
+        &mut self,
+        column: usize,
+        epsilon: f64,
+        cost: f64,
+        solver: SolverType,
+    ) {
+        if self.tag_column_params.len() <= column {
+            self.tag_column_params.resize(column + 1, None);
+        }
+        self.tag_column_params[column] = Some(SolverParams {
+            epsilon,
+            cost,
+            solver,
+        });
+    }
+
+    /// Enables the fallback tag model that predicts tags of tokens without their own tag models.
+    ///
+    /// The fallback model is trained over all tokens with features that do not depend on the
+    /// surface itself, such as the last characters and character types, so unknown words also get
+    /// tags. Since such features cannot predict surface-specific tags like pronunciations, only
+    /// the given tag columns are trained.
+    ///
+    /// # Errors
+    ///
+    /// If examples have already been added, an error variant will be returned.
+    pub fn set_fallback_tag_columns(&mut self, columns: Vec<usize>) -> Result<()> {
+        if !self.ys.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "columns",
+                "must be specified before adding examples",
+            ));
+        }
+        self.tag_trainer.set_fallback_columns(columns);
+        Ok(())
+    }
+
+    /// Sets the schema of tag columns, which is stored in the trained model.
+    ///
+    /// Lemmas in [`TagColumnKind::Lemma`](crate::TagColumnKind::Lemma) columns are trained as
+    /// edit scripts from the surfaces. Since the scripts do not depend on the surfaces, enabling
+    /// the fallback model for lemma columns by [`Trainer::set_fallback_tag_columns()`] allows
+    /// predicting lemmas of unseen inflections.
+    ///
+    /// # Errors
+    ///
+    /// If examples have already been added, an error variant will be returned.
+    pub fn set_tag_schema(&mut self, schema: Vec<TagColumn>) -> Result<()> {
+        if !self.ys.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "schema",
+                "must be specified before adding examples",
+            ));
+        }
+        self.tag_trainer
+            .set_lemma_columns(lemma_columns(&schema).collect());
+        self.tag_schema = schema;
+        Ok(())
+    }
+
+    /// Sets categories of dictionary words.
+    ///
+    /// Words in the same category share additional weights, so a reliable word list can
+    /// influence segmentation more than a noisy one. The category name of each word is stored
+    /// in the trained model as the comment of its [`WordWeightRecord`], which does not affect
+    /// prediction. Words without a category have empty comments.
+    ///
+    /// # Arguments
+    ///
+    /// * `dict_name` - The name of a dictionary given to [`Trainer::add_dictionary()`], or an
+    ///   empty string for the dictionary given to [`Trainer::new()`].
+    /// * `categories` - A category name of each word in the dictionary. `None` means the word has
+    ///   no category.
+    ///
+    /// # Errors
+    ///
+    /// If the dictionary does not exist, the length of `categories` does not match the number of
+    /// dictionary words, or examples have already been added, an error variant will be returned.
+    pub fn set_dict_word_categories(
+        &mut self,
+        dict_name: &str,
+        categories: Vec<Option<String>>,
+    ) -> Result<()> {
+        if !self.ys.is_empty() {
+            return Err(VaporettoError::invalid_argument(
+                "categories",
+                "must be specified before adding examples",
+            ));
+        }
+        let dict = self
+            .dicts
+            .iter_mut()
+            .find(|dict| dict.name == dict_name)
+            .ok_or_else(|| {
+                VaporettoError::invalid_argument(
+                    "dict_name",
+                    format!("dictionary `{dict_name}` does not exist"),
+                )
+            })?;
+        if categories.len() != dict.words.len() {
+            return Err(VaporettoError::invalid_argument(
+                "categories",
+                "does not match the number of dictionary words",
+            ));
+        }
+        let mut category_ids = HashMap::new();
+        dict.categories.clear();
+        dict.word_categories = categories
+            .into_iter()
+            .map(|category| {
+                category.map(|category| {
+                    *category_ids.entry(category.clone()).or_insert_with(|| {
+                        dict.categories.push(category);
+                        dict.categories.len() - 1
+                    })
+                })
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn push_dict_features(
+        &self,
+        features: &mut Vec<BoundaryFeature<'a>>,
+        dict_id: usize,
+        length: usize,
+        word_id: usize,
+        position: DictionaryWordPosition,
+    ) {
+        features.push(match position {
+            DictionaryWordPosition::Left => BoundaryFeature::dict_word_left(dict_id, length),
+            DictionaryWordPosition::Inside => BoundaryFeature::dict_word_inside(dict_id, length),
+            DictionaryWordPosition::Right => BoundaryFeature::dict_word_right(dict_id, length),
+        });
+        if let Some(&Some(category_id)) = self.dicts[dict_id].word_categories.get(word_id) {
+            features.push(BoundaryFeature::dict_category(
+                dict_id,
+                category_id,
+                position,
+            ));
+        }
+        if self.dict_word_min_freq.is_some() {
+            features.push(BoundaryFeature::dict_word_id(dict_id, word_id, position));
+        }
+    }
+
     fn gen_features<'b>(
         &self,
         sentence: &'a Sentence<'a, 'b>,
@@ -293,25 +670,43 @@ impl<'a> Trainer<'a> {
             examples.push((features, b));
         }
         // adds dictionary features
-        if let Some(pma) = self.dict_pma.as_ref() {
+        for (dict_id, dict) in self.dicts.iter().enumerate() {
+            let Some(pma) = dict.pma.as_ref() else {
+                continue;
+            };
             for m in pma.find_overlapping_iter(sentence.text.as_ref()) {
                 debug_assert!(sentence.text.is_char_boundary(m.start()));
                 let start = unsafe { sentence.str_to_char_pos(m.start()) };
                 debug_assert!(sentence.text.is_char_boundary(m.end()));
                 let end = unsafe { sentence.str_to_char_pos(m.end()) };
                 let length = (end - start).min(usize::from(self.dict_word_max_len));
+                let word_id = usize::try_from(m.value()).unwrap();
                 if start != 0 {
-                    examples[start - 1]
-                        .0
-                        .push(BoundaryFeature::dict_word_left(length));
+                    self.push_dict_features(
+                        &mut examples[start - 1].0,
+                        dict_id,
+                        length,
+                        word_id,
+                        DictionaryWordPosition::Left,
+                    );
                 }
                 for example in &mut examples[start..end - 1] {
-                    example.0.push(BoundaryFeature::dict_word_inside(length));
+                    self.push_dict_features(
+                        &mut example.0,
+                        dict_id,
+                        length,
+                        word_id,
+                        DictionaryWordPosition::Inside,
+                    );
                 }
                 if end != sentence.len() {
-                    examples[end - 1]
-                        .0
-                        .push(BoundaryFeature::dict_word_right(length));
+                    self.push_dict_features(
+                        &mut examples[end - 1].0,
+                        dict_id,
+                        length,
+                        word_id,
+                        DictionaryWordPosition::Right,
+                    );
                 }
             }
         }
@@ -319,9 +714,120 @@ impl<'a> Trainer<'a> {
 
     /// Adds a sentence to the trainer.
     pub fn add_example<'b>(&mut self, sentence: &'a Sentence<'a, 'b>) {
+        self.add_example_with_boundary_weights(sentence, 1, false, |_| 1);
+    }
+
+    /// Adds a sentence with a weight to the trainer.
+    ///
+    /// The sentence is treated as if it were added `weight` times, so that a small corpus is
+    /// not overwhelmed by larger ones. If `weight` is 0, the sentence is ignored.
+    ///
+    /// # Errors
+    ///
+    /// If `weight` is larger than [`MAX_EXAMPLE_WEIGHT`], an error variant will be returned.
+    pub fn add_weighted_example<'b>(
+        &mut self,
+        sentence: &'a Sentence<'a, 'b>,
+        weight: u32,
+    ) -> Result<()> {
+        if weight > MAX_EXAMPLE_WEIGHT {
+            return Err(VaporettoError::invalid_argument(
+                "weight",
+                format!("must be at most {MAX_EXAMPLE_WEIGHT}"),
+            ));
+        }
+        if weight == 0 {
+            self.n_sentences += 1;
+            return Ok(());
+        }
+        self.add_example_with_boundary_weights(sentence, weight, false, |_| weight);
+        Ok(())
+    }
+
+    /// Adds a sentence annotated by another analyzer with the confidence of each boundary.
+    ///
+    /// This is useful to distill the noisy output of a slow analyzer into a model. Tags of a
+    /// token are trained only if all boundaries of the token are used, and with the minimum weight
+    /// of them. Unlike [`Trainer::add_example()`], [`CharacterBoundary::Unknown`] boundaries are
+    /// not trained.
+    ///
+    /// # Arguments
+    ///
+    /// * `sentence` - A sentence annotated by another analyzer.
+    /// * `confidences` - The confidence of each boundary between 0 and 1.
+    /// * `mode` - How to use the confidences.
+    ///
+    /// # Errors
+    ///
+    /// If the length of `confidences` is not equal to the number of boundaries, a confidence is
+    /// out of range, or the scale of [`ConfidenceMode::Weight`] is larger than
+    /// [`MAX_EXAMPLE_WEIGHT`], an error variant will be returned.
+    pub fn add_example_with_confidences<'b>(
+        &mut self,
+        sentence: &'a Sentence<'a, 'b>,
+        confidences: &[f64],
+        mode: ConfidenceMode,
+    ) -> Result<()> {
+        if confidences.len() != sentence.boundaries().len() {
+            return Err(VaporettoError::invalid_argument(
+                "confidences",
+                "must have the same length as the boundaries",
+            ));
+        }
+        if confidences.iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return Err(VaporettoError::invalid_argument(
+                "confidences",
+                "must be in the range of [0, 1]",
+            ));
+        }
+        if matches!(mode, ConfidenceMode::Weight(scale) if scale > MAX_EXAMPLE_WEIGHT) {
+            return Err(VaporettoError::invalid_argument(
+                "mode",
+                format!("the scale must be at most {MAX_EXAMPLE_WEIGHT}"),
+            ));
+        }
+        self.add_example_with_boundary_weights(sentence, 1, true, |i| match mode {
+            ConfidenceMode::Threshold(threshold) => u32::from(confidences[i] >= threshold),
+            ConfidenceMode::Weight(scale) => (confidences[i] * f64::from(scale)).round() as u32,
+        });
+        Ok(())
+    }
+
+    /// Adds a sentence with a weight of each boundary. Boundaries with weight 0 are ignored.
+    ///
+    /// If `skip_unknown` is true, unknown boundaries are also ignored. Otherwise, they are trained
+    /// as the third class.
+    fn add_example_with_boundary_weights<'b>(
+        &mut self,
+        sentence: &'a Sentence<'a, 'b>,
+        sentence_weight: u32,
+        skip_unknown: bool,
+        boundary_weight: impl Fn(usize) -> u32,
+    ) {
+        let sentence_id = self.n_sentences;
+        self.n_sentences += 1;
         let mut examples = vec![];
         self.gen_features(sentence, &mut examples);
-        for (features, b) in examples {
+        if self.dict_word_min_freq.is_some() {
+            for dict in &mut self.dicts {
+                if let Some(pma) = dict.pma.as_ref() {
+                    for m in pma.find_overlapping_iter(sentence.text.as_ref()) {
+                        let start = unsafe { sentence.str_to_char_pos(m.start()) };
+                        let end = unsafe { sentence.str_to_char_pos(m.end()) };
+                        if is_token(sentence, start, end) {
+                            let word_id = usize::try_from(m.value()).unwrap();
+                            dict.word_occurrences
+                                .push((sentence_id, word_id, sentence_weight));
+                        }
+                    }
+                }
+            }
+        }
+        for (i, (features, b)) in examples.into_iter().enumerate() {
+            let weight = boundary_weight(i);
+            if (skip_unknown && b == CharacterBoundary::Unknown) || weight == 0 {
+                continue;
+            }
             let mut feature_vector = HashMap::new();
             for feature in features {
                 let new_id = self.feature_ids.len() + 1;
@@ -331,11 +837,31 @@ impl<'a> Trainer<'a> {
                     .or_insert(new_id.try_into().unwrap());
                 *feature_vector.entry(feature_id).or_insert(0f64) += 1f64;
             }
-            self.xs.push(feature_vector.into_iter().collect());
+            // liblinear requires feature indices in ascending order, and sorting them also makes
+            // the result independent of the iteration order of the hash map.
+            let mut feature_vector: Vec<_> = feature_vector.into_iter().collect();
+            feature_vector.sort_unstable_by_key(|&(fid, _)| fid);
+            // Weighted examples are duplicated because the solver does not support instance
+            // weights.
+            for _ in 1..weight {
+                self.xs.push(feature_vector.clone());
+                self.ys.push(f64::from(b as u8));
+                self.sentence_ids.push(sentence_id);
+            }
+            self.xs.push(feature_vector);
             self.ys.push(f64::from(b as u8));
+            self.sentence_ids.push(sentence_id);
         }
 
-        self.tag_trainer.add_example(sentence);
+        let n_boundaries = sentence.boundaries().len();
+        self.tag_trainer
+            .add_example(sentence, sentence_id, |token| {
+                // Boundaries on both edges and inside the token.
+                (token.start().saturating_sub(1)..token.end().min(n_boundaries))
+                    .map(&boundary_weight)
+                    .min()
+                    .unwrap_or(sentence_weight)
+            });
     }
 
     /// Trains word boundaries and tags.
@@ -349,9 +875,77 @@ impl<'a> Trainer<'a> {
     /// # Errors
     ///
     /// If the solver returns an error, that will be propagated.
-    pub fn train(self, epsilon: f64, cost: f64, solver: SolverType) -> Result<Model> {
+    pub fn train(mut self, epsilon: f64, cost: f64, solver: SolverType) -> Result<Model> {
+        let xs = core::mem::take(&mut self.xs);
+        let ys = core::mem::take(&mut self.ys);
+        self.train_examples(xs, ys, epsilon, cost, solver, &|_| true)
+    }
+
+    /// Trains word boundaries and tags only from sentences whose indices satisfy `filter`.
+    ///
+    /// Sentences are indexed from 0 in the order they are added. Unlike [`Trainer::train()`],
+    /// the trainer is not consumed, so features are extracted only once for cross-validation.
+    ///
+    /// # Arguments
+    ///
+    /// * `epsilon` - The tolerance of the termination criterion.
+    /// * `cost` - The parameter C.
+    /// * `solver` - Solver type.
+    /// * `filter` - Returns `true` for indices of sentences used for training.
+    ///
+    /// # Errors
+    ///
+    /// If the solver returns an error, that will be propagated.
+    pub fn train_subset(
+        &self,
+        epsilon: f64,
+        cost: f64,
+        solver: SolverType,
+        filter: impl Fn(usize) -> bool,
+    ) -> Result<Model> {
+        let mut xs = vec![];
+        let mut ys = vec![];
+        for ((x, &y), &sentence_id) in self.xs.iter().zip(&self.ys).zip(&self.sentence_ids) {
+            if filter(sentence_id) {
+                xs.push(x.clone());
+                ys.push(y);
+            }
+        }
+        self.train_examples(xs, ys, epsilon, cost, solver, &filter)
+    }
+
+    fn train_examples(
+        &self,
+        mut xs: Vec<Vec<(u32, f64)>>,
+        ys: Vec<f64>,
+        epsilon: f64,
+        cost: f64,
+        solver: SolverType,
+        filter: &dyn Fn(usize) -> bool,
+    ) -> Result<Model> {
+        // Removes individual features of rare dictionary words.
+        if let Some(min_freq) = self.dict_word_min_freq {
+            let word_freqs: Vec<_> = self
+                .dicts
+                .iter()
+                .map(|dict| dict.word_freqs(filter))
+                .collect();
+            let mut removed = vec![false; self.feature_ids.len() + 1];
+            for (feature, &fid) in &self.feature_ids {
+                if let BoundaryFeature::DictionaryWordId(DictionaryIdFeature {
+                    dict_id, id, ..
+                }) = feature
+                {
+                    removed[usize::try_from(fid)?] = word_freqs[*dict_id][*id] < min_freq;
+                }
+            }
+            for x in &mut xs {
+                x.retain(|&(fid, _)| !removed[usize::try_from(fid).unwrap()]);
+            }
+        }
+
         let mut builder = liblinear::Builder::new();
-        let training_input = liblinear::util::TrainingInput::from_sparse_features(self.ys, self.xs)
+        let training_input = liblinear::util::TrainingInput::from_sparse_features(ys, xs)
             .map_err(|e| VaporettoError::invalid_model(format!("liblinear error: {e:?}")))?;
         builder.problem().input_data(training_input).bias(1.0);
         builder
@@ -359,9 +953,7 @@ impl<'a> Trainer<'a> {
             .solver_type(solver.into())
             .stopping_criterion(epsilon)
             .constraints_violation_cost(cost);
-        let model = builder
-            .build_model()
-            .map_err(|e| VaporettoError::invalid_model(e.to_string()))?;
+        let model = build_liblinear_model(builder)?;
 
         let wb_idx = i32::try_from(
             model
@@ -380,19 +972,74 @@ impl<'a> Trainer<'a> {
                 .abs();
             weight_max = weight_max.max(weight);
         }
-        let quantize_multiplier = weight_max / f64::from((1 << (QUANTIZE_BIT_DEPTH - 1)) - 1);
+        if self.narrow_weights && self.quantize_bit_depth > 16 {
+            return Err(VaporettoError::invalid_argument(
+                "quantize_bit_depth",
+                "must be at most 16 to narrow weights",
+            ));
+        }
+        let quantize_max = (1 << (self.quantize_bit_depth - 1)) - 1;
+        let mut quantize_multiplier = weight_max / f64::from(quantize_max);
         if quantize_multiplier == 0. {
             return Err(VaporettoError::invalid_model("all weights are zero"));
         }
 
+        let tag_models = self.tag_trainer.train(
+            SolverParams {
+                epsilon,
+                cost,
+                solver,
+            },
+            &self.tag_column_params,
+            self.quantize_bit_depth,
+            filter,
+        )?;
+
+        // The predictor sums up weights of n-grams sharing suffixes, so the multiplier is enlarged
+        // until the summed weights also fit in the bit depth if narrow weights are requested.
+        loop {
+            let mut model = self.quantize_model(&model, wb_idx, quantize_multiplier)?;
+            let merged_max = if self.narrow_weights {
+                self.max_merged_weight(&model)?
+            } else {
+                0
+            };
+            if merged_max <= quantize_max {
+                model.0.tag_models = tag_models;
+                model.0.quantize_bit_depth = Some(self.quantize_bit_depth);
+                if !self.tag_schema.is_empty() {
+                    model.set_tag_schema(self.tag_schema.clone())?;
+                }
+                return Ok(model);
+            }
+            quantize_multiplier *= f64::from(merged_max) / f64::from(quantize_max);
+        }
+    }
+
+    /// Converts weights of the boundary model into integers divided by the given multiplier.
+    fn quantize_model(
+        &self,
+        model: &impl LibLinearModel,
+        wb_idx: i32,
+        quantize_multiplier: f64,
+    ) -> Result<Model> {
+        let bias = model.label_bias(wb_idx);
+
         // Uses BTreeMap to improve compression ratio.
         let mut char_ngram_weights: BTreeMap<_, Vec<_>> = BTreeMap::new();
         let mut type_ngram_weights: BTreeMap<_, Vec<_>> = BTreeMap::new();
-        let mut dict_weights = vec![(0, 0, 0); usize::from(self.dict_word_max_len)];
+        let mut dict_weights =
+            vec![vec![(0, 0, 0); usize::from(self.dict_word_max_len)]; self.dicts.len()];
+        let mut dict_word_weights = HashMap::new();
+        let mut dict_category_weights: Vec<_> = self
+            .dicts
+            .iter()
+            .map(|dict| vec![(0, 0, 0); dict.categories.len()])
+            .collect();
 
         let bias = unsafe { (bias / quantize_multiplier).to_int_unchecked::<i32>() };
 
-        for (feature, fid) in self.feature_ids {
+        for (&feature, &fid) in &self.feature_ids {
             let raw_weight = model.feature_coefficient(i32::try_from(fid)?, wb_idx);
             let weight = unsafe { (raw_weight / quantize_multiplier).to_int_unchecked::<i32>() };
 
@@ -424,31 +1071,83 @@ impl<'a> Trainer<'a> {
                 }) => {
                     let len = ngram.len();
                     let pos = usize::try_from(
-                        isize::from(self.char_window_size) - isize::try_from(len)? - rel_position,
+                        isize::from(self.type_window_size) - isize::try_from(len)? - rel_position,
                     )
                     .unwrap();
                     if let Some(weights) = type_ngram_weights.get_mut(ngram) {
                         weights[pos] = weight;
                     } else {
-                        let mut weights = vec![0; usize::from(self.char_window_size) * 2 - len + 1];
+                        let mut weights = vec![0; usize::from(self.type_window_size) * 2 - len + 1];
                         weights[pos] = weight;
                         type_ngram_weights.insert(ngram.to_vec(), weights);
                     }
                 }
-                BoundaryFeature::DictionaryWord(DictionaryWordFeature { length, position }) => {
-                    let weights = &mut dict_weights[length - 1];
-                    match position {
-                        DictionaryWordPosition::Left => weights.0 = weight,
-                        DictionaryWordPosition::Inside => weights.1 = weight,
-                        DictionaryWordPosition::Right => weights.2 = weight,
-                    }
+                BoundaryFeature::DictionaryWord(DictionaryWordFeature {
+                    dict_id,
+                    length,
+                    position,
+                }) => {
+                    position.set_weight(&mut dict_weights[dict_id][length - 1], weight);
+                }
+                BoundaryFeature::DictionaryWordId(DictionaryIdFeature {
+                    dict_id,
+                    id,
+                    position,
+                }) => {
+                    position.set_weight(
+                        dict_word_weights.entry((dict_id, id)).or_insert((0, 0, 0)),
+                        weight,
+                    );
+                }
+                BoundaryFeature::DictionaryCategory(DictionaryIdFeature {
+                    dict_id,
+                    id,
+                    position,
+                }) => {
+                    position.set_weight(&mut dict_category_weights[dict_id][id], weight);
                 }
             }
         }
 
-        let tag_models = self.tag_trainer.train(epsilon, cost, solver)?;
+        let mut dict_models = self.dicts.iter().enumerate().map(|(dict_id, dict)| {
+            let dict_weights = &dict_weights[dict_id];
+            let dict_category_weights = &dict_category_weights[dict_id];
+            let records = dict
+                .words
+                .iter()
+                .enumerate()
+                .map(|(word_id, word)| {
+                    let word_len = word.chars().count();
+                    let idx = word_len.min(dict_weights.len()) - 1;
+                    let mut word_weights = dict_weights[idx];
+                    let category_id = dict.word_categories.get(word_id).copied().flatten();
+                    // Category and individual weights are added to the shared weights.
+                    for (left, inside, right) in category_id
+                        .map(|id| dict_category_weights[id])
+                        .into_iter()
+                        .chain(dict_word_weights.get(&(dict_id, word_id)).copied())
+                    {
+                        word_weights.0 += left;
+                        word_weights.1 += inside;
+                        word_weights.2 += right;
+                    }
+                    let mut weights = vec![0; word_len + 1];
+                    *weights.first_mut().unwrap() = word_weights.0;
+                    weights[1..word_len].fill(word_weights.1);
+                    *weights.last_mut().unwrap() = word_weights.2;
+                    WordWeightRecord {
+                        word: word.clone(),
+                        weights,
+                        comment: category_id
+                            .map_or_else(String::new, |id| dict.categories[id].clone()),
+                    }
+                })
+                .collect();
+            (dict.name.clone(), DictModel::new(records))
+        });
+        let (_, dict_model) = dict_models.next().unwrap();
 
-        Ok(Model::new(
+        let mut model = Model::new(
             NgramModel(
                 char_ngram_weights
                     .into_iter()
@@ -461,29 +1160,79 @@ impl<'a> Trainer<'a> {
                     .map(|(ngram, weights)| NgramData { ngram, weights })
                     .collect(),
             ),
-            DictModel::new(
-                self.dict_words
-                    .into_iter()
-                    .map(|word| {
-                        let word_len = word.chars().count();
-                        let idx = word_len.min(dict_weights.len()) - 1;
-                        let mut weights = vec![0; word_len + 1];
-                        *weights.first_mut().unwrap() = dict_weights[idx].0;
-                        weights[1..word_len].fill(dict_weights[idx].1);
-                        *weights.last_mut().unwrap() = dict_weights[idx].2;
-                        WordWeightRecord {
-                            word,
-                            weights,
-                            comment: "".to_string(),
-                        }
-                    })
-                    .collect(),
-            ),
+            dict_model,
             bias,
             self.char_window_size,
             self.type_window_size,
-            tag_models,
-        ))
+            vec![],
+        );
+        model.0.named_dict_models = dict_models.collect();
+        Ok(model)
+    }
+
+    /// Returns the largest absolute weight stored by the predictor of the given model.
+    fn max_merged_weight(&self, model: &Model) -> Result<i32> {
+        let mut dict_model = DictModel(model.0.dict_model.0.clone());
+        for (_, named_dict_model) in &model.0.named_dict_models {
+            dict_model.0.extend(named_dict_model.0.iter().cloned());
+        }
+        let char_max = char_scorer::max_merged_weight(
+            NgramModel(model.0.char_ngram_model.0.clone()),
+            dict_model,
+            self.char_window_size,
+        )?;
+        let type_max = type_scorer::max_merged_weight(
+            NgramModel(model.0.type_ngram_model.0.clone()),
+            self.type_window_size,
+        );
+        Ok(char_max.max(type_max))
+    }
+
+    /// Trains only tags.
+    ///
+    /// The returned tag models can be attached to an existing model by
+    /// [`Model::replace_tag_models()`] or [`Model::append_tag_columns()`], so tags can be
+    /// retrained without changing word boundaries. The tag schema given by
+    /// [`Trainer::set_tag_schema()`] is not attached, so set it by [`Model::set_tag_schema()`]
+    /// if necessary.
+    ///
+    /// # Arguments
+    ///
+    /// * `epsilon` - The tolerance of the termination criterion.
+    /// * `cost` - The parameter C.
+    /// * `solver` - Solver type.
+    ///
+    /// # Errors
+    ///
+    /// If the solver returns an error, that will be propagated.
+    pub fn train_tags(self, epsilon: f64, cost: f64, solver: SolverType) -> Result<Vec<TagModel>> {
+        self.train_tags_subset(epsilon, cost, solver, |_| true)
+    }
+
+    /// Trains only tags from sentences whose indices satisfy `filter`.
+    ///
+    /// See [`Trainer::train_tags()`] and [`Trainer::train_subset()`] for details.
+    ///
+    /// # Errors
+    ///
+    /// If the solver returns an error, that will be propagated.
+    pub fn train_tags_subset(
+        &self,
+        epsilon: f64,
+        cost: f64,
+        solver: SolverType,
+        filter: impl Fn(usize) -> bool,
+    ) -> Result<Vec<TagModel>> {
+        self.tag_trainer.train(
+            SolverParams {
+                epsilon,
+                cost,
+                solver,
+            },
+            &self.tag_column_params,
+            self.quantize_bit_depth,
+            &filter,
+        )
     }
 
     /// Returns the number of boundary features.
@@ -723,8 +1472,8 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Hiragana as u8], 1),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], -1),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], 0),
-                BoundaryFeature::dict_word_inside(2),
-                BoundaryFeature::dict_word_inside(3),
+                BoundaryFeature::dict_word_inside(0, 2),
+                BoundaryFeature::dict_word_inside(0, 3),
             ],
             examples[0].0,
         );
@@ -747,8 +1496,8 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], -2),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], -1),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Katakana as u8], 0),
-                BoundaryFeature::dict_word_right(2),
-                BoundaryFeature::dict_word_inside(3),
+                BoundaryFeature::dict_word_right(0, 2),
+                BoundaryFeature::dict_word_inside(0, 3),
             ],
             examples[1].0,
         );
@@ -771,8 +1520,8 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], -2),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Katakana as u8], -1),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], 0),
-                BoundaryFeature::dict_word_right(3),
-                BoundaryFeature::dict_word_left(3),
+                BoundaryFeature::dict_word_right(0, 3),
+                BoundaryFeature::dict_word_left(0, 3),
             ],
             examples[2].0,
         );
@@ -795,7 +1544,7 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Katakana as u8], -2),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], -1),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], 0),
-                BoundaryFeature::dict_word_inside(3),
+                BoundaryFeature::dict_word_inside(0, 3),
             ],
             examples[3].0,
         );
@@ -818,7 +1567,7 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], -2),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], -1),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Hiragana as u8], 0),
-                BoundaryFeature::dict_word_inside(3),
+                BoundaryFeature::dict_word_inside(0, 3),
             ],
             examples[4].0,
         );
@@ -841,7 +1590,7 @@ mod tests {
                 BoundaryFeature::type_ngram(&[Katakana as u8, Katakana as u8], -2),
                 BoundaryFeature::type_ngram(&[Katakana as u8, Hiragana as u8], -1),
                 BoundaryFeature::type_ngram(&[Hiragana as u8, Hiragana as u8], 0),
-                BoundaryFeature::dict_word_right(3),
+                BoundaryFeature::dict_word_right(0, 3),
             ],
             examples[5].0,
         );
@@ -865,4 +1614,597 @@ mod tests {
         );
         assert_eq!(NotWordBoundary, examples[6].1);
     }
+
+    #[test]
+    fn check_features_1111_dict_word_weights() {
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let mut trainer =
+            Trainer::new(1, 1, 1, 1, vec!["これ".into(), "テスト".into()], 4, &[]).unwrap();
+        trainer.learn_dict_word_weights(2).unwrap();
+        trainer
+            .set_dict_word_categories("", vec![None, Some("外来語".into())])
+            .unwrap();
+        let mut examples = vec![];
+        trainer.gen_features(&s, &mut examples);
+
+        let dict_features: Vec<Vec<_>> = examples
+            .iter()
+            .map(|(features, _)| {
+                features
+                    .iter()
+                    .filter(|f| {
+                        !matches!(
+                            f,
+                            BoundaryFeature::CharacterNgram(_)
+                                | BoundaryFeature::CharacterTypeNgram(_)
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                // こ-れ
+                vec![
+                    &BoundaryFeature::dict_word_inside(0, 2),
+                    &BoundaryFeature::dict_word_id(0, 0, DictionaryWordPosition::Inside),
+                ],
+                // れ|は
+                vec![
+                    &BoundaryFeature::dict_word_right(0, 2),
+                    &BoundaryFeature::dict_word_id(0, 0, DictionaryWordPosition::Right),
+                ],
+                // は|テ
+                vec![
+                    &BoundaryFeature::dict_word_left(0, 3),
+                    &BoundaryFeature::dict_category(0, 0, DictionaryWordPosition::Left),
+                    &BoundaryFeature::dict_word_id(0, 1, DictionaryWordPosition::Left),
+                ],
+                // テ-ス
+                vec![
+                    &BoundaryFeature::dict_word_inside(0, 3),
+                    &BoundaryFeature::dict_category(0, 0, DictionaryWordPosition::Inside),
+                    &BoundaryFeature::dict_word_id(0, 1, DictionaryWordPosition::Inside),
+                ],
+                // ス-ト
+                vec![
+                    &BoundaryFeature::dict_word_inside(0, 3),
+                    &BoundaryFeature::dict_category(0, 0, DictionaryWordPosition::Inside),
+                    &BoundaryFeature::dict_word_id(0, 1, DictionaryWordPosition::Inside),
+                ],
+                // ト|で
+                vec![
+                    &BoundaryFeature::dict_word_right(0, 3),
+                    &BoundaryFeature::dict_category(0, 0, DictionaryWordPosition::Right),
+                    &BoundaryFeature::dict_word_id(0, 1, DictionaryWordPosition::Right),
+                ],
+                // で-す
+                vec![],
+            ],
+            dict_features,
+        );
+    }
+
+    #[test]
+    fn check_dict_word_freqs() {
+        let s = Sentence::from_tokenized("す です す テスト").unwrap();
+        let mut trainer = Trainer::new(
+            1,
+            1,
+            1,
+            1,
+            vec!["す".into(), "です".into(), "ス".into()],
+            4,
+            &[],
+        )
+        .unwrap();
+        trainer.learn_dict_word_weights(1).unwrap();
+        trainer.add_weighted_example(&s, 2).unwrap();
+
+        // Occurrences inside longer tokens are not counted.
+        assert_eq!(vec![4, 2, 0], trainer.dicts[0].word_freqs(|_| true));
+    }
+
+    #[test]
+    fn check_dict_word_freqs_partial_annotation() {
+        let s = Sentence::from_partial_annotation("す|で-す す テ-ス-ト").unwrap();
+        let mut trainer =
+            Trainer::new(1, 1, 1, 1, vec!["す".into(), "です".into()], 4, &[]).unwrap();
+        trainer.learn_dict_word_weights(1).unwrap();
+        trainer.add_example(&s);
+
+        // Occurrences next to unknown boundaries are not counted.
+        assert_eq!(vec![1, 0], trainer.dicts[0].word_freqs(|_| true));
+    }
+
+    #[test]
+    fn check_dict_word_options_after_examples() {
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let mut trainer = Trainer::new(1, 1, 1, 1, vec!["これ".into()], 4, &[]).unwrap();
+        trainer.add_example(&s);
+        assert!(trainer.learn_dict_word_weights(1).is_err());
+        assert!(trainer.set_dict_word_categories("", vec![None]).is_err());
+    }
+
+    #[test]
+    fn check_weighted_example() {
+        let s1 = Sentence::from_tokenized("これ は テスト").unwrap();
+        let s2 = Sentence::from_tokenized("テスト です").unwrap();
+
+        let mut trainer_dup = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer_dup.add_example(&s1);
+        trainer_dup.add_example(&s2);
+        trainer_dup.add_example(&s2);
+        trainer_dup.add_example(&s2);
+
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer.add_weighted_example(&s1, 1).unwrap();
+        trainer.add_weighted_example(&s2, 3).unwrap();
+        trainer.add_weighted_example(&s1, 0).unwrap();
+
+        // The order of examples does not matter.
+        let sorted_examples = |trainer: &Trainer| {
+            let mut examples: Vec<_> = trainer
+                .xs
+                .iter()
+                .zip(&trainer.ys)
+                .map(|(x, &y)| (x.clone(), y))
+                .collect();
+            examples.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            examples
+        };
+        assert_eq!(trainer_dup.feature_ids, trainer.feature_ids);
+        assert_eq!(sorted_examples(&trainer_dup), sorted_examples(&trainer));
+    }
+
+    #[test]
+    fn check_add_example_partial_annotation() {
+        let s = Sentence::from_partial_annotation("こ-れ|は テ-ス ト|で-す").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer.add_example(&s);
+
+        // Unknown boundaries are trained as the third class.
+        assert_eq!(vec![0., 1., 2., 0., 2., 1., 0.], trainer.ys);
+        assert_eq!(s.boundaries().len(), trainer.xs.len());
+    }
+
+    #[test]
+    fn check_confidences_partial_annotation() {
+        let s = Sentence::from_partial_annotation("こ-れ|は テ-ス ト|で-す").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer
+            .add_example_with_confidences(&s, &[1.; 7], ConfidenceMode::Threshold(0.5))
+            .unwrap();
+
+        // Unknown boundaries are not trained from confidences.
+        assert_eq!(vec![0., 1., 0., 1., 0.], trainer.ys);
+    }
+
+    #[test]
+    fn check_confidences_threshold() {
+        let s_conf = Sentence::from_tokenized("これ は テスト").unwrap();
+        let s_part = Sentence::from_partial_annotation("こ-れ|は テ-ス ト").unwrap();
+
+        let mut trainer_conf = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer_conf
+            .add_example_with_confidences(
+                &s_conf,
+                &[0.9, 0.8, 0.7, 1.0, 0.2],
+                ConfidenceMode::Threshold(0.8),
+            )
+            .unwrap();
+        // Boundaries below the threshold are ignored like unknown boundaries.
+        let mut trainer_part = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer_part
+            .add_example_with_confidences(&s_part, &[1.; 5], ConfidenceMode::Threshold(0.8))
+            .unwrap();
+
+        assert_eq!(trainer_part.feature_ids, trainer_conf.feature_ids);
+        assert_eq!(trainer_part.xs, trainer_conf.xs);
+        assert_eq!(trainer_part.ys, trainer_conf.ys);
+    }
+
+    #[test]
+    fn check_confidences_weight() {
+        let s = Sentence::from_tokenized("これ は テスト").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer
+            .add_example_with_confidences(&s, &[1.0, 0.5, 0.1, 0.7, 0.9], ConfidenceMode::Weight(3))
+            .unwrap();
+
+        // Weights are 3, 2, 0, 2, and 3, respectively.
+        assert_eq!(vec![0., 0., 0., 1., 1., 0., 0., 0., 0., 0.], trainer.ys);
+    }
+
+    #[test]
+    fn check_confidences_invalid() {
+        let s = Sentence::from_tokenized("これ は").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+
+        assert!(trainer
+            .add_example_with_confidences(&s, &[1.0], ConfidenceMode::Threshold(0.5))
+            .is_err());
+        assert!(trainer
+            .add_example_with_confidences(&s, &[1.0, 1.5], ConfidenceMode::Threshold(0.5))
+            .is_err());
+        assert!(trainer
+            .add_example_with_confidences(&s, &[1.0, f64::NAN], ConfidenceMode::Weight(2))
+            .is_err());
+        assert!(trainer
+            .add_example_with_confidences(
+                &s,
+                &[1.0, 1.0],
+                ConfidenceMode::Weight(MAX_EXAMPLE_WEIGHT + 1)
+            )
+            .is_err());
+        assert!(trainer
+            .add_weighted_example(&s, MAX_EXAMPLE_WEIGHT + 1)
+            .is_err());
+        assert!(trainer.ys.is_empty());
+    }
+
+    #[test]
+    fn check_confidences_tags() {
+        let s = Sentence::from_tokenized("これ/代名詞 は/助詞 テスト/名詞").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer
+            .add_example_with_confidences(
+                &s,
+                &[1.0, 1.0, 0.3, 1.0, 1.0],
+                ConfidenceMode::Threshold(0.5),
+            )
+            .unwrap();
+        let model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+
+        // Tags of tokens adjacent to the ignored boundary are not trained.
+        let tokens: Vec<_> = model.tag_models().iter().map(|m| &m.token).collect();
+        assert_eq!(vec!["これ"], tokens);
+    }
+
+    #[test]
+    fn check_train_tags() {
+        let sents = [
+            Sentence::from_tokenized("これ/代名詞 は/助詞 テスト/名詞").unwrap(),
+            Sentence::from_tokenized("これ/連体詞 テスト/名詞").unwrap(),
+        ];
+        let new_trainer = || {
+            let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+            for s in &sents {
+                trainer.add_example(s);
+            }
+            trainer
+        };
+
+        let model = new_trainer()
+            .train(0.01, 1., SolverType::L2RegularizedL2LossSVC)
+            .unwrap();
+        let tag_models = new_trainer()
+            .train_tags(0.01, 1., SolverType::L2RegularizedL2LossSVC)
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", model.tag_models()),
+            format!("{tag_models:?}"),
+        );
+        // Scores of trained columns are scaled back to the raw scores.
+        assert!(tag_models
+            .iter()
+            .any(|m| m.score_scales.iter().any(Option::is_some)));
+
+        // Column-specific hyperparameters take precedence.
+        let mut trainer = new_trainer();
+        trainer.set_tag_column_params(0, 0.01, 1., SolverType::L2RegularizedL2LossSVC);
+        let column_tag_models = trainer
+            .train_tags(0.1, 0.5, SolverType::L2RegularizedL2LossSVCDual)
+            .unwrap();
+        assert_eq!(format!("{tag_models:?}"), format!("{column_tag_models:?}"),);
+
+        let mut model = model;
+        model.replace_tag_models(tag_models).unwrap();
+        let tag_models = new_trainer()
+            .train_tags(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+        model.append_tag_columns(tag_models).unwrap();
+        assert_eq!(2, model.n_tag_columns());
+    }
+
+    #[test]
+    fn check_train_tag_tuples() {
+        let sents = [
+            Sentence::from_tokenized("これ/代名詞/コレ は/助詞/ワ").unwrap(),
+            Sentence::from_tokenized("これ/連体詞/コレ テスト/名詞/テスト").unwrap(),
+            Sentence::from_tokenized("これ/連体詞/コノ テスト/名詞/テスト").unwrap(),
+        ];
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        for s in &sents {
+            trainer.add_example(s);
+        }
+        let mut model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+        let tag_model = model
+            .tag_models()
+            .iter()
+            .find(|m| m.token == "これ")
+            .unwrap();
+        assert_eq!(
+            vec![
+                vec![Some(0), Some(0)],
+                vec![Some(1), Some(0)],
+                vec![Some(1), Some(1)],
+            ],
+            tag_model.tag_tuples,
+        );
+        // Single-candidate columns do not need to be restricted.
+        assert!(model
+            .tag_models()
+            .iter()
+            .filter(|m| m.token != "これ")
+            .all(|m| m.tag_tuples.is_empty()));
+
+        model.select_tag_columns(&[1, 1]).unwrap();
+        let tag_model = model
+            .tag_models()
+            .iter()
+            .find(|m| m.token == "これ")
+            .unwrap();
+        assert_eq!(
+            vec![vec![Some(0), Some(0)], vec![Some(1), Some(1)]],
+            tag_model.tag_tuples,
+        );
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn check_fallback_tags() {
+        let sents = [
+            Sentence::from_tokenized("テスト/名詞/テスト を/助詞/ヲ する/動詞/スル").unwrap(),
+            Sentence::from_tokenized("ケーキ/名詞/ケーキ を/助詞/ヲ 食べる/動詞/タベル").unwrap(),
+            Sentence::from_tokenized("パン/名詞/パン を/助詞/ヲ 焼く/動詞/ヤク").unwrap(),
+            Sentence::from_tokenized("ゲーム/名詞/ゲーム を/助詞/ヲ 作る/動詞/ツクル").unwrap(),
+        ];
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer.set_fallback_tag_columns(vec![0]).unwrap();
+        for s in &sents {
+            trainer.add_example(s);
+        }
+        assert!(trainer.set_fallback_tag_columns(vec![1]).is_err());
+        let model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+        assert!(model.tag_models()[0].is_fallback());
+
+        let predictor = crate::Predictor::new(model, true).unwrap();
+        let data = predictor.serialize_to_vec().unwrap();
+        let (predictor, _) =
+            unsafe { crate::Predictor::deserialize_from_slice_unchecked(&data).unwrap() };
+        let mut s = Sentence::from_raw("カメラを買う").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        let token = s.iter_tokens().next().unwrap();
+        assert_eq!("カメラ", token.surface());
+        // Surface-specific tags are not predicted by the fallback model.
+        assert_eq!(&[Some("名詞".into()), None], token.tags());
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn check_lemma_edit_script() {
+        use crate::model::{apply_lemma_edit_script, lemma_edit_script};
+
+        assert_eq!("-1+る", lemma_edit_script("食べた", "食べる"));
+        assert_eq!("-0+", lemma_edit_script("猫", "猫"));
+        assert_eq!("-2+く", lemma_edit_script("行った", "行く"));
+        for (surface, lemma) in [("食べた", "食べる"), ("猫", "猫"), ("行った", "行く")]
+        {
+            let script = lemma_edit_script(surface, lemma);
+            assert_eq!(
+                Some(lemma),
+                apply_lemma_edit_script(surface, &script).as_deref(),
+            );
+        }
+        assert_eq!(None, apply_lemma_edit_script("た", "-2+る"));
+        assert_eq!(None, apply_lemma_edit_script("た", "る"));
+    }
+
+    #[cfg(feature = "tag-prediction")]
+    #[test]
+    fn check_train_lemmas() {
+        let sents = [
+            Sentence::from_tokenized("パン/名詞/パン を/助詞/を 食べた/動詞/食べる").unwrap(),
+            Sentence::from_tokenized("テレビ/名詞/テレビ を/助詞/を 見た/動詞/見る").unwrap(),
+            Sentence::from_tokenized("ゴミ/名詞/ゴミ を/助詞/を 捨てた/動詞/捨てる").unwrap(),
+            Sentence::from_tokenized("服/名詞/服 を/助詞/を 着た/動詞/着る").unwrap(),
+        ];
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer
+            .set_tag_schema(vec![TagColumn::new("pos"), TagColumn::lemma("lemma")])
+            .unwrap();
+        trainer.set_fallback_tag_columns(vec![0, 1]).unwrap();
+        for s in &sents {
+            trainer.add_example(s);
+        }
+        assert!(trainer.set_tag_schema(vec![]).is_err());
+        let model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+        assert_eq!(crate::TagColumnKind::Lemma, model.tag_schema()[1].kind());
+
+        let mut predictor = crate::Predictor::new(model, true).unwrap();
+        predictor.store_tag_scores(true);
+        let mut s = Sentence::from_raw("パンを食べた").unwrap();
+        predictor.predict(&mut s);
+        s.fill_tags();
+        let lemmas: Vec<_> = s.iter_tokens().map(|t| t.lemma()).collect();
+        assert_eq!(vec![Some("パン"), Some("を"), Some("食べる")], lemmas);
+
+        // Candidates of the lemma column are edit scripts, and decoded into lemmas on request.
+        let token = s.iter_tokens().nth(2).unwrap();
+        let cands = token.raw_tag_candidates().unwrap();
+        assert_eq!("動詞", cands[0][0].0);
+        assert_eq!(vec![("-1+る", 0)], cands[1]);
+        let cands = token.decoded_tag_candidates().unwrap();
+        assert_eq!(vec![("食べる".into(), 0)], cands[1]);
+        #[cfg(feature = "std")]
+        assert_eq!("食べる", token.tag_probabilities().unwrap()[1][0].0);
+
+        // An unseen inflection is predicted by the fallback model.
+        let mut s = Sentence::from_tokenized("ゴミ を 寝た").unwrap();
+        predictor.predict(&mut s);
+        s.boundaries_mut().copy_from_slice(
+            Sentence::from_tokenized("ゴミ を 寝た")
+                .unwrap()
+                .boundaries(),
+        );
+        s.fill_tags();
+        let token = s.iter_tokens().nth(2).unwrap();
+        assert_eq!("寝た", token.surface());
+        assert_eq!(Some("寝る"), token.lemma());
+        assert_eq!(Some("寝る"), token.tag_by_name("lemma"));
+    }
+
+    #[test]
+    fn check_quantize_bit_depth() {
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        assert!(trainer.set_quantize_bit_depth(1).is_err());
+        assert!(trainer.set_quantize_bit_depth(25).is_err());
+        trainer.set_quantize_bit_depth(8).unwrap();
+        trainer.add_example(&s);
+        let model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+
+        assert_eq!(Some(8), model.quantize_bit_depth());
+        assert!((-127..=127).contains(&model.0.bias));
+        let mut weights = model
+            .0
+            .char_ngram_model
+            .0
+            .iter()
+            .flat_map(|d| &d.weights)
+            .chain(model.0.type_ngram_model.0.iter().flat_map(|d| &d.weights));
+        assert!(weights.all(|w| (-127..=127).contains(w)));
+
+        let mut trainer = Trainer::new(2, 2, 2, 2, vec![], 4, &[]).unwrap();
+        trainer.set_quantize_bit_depth(20).unwrap();
+        trainer.set_narrow_weights(true);
+        trainer.add_example(&s);
+        assert!(trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .is_err());
+    }
+
+    #[test]
+    fn check_reproducible_training() {
+        let sents = [
+            Sentence::from_tokenized("これ/代名詞/これ は/助詞/は テスト/名詞/てすと").unwrap(),
+            Sentence::from_tokenized("テスト/名詞/てすと です/助動詞/です").unwrap(),
+            Sentence::from_tokenized("これ/連体詞/これ も テスト/名詞/てすと").unwrap(),
+        ];
+        let train = |solver| {
+            let mut trainer =
+                Trainer::new(3, 3, 3, 3, vec!["これ".into(), "テスト".into()], 4, &[]).unwrap();
+            trainer.learn_dict_word_weights(1).unwrap();
+            for s in &sents {
+                trainer.add_weighted_example(s, 2).unwrap();
+            }
+            trainer.train(0.01, 1., solver).unwrap().to_vec().unwrap()
+        };
+
+        // These solvers do not shuffle examples, so models depend only on the order of features.
+        for solver in [
+            SolverType::L2RegularizedLogistic,
+            SolverType::L2RegularizedL2LossSVC,
+        ] {
+            let expected = train(solver);
+            // Trainings running in 4 threads at once must produce the same model as the training
+            // in a single thread.
+            let models: Vec<_> = std::thread::scope(|s| {
+                let handles: Vec<_> = (0..4).map(|_| s.spawn(|| train(solver))).collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+            for model in models {
+                assert_eq!(expected, model, "{solver:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn check_train_subset() {
+        let sents = [
+            Sentence::from_tokenized("これ/代名詞 は/助詞 テスト/名詞").unwrap(),
+            Sentence::from_tokenized("火星/名詞 の/助詞 猫/名詞").unwrap(),
+            Sentence::from_tokenized("これ/代名詞 は/助詞 猫/名詞").unwrap(),
+        ];
+        let mut trainer =
+            Trainer::new(2, 2, 2, 2, vec!["テスト".into(), "火星".into()], 4, &[]).unwrap();
+        trainer.learn_dict_word_weights(1).unwrap();
+        for s in &sents {
+            trainer.add_example(s);
+        }
+        assert_eq!(vec![1, 1], trainer.dicts[0].word_freqs(|_| true));
+        assert_eq!(vec![1, 0], trainer.dicts[0].word_freqs(|i| i != 1));
+
+        // Features only in the excluded sentence are not trained.
+        let model = trainer
+            .train_subset(0.01, 1., SolverType::L1RegularizedL2LossSVC, |i| i != 1)
+            .unwrap();
+        assert!(model
+            .0
+            .char_ngram_model
+            .0
+            .iter()
+            .all(|d| !d.ngram.contains(['火', '星', 'の'])));
+        assert!(model.0.tag_models.iter().all(|m| m.token != "火星"));
+        assert!(model.0.tag_models.iter().any(|m| m.token == "猫"));
+
+        let tag_models = trainer
+            .train_tags_subset(0.01, 1., SolverType::L1RegularizedL2LossSVC, |i| i == 0)
+            .unwrap();
+        let mut tokens: Vec<_> = tag_models.iter().map(|m| m.token.as_str()).collect();
+        tokens.sort_unstable();
+        assert_eq!(vec!["これ", "は", "テスト"], tokens);
+
+        // Training from all sentences is the same as train().
+        let model = trainer
+            .train_subset(0.01, 1., SolverType::L2RegularizedL2LossSVC, |_| true)
+            .unwrap();
+        assert_eq!(
+            trainer
+                .train(0.01, 1., SolverType::L2RegularizedL2LossSVC)
+                .unwrap()
+                .to_vec()
+                .unwrap(),
+            model.to_vec().unwrap(),
+        );
+    }
+
+    #[test]
+    fn check_train_different_window_sizes() {
+        // Type n-gram weights used to be sized by the character window.
+        let s = Sentence::from_tokenized("これ は テスト です").unwrap();
+        let mut trainer = Trainer::new(2, 2, 3, 3, vec![], 4, &[]).unwrap();
+        trainer.add_example(&s);
+        let model = trainer
+            .train(0.01, 1., SolverType::L1RegularizedL2LossSVC)
+            .unwrap();
+
+        let char_ngrams = &model.0.char_ngram_model.0;
+        let type_ngrams = &model.0.type_ngram_model.0;
+        assert!(!char_ngrams.is_empty());
+        assert!(!type_ngrams.is_empty());
+        for d in char_ngrams {
+            assert_eq!(2 * 2 - d.ngram.chars().count() + 1, d.weights.len());
+        }
+        for d in type_ngrams {
+            assert_eq!(2 * 3 - d.ngram.len() + 1, d.weights.len());
+        }
+
+        let predictor = crate::Predictor::new(model, false).unwrap();
+        let mut s = Sentence::from_raw("これはテストです").unwrap();
+        predictor.predict(&mut s);
+        assert_eq!(7, s.boundaries().len());
+    }
 }
@@ -98,6 +98,12 @@ pub enum TypeScorer {
 
     #[cfg(feature = "tag-prediction")]
     BoundaryTag(TypeScorerBoundaryTag),
+
+    // Variants are appended so that data serialized by older versions with the same features can
+    // still be decoded. Indices depend on the enabled features because `BoundaryTag` is
+    // cfg-gated.
+    BoundaryI16(TypeScorerBoundary<i16>),
+    BoundaryI8(TypeScorerBoundary<i8>),
 }
 
 impl TypeScorer {
@@ -117,7 +123,7 @@ impl TypeScorer {
                 0..=CACHE_MAX_WINDOW_SIZE => Ok(Some(Self::BoundaryCache(
                     TypeScorerBoundaryCache::new(ngram_model, window_size)?,
                 ))),
-                _ => Ok(Some(Self::Boundary(TypeScorerBoundary::new(
+                _ => Ok(Some(Self::narrow_boundary(TypeScorerBoundary::new(
                     ngram_model,
                     window_size,
                 )?))),
@@ -136,17 +142,30 @@ impl TypeScorer {
             0..=CACHE_MAX_WINDOW_SIZE => Ok(Some(Self::BoundaryCache(
                 TypeScorerBoundaryCache::new(ngram_model, window_size)?,
             ))),
-            _ => Ok(Some(Self::Boundary(TypeScorerBoundary::new(
+            _ => Ok(Some(Self::narrow_boundary(TypeScorerBoundary::new(
                 ngram_model,
                 window_size,
             )?))),
         }
     }
 
+    /// Stores weights in the narrowest type that can represent all of them.
+    fn narrow_boundary(scorer: TypeScorerBoundary) -> Self {
+        match scorer.try_narrow() {
+            Ok(scorer) => Self::BoundaryI8(scorer),
+            Err(scorer) => match scorer.try_narrow() {
+                Ok(scorer) => Self::BoundaryI16(scorer),
+                Err(scorer) => Self::Boundary(scorer),
+            },
+        }
+    }
+
     #[inline]
     pub fn add_scores(&self, sentence: &mut Sentence) {
         match self {
             Self::Boundary(scorer) => scorer.add_scores(sentence),
+            Self::BoundaryI16(scorer) => scorer.add_scores(sentence),
+            Self::BoundaryI8(scorer) => scorer.add_scores(sentence),
 
             #[cfg(feature = "cache-type-score")]
             Self::BoundaryCache(scorer) => scorer.add_scores(sentence),
@@ -176,6 +195,17 @@ impl TypeScorer {
     }
 }
 
+/// Returns the largest absolute weight stored by the boundary scorer, in which weights of n-grams
+/// sharing suffixes are summed up.
+#[cfg(feature = "train")]
+pub fn max_merged_weight(ngram_model: NgramModel<Vec<u8>>, window_size: u8) -> i32 {
+    boundary_scorer::merge_weights(ngram_model, window_size)
+        .iter()
+        .map(|(_, weight)| weight.max_abs())
+        .max()
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -10,19 +10,24 @@ use daachorse::DoubleArrayAhoCorasick;
 
 use crate::errors::{Result, VaporettoError};
 use crate::ngram_model::NgramModel;
-use crate::predictor::{PositionalWeight, WeightVector};
+use crate::predictor::{PositionalWeight, WeightElement, WeightVector};
 use crate::sentence::Sentence;
 use crate::type_scorer::TypeWeightMerger;
 
-pub struct TypeScorerBoundary {
+pub struct TypeScorerBoundary<T = i32> {
     pma: DoubleArrayAhoCorasick<u32>,
-    weights: Vec<PositionalWeight<WeightVector>>,
+    weights: Vec<PositionalWeight<WeightVector<T>>>,
 }
 
-impl<'de, Context> BorrowDecode<'de, Context> for TypeScorerBoundary {
+impl<'de, T, Context> BorrowDecode<'de, Context> for TypeScorerBoundary<T>
+where
+    T: WeightElement + Decode<Context>,
+{
     /// WARNING: This function is inherently unsafe. Do not publish this function outside this
     /// crate.
-    fn borrow_decode<D: BorrowDecoder<'de>>(decoder: &mut D) -> Result<Self, DecodeError> {
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
         let pma_data: &[u8] = BorrowDecode::borrow_decode(decoder)?;
         let (pma, _) = unsafe { DoubleArrayAhoCorasick::deserialize_unchecked(pma_data) };
         Ok(Self {
@@ -32,7 +37,10 @@ impl<'de, Context> BorrowDecode<'de, Context> for TypeScorerBoundary {
     }
 }
 
-impl Encode for TypeScorerBoundary {
+impl<T> Encode for TypeScorerBoundary<T>
+where
+    T: WeightElement + Encode,
+{
     fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
         let pma_data = self.pma.serialize();
         Encode::encode(&pma_data, encoder)?;
@@ -41,16 +49,24 @@ impl Encode for TypeScorerBoundary {
     }
 }
 
+/// Sums up weights of n-grams sharing suffixes.
+pub fn merge_weights(
+    ngram_model: NgramModel<Vec<u8>>,
+    window_size: u8,
+) -> Vec<(Vec<u8>, PositionalWeight<Vec<i32>>)> {
+    let mut merger = TypeWeightMerger::default();
+    for d in ngram_model.0 {
+        let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
+        merger.add(d.ngram, weight);
+    }
+    merger.merge()
+}
+
 impl TypeScorerBoundary {
     pub fn new(ngram_model: NgramModel<Vec<u8>>, window_size: u8) -> Result<Self> {
-        let mut merger = TypeWeightMerger::default();
-        for d in ngram_model.0 {
-            let weight = PositionalWeight::new(-i16::from(window_size), d.weights);
-            merger.add(d.ngram, weight);
-        }
         let mut ngrams = vec![];
         let mut weights = vec![];
-        for (ngram, weight) in merger.merge() {
+        for (ngram, weight) in merge_weights(ngram_model, window_size) {
             ngrams.push(ngram);
             weights.push(weight.into());
         }
@@ -59,6 +75,24 @@ impl TypeScorerBoundary {
         Ok(Self { pma, weights })
     }
 
+    /// Converts weights into a narrower type, or returns `self` if some weights do not fit in it.
+    pub fn try_narrow<T: WeightElement>(self) -> core::result::Result<TypeScorerBoundary<T>, Self> {
+        match self
+            .weights
+            .iter()
+            .map(PositionalWeight::try_narrow)
+            .collect::<Option<_>>()
+        {
+            Some(weights) => Ok(TypeScorerBoundary {
+                pma: self.pma,
+                weights,
+            }),
+            None => Err(self),
+        }
+    }
+}
+
+impl<T: WeightElement> TypeScorerBoundary<T> {
     #[allow(clippy::cast_possible_wrap)]
     #[inline(always)]
     pub fn add_scores(&self, sentence: &mut Sentence) {
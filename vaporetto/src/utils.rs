@@ -7,23 +7,21 @@ use alloc::vec::Vec;
 use std::io::{self, Read};
 
 use bincode::{
-    de::Decoder,
+    de::{BorrowDecoder, Decoder},
     enc::{write::Writer, Encoder},
     error::{DecodeError, EncodeError},
-    Decode, Encode,
+    BorrowDecode, Decode, Encode,
 };
 use hashbrown::{DefaultHashBuilder, HashMap};
 
 #[cfg(feature = "fix-weight-length")]
 #[inline(always)]
-pub const fn trim_end_zeros(mut w: &[i32]) -> &[i32] {
-    while let Some((&last, rest)) = w.split_last() {
-        if last != 0 {
-            break;
-        }
-        w = rest;
-    }
-    w
+pub fn trim_end_zeros<T: Default + PartialEq>(w: &[T]) -> &[T] {
+    let len = w
+        .iter()
+        .rposition(|x| *x != T::default())
+        .map_or(0, |i| i + 1);
+    &w[..len]
 }
 
 pub struct VecWriter(pub Vec<u8>);
@@ -70,6 +68,19 @@ where
     }
 }
 
+impl<'de, K, V, S, Context> BorrowDecode<'de, Context> for SerializableHashMap<K, V, S>
+where
+    K: Decode<Context> + Eq + Hash,
+    V: Decode<Context>,
+    S: BuildHasher + Default,
+{
+    fn borrow_decode<D: BorrowDecoder<'de, Context = Context>>(
+        decoder: &mut D,
+    ) -> Result<Self, DecodeError> {
+        Decode::decode(decoder)
+    }
+}
+
 impl<K, V, S> Encode for SerializableHashMap<K, V, S>
 where
     K: Encode,
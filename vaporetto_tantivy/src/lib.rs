@@ -51,7 +51,7 @@
 use std::sync::Arc;
 
 use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
-use vaporetto::{CharacterBoundary, CharacterType, Model, Predictor, Sentence};
+use vaporetto::{CharacterBoundary, CharacterType, Model, Predictor, Sentence, TagColumnKind};
 use vaporetto_rules::{
     sentence_filters::{ConcatGraphemeClustersFilter, KyteaWsConstFilter, SplitLinebreaksFilter},
     string_filters::KyteaFullwidthFilter,
@@ -64,6 +64,7 @@ pub struct VaporettoTokenizer {
     predictor: Arc<Predictor>,
     prefilter: KyteaFullwidthFilter,
     postfilters: Vec<Arc<dyn SentenceFilter>>,
+    lemmas: bool,
 }
 
 fn build_post_filters(
@@ -106,6 +107,49 @@ impl VaporettoTokenizer {
             predictor: Arc::new(Predictor::new(model, false)?),
             prefilter: KyteaFullwidthFilter,
             postfilters,
+            lemmas: false,
+        })
+    }
+
+    /// Creates a new VaporettoTokenizer that emits lemmas of tokens instead of their surfaces.
+    ///
+    /// Tokens whose lemmas are not predicted are emitted as they are. Offsets of tokens still
+    /// point to their surfaces in the original text.
+    ///
+    /// Lemmas are predicted from the text normalized by the prefilter, but the unchanged prefix of
+    /// each lemma is taken from the original text, so a lemma equal to its surface is emitted
+    /// in the same form as by the tokenizer created by [`VaporettoTokenizer::new()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - A model data of Vaporetto containing a lemma column.
+    /// * `wsconst` - Character types that the tokenizer does not segment.
+    ///   D: Digit, R: Roman, H: Hiragana, T: Katakana, K: Kanji, O: Other,
+    ///   G: Grapheme cluster.
+    ///
+    /// # Errors
+    ///
+    /// Error is returned when
+    ///   - the model is invalid,
+    ///   - the model does not have a lemma column, or
+    ///   - `wsconst` contains an invalid character type.
+    pub fn new_with_lemmas(
+        model: Model,
+        wsconst: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        if !model
+            .tag_schema()
+            .iter()
+            .any(|c| c.kind() == TagColumnKind::Lemma)
+        {
+            return Err("The model does not have a lemma column".into());
+        }
+        let postfilters = build_post_filters(wsconst)?;
+        Ok(Self {
+            predictor: Arc::new(Predictor::new(model, true)?),
+            prefilter: KyteaFullwidthFilter,
+            postfilters,
+            lemmas: true,
         })
     }
 
@@ -140,16 +184,39 @@ impl VaporettoTokenizer {
                 predictor: Arc::new(predictor),
                 prefilter: KyteaFullwidthFilter,
                 postfilters,
+                lemmas: false,
             },
             rest,
         ))
     }
 }
 
+/// Replaces the prefix of the lemma shared with the normalized surface by the corresponding
+/// characters of the original surface.
+///
+/// The prefilter converts each character into exactly one character, so both surfaces have the
+/// same number of characters.
+fn restore_lemma(original: &str, normalized: &str, lemma: &str) -> String {
+    let mut original_end = 0;
+    let mut lemma_start = 0;
+    for ((c, n), l) in original.chars().zip(normalized.chars()).zip(lemma.chars()) {
+        if n != l {
+            break;
+        }
+        original_end += c.len_utf8();
+        lemma_start += l.len_utf8();
+    }
+    let mut result = String::from(&original[..original_end]);
+    result.push_str(&lemma[lemma_start..]);
+    result
+}
+
 pub struct VaporettoTokenStream<'a> {
     text: &'a str,
     token: Token,
     boundary_pos: Vec<usize>,
+    // Lemmas of tokens. Empty if lemmas are not emitted.
+    lemmas: Vec<Option<String>>,
     offset_to: usize,
     position: usize,
 }
@@ -162,6 +229,7 @@ impl Tokenizer for VaporettoTokenizer {
             return VaporettoTokenStream {
                 text,
                 boundary_pos: vec![],
+                lemmas: vec![],
                 token: Token::default(),
                 offset_to: 0,
                 position: 0,
@@ -190,10 +258,28 @@ impl Tokenizer for VaporettoTokenizer {
         }
         boundary_pos.push(text.len());
 
+        let lemmas = if self.lemmas {
+            s.fill_tags();
+            let mut offset_from = 0;
+            s.iter_tokens()
+                .zip(&boundary_pos)
+                .map(|(token, &offset_to)| {
+                    let original = &text[offset_from..offset_to];
+                    offset_from = offset_to;
+                    token
+                        .lemma()
+                        .map(|lemma| restore_lemma(original, token.surface(), lemma))
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
         VaporettoTokenStream {
             text,
             token: Token::default(),
             boundary_pos,
+            lemmas,
             offset_to: 0,
             position: 0,
         }
@@ -207,9 +293,13 @@ impl TokenStream for VaporettoTokenStream<'_> {
             self.offset_to = self.boundary_pos[self.position];
             self.token.offset_to = self.offset_to;
             self.token.text.clear();
-            self.token
-                .text
-                .push_str(&self.text[self.token.offset_from..self.token.offset_to]);
+            if let Some(Some(lemma)) = self.lemmas.get(self.position) {
+                self.token.text.push_str(lemma);
+            } else {
+                self.token
+                    .text
+                    .push_str(&self.text[self.token.offset_from..self.token.offset_to]);
+            }
             self.token.position = self.position;
             self.token.position_length = self.boundary_pos.len();
             self.position += 1;
@@ -252,6 +342,23 @@ mod tests {
         tokens
     }
 
+    fn lemma_token_stream_helper(text: &str, wsconst: &str) -> Vec<Token> {
+        let mut f = Cursor::new(include_bytes!("../test_model/model_lemma.zst"));
+        let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut f).unwrap();
+        let mut buff = vec![];
+        decoder.read_to_end(&mut buff).unwrap();
+        let model = Model::read(&mut buff.as_slice()).unwrap();
+        let mut a =
+            TextAnalyzer::from(VaporettoTokenizer::new_with_lemmas(model, wsconst).unwrap());
+        let mut token_stream = a.token_stream(text);
+        let mut tokens: Vec<Token> = vec![];
+        let mut add_token = |token: &Token| {
+            tokens.push(token.clone());
+        };
+        token_stream.process(&mut add_token);
+        tokens
+    }
+
     #[test]
     fn test_tokenize_empty() {
         let tokens = token_stream_helper("", "");
@@ -489,4 +596,95 @@ mod tests {
         assert_eq!(token.position, 2);
         assert_eq!(token.position_length, 3);
     }
+
+    #[test]
+    fn test_new_with_lemmas_without_lemma_column() {
+        let mut f = Cursor::new(include_bytes!("../test_model/model.zst"));
+        let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut f).unwrap();
+        let mut buff = vec![];
+        decoder.read_to_end(&mut buff).unwrap();
+        let model = Model::read(&mut buff.as_slice()).unwrap();
+
+        assert!(VaporettoTokenizer::new_with_lemmas(model, "").is_err());
+    }
+
+    #[test]
+    fn test_tokenizer_lemmas() {
+        let tokens = lemma_token_stream_helper("東京に行った", "");
+
+        assert_eq!(tokens.len(), 4);
+
+        let token = &tokens[0];
+        assert_eq!(token.text, "東京");
+        assert_eq!(token.offset_from, 0);
+        assert_eq!(token.offset_to, 6);
+        assert_eq!(token.position, 0);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[1];
+        assert_eq!(token.text, "に");
+        assert_eq!(token.offset_from, 6);
+        assert_eq!(token.offset_to, 9);
+        assert_eq!(token.position, 1);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[2];
+        assert_eq!(token.text, "行く");
+        assert_eq!(token.offset_from, 9);
+        assert_eq!(token.offset_to, 15);
+        assert_eq!(token.position, 2);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[3];
+        assert_eq!(token.text, "た");
+        assert_eq!(token.offset_from, 15);
+        assert_eq!(token.offset_to, 18);
+        assert_eq!(token.position, 3);
+        assert_eq!(token.position_length, 4);
+    }
+
+    #[test]
+    fn test_tokenizer_lemmas_fullwidth() {
+        // "PC" is converted to "ＰＣ" by the prefilter, but the lemma is emitted in the original
+        // form so that it matches tokens of the surface tokenizer.
+        let tokens = lemma_token_stream_helper("PCを買った", "");
+
+        assert_eq!(tokens.len(), 4);
+
+        let token = &tokens[0];
+        assert_eq!(token.text, "PC");
+        assert_eq!(token.offset_from, 0);
+        assert_eq!(token.offset_to, 2);
+        assert_eq!(token.position, 0);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[1];
+        assert_eq!(token.text, "を");
+        assert_eq!(token.offset_from, 2);
+        assert_eq!(token.offset_to, 5);
+        assert_eq!(token.position, 1);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[2];
+        assert_eq!(token.text, "買う");
+        assert_eq!(token.offset_from, 5);
+        assert_eq!(token.offset_to, 11);
+        assert_eq!(token.position, 2);
+        assert_eq!(token.position_length, 4);
+
+        let token = &tokens[3];
+        assert_eq!(token.text, "た");
+        assert_eq!(token.offset_from, 11);
+        assert_eq!(token.offset_to, 14);
+        assert_eq!(token.position, 3);
+        assert_eq!(token.position_length, 4);
+    }
+
+    #[test]
+    fn test_restore_lemma() {
+        assert_eq!(restore_lemma("PC", "ＰＣ", "ＰＣ"), "PC");
+        assert_eq!(restore_lemma("walked", "ｗａｌｋｅｄ", "ｗａｌｋ"), "walk");
+        assert_eq!(restore_lemma("行った", "行った", "行く"), "行く");
+        assert_eq!(restore_lemma("Ab", "Ａｂ", "ａ"), "ａ");
+    }
 }